@@ -0,0 +1,494 @@
+//! Recognizes the audio formats that use `APEv2` tags, and reads the stream information from their headers.
+//!
+//! The tags themselves are read and written using the `ape` crate, which doesn't care what the audio is.
+//! Monkey's Audio is recognized by `infer`. `WavPack`, Musepack and TAK are not, so the matchers for them are here.
+
+use anyhow::{bail, Context, Result};
+use std::{fs::File, io::Read, path::Path};
+
+use crate::file_types::FileTypes;
+
+/// How much of the start of the file is read to find the stream information.
+const HEADER_LEN: u64 = 64 * 1024;
+
+/// The `WavPack` sample rates, indexed by bits 23-26 of the block header flags. Index 15 means a custom rate.
+const WAVPACK_SAMPLE_RATES: [u32; 15] = [
+    6000, 8000, 9600, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 64000, 88200, 96000,
+    192_000,
+];
+
+/// The Musepack sample rates, indexed by the sample frequency field in the stream header.
+const MUSEPACK_SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// The number of samples in a Musepack SV7 frame.
+const MUSEPACK_FRAME_SAMPLES: u64 = 1152;
+
+/// The stream information from the header of a Monkey's Audio, `WavPack`, Musepack or TAK file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApeStreamInfo {
+    /// The audio format, eg. `FileTypes::WavPack`.
+    pub format: FileTypes,
+
+    /// The number of channels.
+    pub channels: u16,
+
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+
+    /// The number of bits per sample. Musepack is lossy and doesn't have one.
+    pub bits_per_sample: Option<u16>,
+
+    /// The duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl ApeStreamInfo {
+    /// Reads the stream information from the start of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, isn't one of the formats above, or the header is cut short.
+    pub fn read_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut data = Vec::new();
+        file.take(HEADER_LEN).read_to_end(&mut data)?;
+
+        let info = if data.starts_with(b"MAC ") {
+            monkeys_audio_info(&data)
+        } else if is_wavpack(&data) {
+            wavpack_info(&data)
+        } else if is_musepack(&data) {
+            musepack_info(&data)
+        } else if is_tak(&data) {
+            tak_info(&data)
+        } else {
+            bail!("Not a Monkey's Audio, WavPack, Musepack or TAK file.");
+        };
+
+        info.with_context(|| {
+            format!(
+                "Unable to read the stream information in {}",
+                path.display()
+            )
+        })
+    }
+}
+
+/// Checks for the `wvpk` ID of a `WavPack` block.
+pub(crate) fn is_wavpack(buf: &[u8]) -> bool {
+    buf.starts_with(b"wvpk")
+}
+
+/// Checks for the `MPCK` (SV8) or `MP+` (SV7) ID of a Musepack file.
+pub(crate) fn is_musepack(buf: &[u8]) -> bool {
+    buf.starts_with(b"MPCK")
+        || (buf.starts_with(b"MP+") && buf.get(3).is_some_and(|v| v & 0x0f == 7))
+}
+
+/// Checks for the `tBaK` ID of a TAK file.
+pub(crate) fn is_tak(buf: &[u8]) -> bool {
+    buf.starts_with(b"tBaK")
+}
+
+/// Gets the stream information from the Monkey's Audio header. Files from version 3.98 on have a descriptor
+/// in front of the header, while older files have the header straight after the ID.
+fn monkeys_audio_info(data: &[u8]) -> Result<ApeStreamInfo> {
+    let version = le_u16(data, 4)?;
+
+    let (
+        channels,
+        sample_rate,
+        bits_per_sample,
+        blocks_per_frame,
+        final_frame_blocks,
+        total_frames,
+    ) = if version >= 3980 {
+        let header = le_u32(data, 8)? as usize;
+        (
+            le_u16(data, header + 18)?,
+            le_u32(data, header + 20)?,
+            le_u16(data, header + 16)?,
+            le_u32(data, header + 4)?,
+            le_u32(data, header + 8)?,
+            le_u32(data, header + 12)?,
+        )
+    } else {
+        let compression = le_u16(data, 6)?;
+        let flags = le_u16(data, 8)?;
+        let bits_per_sample = if flags & 0x01 != 0 {
+            8
+        } else if flags & 0x08 != 0 {
+            24
+        } else {
+            16
+        };
+        let blocks_per_frame = if version >= 3950 {
+            73728 * 4
+        } else if version >= 3900 || (version >= 3800 && compression == 4000) {
+            73728
+        } else {
+            9216
+        };
+        (
+            le_u16(data, 10)?,
+            le_u32(data, 12)?,
+            bits_per_sample,
+            blocks_per_frame,
+            le_u32(data, 28)?,
+            le_u32(data, 24)?,
+        )
+    };
+
+    let samples = u64::from(total_frames.saturating_sub(1)) * u64::from(blocks_per_frame)
+        + if total_frames > 0 {
+            u64::from(final_frame_blocks)
+        } else {
+            0
+        };
+
+    Ok(ApeStreamInfo {
+        format: FileTypes::Ape,
+        channels,
+        sample_rate,
+        bits_per_sample: Some(bits_per_sample),
+        duration_ms: duration_ms(samples, sample_rate),
+    })
+}
+
+/// Gets the stream information from the first `WavPack` block. The header has the sample count and most of the format
+/// in its flags, while the channel count and any non-standard sample rate are in the metadata sub-blocks after it.
+fn wavpack_info(data: &[u8]) -> Result<ApeStreamInfo> {
+    let block_size = le_u32(data, 4)? as usize;
+    let total_samples_high = *data.get(11).context("The header is too short.")?;
+    let total_samples = le_u32(data, 12)?;
+    let flags = le_u32(data, 24)?;
+
+    let bits_per_sample = if flags & 0x80 == 0 {
+        ((flags & 0x03) as u16 + 1) * 8
+    } else {
+        32
+    };
+    let mut channels = if flags & 0x04 == 0 { 2 } else { 1 };
+    let mut sample_rate = WAVPACK_SAMPLE_RATES
+        .get(((flags >> 23) & 0x0f) as usize)
+        .copied()
+        .unwrap_or_default();
+
+    // The metadata sub-blocks. The size is in 16-bit words, and the last byte is padding if the size is odd.
+    let end = data.len().min(block_size + 8);
+    let mut position = 32;
+    while position + 2 <= end {
+        let id = data[position];
+        let (header_len, size) = if id & 0x80 == 0 {
+            (2, usize::from(data[position + 1]) * 2)
+        } else {
+            let size = data.get(position + 1..position + 4).unwrap_or_default();
+            let size = size
+                .iter()
+                .rev()
+                .fold(0, |acc, b| (acc << 8) | usize::from(*b));
+            (4, size * 2)
+        };
+        let len = size - usize::from(id & 0x40 != 0 && size > 0);
+        let Some(body) = data.get(position + header_len..position + header_len + len) else {
+            break;
+        };
+
+        match id & 0x3f {
+            // ID_CHANNEL_INFO
+            0x0d if !body.is_empty() => channels = u16::from(body[0]),
+            // ID_SAMPLE_RATE
+            0x27 if body.len() >= 3 => {
+                sample_rate = body
+                    .iter()
+                    .take(4)
+                    .rev()
+                    .fold(0, |acc, b| (acc << 8) | u32::from(*b));
+            }
+            _ => {}
+        }
+        position += header_len + size;
+    }
+
+    // The sample count has an extra high byte from version 4.10. All ones means the count is unknown.
+    let samples = if total_samples == u32::MAX {
+        0
+    } else {
+        (u64::from(total_samples) + (u64::from(total_samples_high) << 32))
+            - u64::from(total_samples_high)
+    };
+
+    Ok(ApeStreamInfo {
+        format: FileTypes::WavPack,
+        channels,
+        sample_rate,
+        bits_per_sample: Some(bits_per_sample),
+        duration_ms: duration_ms(samples, sample_rate),
+    })
+}
+
+/// Gets the stream information from a Musepack file. SV8 files have a stream header (`SH`) packet,
+/// and SV7 files have a fixed header with the number of frames.
+fn musepack_info(data: &[u8]) -> Result<ApeStreamInfo> {
+    if data.starts_with(b"MP+") {
+        let frames = le_u32(data, 4)?;
+        let flags = le_u32(data, 8)?;
+        let sample_rate = MUSEPACK_SAMPLE_RATES[((flags >> 16) & 0x03) as usize];
+
+        return Ok(ApeStreamInfo {
+            format: FileTypes::Musepack,
+            channels: 2,
+            sample_rate,
+            bits_per_sample: None,
+            duration_ms: duration_ms(u64::from(frames) * MUSEPACK_FRAME_SAMPLES, sample_rate),
+        });
+    }
+
+    // Each packet has a 2-byte key and a size that includes the key and the size itself
+    let mut position = 4;
+    while position + 2 < data.len() {
+        let key = &data[position..position + 2];
+        let (size, size_len) = varint(data, position + 2)?;
+        if key == b"SH" {
+            let mut body = position + 2 + size_len + 5; // Skip the CRC and the stream version
+            let (samples, len) = varint(data, body)?;
+            body += len;
+            let (silence, len) = varint(data, body)?;
+            body += len;
+            let Some(fields) = data.get(body..body + 2) else {
+                bail!("The stream header is too short.");
+            };
+            let sample_rate = MUSEPACK_SAMPLE_RATES[usize::from(fields[0] >> 5) & 0x03];
+
+            return Ok(ApeStreamInfo {
+                format: FileTypes::Musepack,
+                channels: u16::from(fields[1] >> 4) + 1,
+                sample_rate,
+                bits_per_sample: None,
+                duration_ms: duration_ms(samples.saturating_sub(silence), sample_rate),
+            });
+        }
+
+        if size == 0 || key == b"AP" || key == b"SE" {
+            break;
+        }
+        position += usize::try_from(size)?;
+    }
+
+    bail!("No stream header found.");
+}
+
+/// Gets the stream information from the `STREAMINFO` metadata block of a TAK file.
+/// The fields are packed as bits, starting with the least significant bit of the first byte.
+fn tak_info(data: &[u8]) -> Result<ApeStreamInfo> {
+    let mut position = 4;
+    while position + 4 <= data.len() {
+        let block_type = data[position] & 0x7f;
+        let size = data[position + 1..position + 4]
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | usize::from(*b));
+
+        match block_type {
+            0 => break,
+            1 => {
+                let Some(block) = data.get(position + 4..position + 14) else {
+                    bail!("The STREAMINFO block is too short.");
+                };
+                let mut bytes = [0_u8; 16];
+                bytes[..10].copy_from_slice(block);
+                let mut bits = u128::from_le_bytes(bytes);
+                let mut take = |count: u32| -> u64 {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let value = (bits & ((1 << count) - 1)) as u64;
+                    bits >>= count;
+                    value
+                };
+
+                // Codec and profile, then the frame size
+                take(6 + 4 + 4);
+                let samples = take(35);
+                take(3); // Data type
+                #[allow(clippy::cast_possible_truncation)]
+                let sample_rate = take(18) as u32 + 6000;
+                #[allow(clippy::cast_possible_truncation)]
+                let bits_per_sample = take(5) as u16 + 8;
+                #[allow(clippy::cast_possible_truncation)]
+                let channels = take(4) as u16 + 1;
+
+                return Ok(ApeStreamInfo {
+                    format: FileTypes::Tak,
+                    channels,
+                    sample_rate,
+                    bits_per_sample: Some(bits_per_sample),
+                    duration_ms: duration_ms(samples, sample_rate),
+                });
+            }
+            _ => position += 4 + size,
+        }
+    }
+
+    bail!("No STREAMINFO block found.");
+}
+
+/// Converts a number of samples to milliseconds. Gives 0 if the sample rate is unknown.
+fn duration_ms(samples: u64, sample_rate: u32) -> u64 {
+    (samples * 1000)
+        .checked_div(u64::from(sample_rate))
+        .unwrap_or_default()
+}
+
+/// Reads a little-endian `u16` at `offset`.
+fn le_u16(data: &[u8], offset: usize) -> Result<u16> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
+        None => bail!("The header is too short."),
+    }
+}
+
+/// Reads a little-endian `u32` at `offset`.
+fn le_u32(data: &[u8], offset: usize) -> Result<u32> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => bail!("The header is too short."),
+    }
+}
+
+/// Reads a Musepack variable-length number at `offset`: 7 bits per byte, most significant first,
+/// with the top bit set on all but the last byte. Returns the number and the number of bytes used.
+fn varint(data: &[u8], offset: usize) -> Result<(u64, usize)> {
+    let mut value = 0_u64;
+    for (i, byte) in data.iter().skip(offset).take(9).enumerate() {
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+
+    bail!("The header is too short.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `WavPack` block header with the given flags and sample count, followed by the sub-blocks.
+    fn wavpack_block(flags: u32, samples: u32, sub_blocks: &[u8]) -> Vec<u8> {
+        let mut data = b"wvpk".to_vec();
+        data.extend_from_slice(&(24 + u32::try_from(sub_blocks.len()).unwrap()).to_le_bytes());
+        data.extend_from_slice(&[0x10, 0x04, 0, 0]);
+        data.extend_from_slice(&samples.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(&samples.to_le_bytes());
+        data.extend_from_slice(&flags.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(sub_blocks);
+        data
+    }
+
+    #[test]
+    /// Tests the matchers used to recognize the files.
+    fn test_matchers() {
+        assert!(is_wavpack(b"wvpk\x00\x10"));
+        assert!(is_musepack(b"MPCKSH"));
+        assert!(is_musepack(b"MP+\x17"));
+        assert!(!is_musepack(b"MP+\x16"));
+        assert!(is_tak(b"tBaK\x01"));
+        assert!(!is_tak(b"MAC "));
+    }
+
+    #[test]
+    /// Tests reading a 16-bit stereo `WavPack` header, and a 24-bit one with a custom rate and six channels.
+    fn test_wavpack_info() {
+        // 2 bytes per sample, stereo, 44.1 kHz
+        let info = wavpack_info(&wavpack_block(0x01 | (9 << 23), 441_000, &[])).unwrap();
+        assert_eq!(info.format, FileTypes::WavPack);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.bits_per_sample, Some(16));
+        assert_eq!(info.duration_ms, 10_000);
+
+        // ID_CHANNEL_INFO (2 bytes) and an odd-sized ID_SAMPLE_RATE (3 bytes)
+        let sub_blocks = [0x0d, 0x01, 0x06, 0x3f, 0x67, 0x02, 0x50, 0xc3, 0x00, 0x00];
+        let info = wavpack_info(&wavpack_block(0x02 | (15 << 23), 500_000, &sub_blocks)).unwrap();
+        assert_eq!(info.channels, 6);
+        assert_eq!(info.sample_rate, 50000);
+        assert_eq!(info.bits_per_sample, Some(24));
+        assert_eq!(info.duration_ms, 10_000);
+    }
+
+    #[test]
+    /// Tests reading a Musepack SV8 stream header with 10 seconds of 48 kHz audio.
+    fn test_musepack_info() {
+        let mut data = b"MPCK".to_vec();
+        // Key, size, CRC, version, 480 000 samples, no silence, 48 kHz, stereo
+        data.extend_from_slice(b"SH\x0e\0\0\0\0\x08\x9d\xa6\x00\0\x3f\x1b");
+        data.extend_from_slice(b"AP\x03");
+
+        let info = musepack_info(&data).unwrap();
+        assert_eq!(info.format, FileTypes::Musepack);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 48000);
+        assert_eq!(info.bits_per_sample, None);
+        assert_eq!(info.duration_ms, 10_000);
+    }
+
+    #[test]
+    /// Tests reading a TAK `STREAMINFO` block with 10 seconds of 16-bit stereo audio at 44.1 kHz.
+    fn test_tak_info() {
+        let fields: [(u128, u32); 8] = [
+            (2, 6),
+            (0, 4),
+            (0, 4),
+            (441_000, 35),
+            (0, 3),
+            (44100 - 6000, 18),
+            (16 - 8, 5),
+            (2 - 1, 4),
+        ];
+        let (mut bits, mut shift) = (0_u128, 0);
+        for (value, count) in fields {
+            bits |= value << shift;
+            shift += count;
+        }
+
+        let mut data = b"tBaK\x01\x0a\0\0".to_vec();
+        data.extend_from_slice(&bits.to_le_bytes()[..10]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+
+        let info = tak_info(&data).unwrap();
+        assert_eq!(info.format, FileTypes::Tak);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.bits_per_sample, Some(16));
+        assert_eq!(info.duration_ms, 10_000);
+    }
+
+    #[test]
+    /// Tests reading a Monkey's Audio 3.99 header.
+    fn test_monkeys_audio_info() {
+        let mut data = b"MAC ".to_vec();
+        data.extend_from_slice(&3990_u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&52_u32.to_le_bytes());
+        data.resize(52, 0);
+        // Compression, flags, blocks per frame, final frame blocks, total frames, bits, channels, rate
+        data.extend_from_slice(&2000_u16.to_le_bytes());
+        data.extend_from_slice(&0_u16.to_le_bytes());
+        data.extend_from_slice(&73728_u32.to_le_bytes());
+        data.extend_from_slice(&73728_u32.to_le_bytes());
+        data.extend_from_slice(&6_u32.to_le_bytes());
+        data.extend_from_slice(&24_u16.to_le_bytes());
+        data.extend_from_slice(&2_u16.to_le_bytes());
+        data.extend_from_slice(&44100_u32.to_le_bytes());
+
+        let info = monkeys_audio_info(&data).unwrap();
+        assert_eq!(info.format, FileTypes::Ape);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.bits_per_sample, Some(24));
+        assert_eq!(info.duration_ms, 10_031);
+    }
+}
@@ -0,0 +1,516 @@
+//! Reads the stream information from DSDIFF (`.dff`) files, and reads and writes the `ID3 ` chunk and the
+//! artist (`DIAR`) and title (`DITI`) in the `DIIN` chunk.
+//!
+//! The `ID3 ` chunk isn't part of the DSDIFF specification, but is what most players and taggers use. It is kept here
+//! as the raw tag, and read and written using the `id3` crate by the applications. When the file is saved, the other
+//! chunks are copied to a new file as-is, so the audio data is never held in memory or changed.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::shared;
+
+/// The length of a chunk header: a 4-byte ID and an 8-byte size.
+const CHUNK_HEADER_LEN: u64 = 12;
+
+/// How the audio in the file is stored.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DffCompression {
+    /// Uncompressed DSD in a `DSD ` chunk.
+    #[default]
+    Dsd,
+
+    /// DST compressed DSD in a `DST ` chunk.
+    Dst,
+}
+
+impl fmt::Display for DffCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dsd => write!(f, "DSD"),
+            Self::Dst => write!(f, "DST"),
+        }
+    }
+}
+
+/// The stream information from the `PROP` chunk, and the duration from the sound data chunk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DffStreamInfo {
+    /// The number of channels.
+    pub channels: u16,
+
+    /// The sample rate in Hz, eg. 2 822 400 for DSD64.
+    pub sample_rate: u32,
+
+    /// Whether the audio is plain DSD or DST compressed.
+    pub compression: DffCompression,
+
+    /// The duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// The stream information, ID3 tag, artist and title of a DSDIFF file.
+#[derive(Debug, Clone)]
+pub struct DffFile {
+    path: PathBuf,
+    info: DffStreamInfo,
+    id3: Option<Vec<u8>>,
+    artist: Option<String>,
+    title: Option<String>,
+}
+
+impl DffFile {
+    /// Reads the stream information, the `ID3 ` chunk and the `DIIN` artist and title from the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a DSDIFF file.
+    pub fn read_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Unable to read {}", path.display()))?,
+        );
+        let chunks = read_chunks(&mut reader)
+            .with_context(|| format!("Unable to read the chunks in {}", path.display()))?;
+
+        let chunk_data = |reader: &mut BufReader<File>, id: &[u8; 4]| -> Result<Option<Vec<u8>>> {
+            chunks
+                .iter()
+                .find(|c| &c.id == id)
+                .map(|c| c.read_data(reader))
+                .transpose()
+        };
+
+        let Some(prop) = chunk_data(&mut reader, b"PROP")? else {
+            bail!("No PROP chunk found in {}", path.display());
+        };
+        let mut info = read_properties(&prop)?;
+
+        if let Some(chunk) = chunks.iter().find(|c| &c.id == b"DSD ") {
+            let bits = chunk.size * 8;
+            info.duration_ms = (bits * 1000)
+                .checked_div(u64::from(info.channels) * u64::from(info.sample_rate))
+                .unwrap_or_default();
+        } else if let Some(dst) = chunk_data(&mut reader, b"DST ")? {
+            info.compression = DffCompression::Dst;
+            info.duration_ms = dst_duration_ms(&dst);
+        }
+
+        let mut artist = None;
+        let mut title = None;
+        if let Some(diin) = chunk_data(&mut reader, b"DIIN")? {
+            for (id, data) in sub_chunks(&diin) {
+                match &id {
+                    b"DIAR" => artist = Some(decode_text(data)),
+                    b"DITI" => title = Some(decode_text(data)),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            info,
+            id3: chunk_data(&mut reader, b"ID3 ")?,
+            artist,
+            title,
+        })
+    }
+
+    /// The stream information, eg. channels and duration.
+    #[must_use]
+    pub const fn stream_info(&self) -> &DffStreamInfo {
+        &self.info
+    }
+
+    /// The contents of the `ID3 ` chunk, ie. a complete ID3v2 tag, if there is one.
+    #[must_use]
+    pub fn id3_data(&self) -> Option<&[u8]> {
+        self.id3.as_deref()
+    }
+
+    /// Sets the contents of the `ID3 ` chunk. The chunk is removed when the file is saved if this is `None`.
+    pub fn set_id3_data(&mut self, data: Option<Vec<u8>>) {
+        self.id3 = data.filter(|d| !d.is_empty());
+    }
+
+    /// The artist from the `DIAR` chunk.
+    #[must_use]
+    pub fn artist(&self) -> Option<&str> {
+        self.artist.as_deref()
+    }
+
+    /// Sets the artist in the `DIAR` chunk, or removes it if this is `None`.
+    pub fn set_artist(&mut self, artist: Option<&str>) {
+        self.artist = artist.filter(|a| !a.is_empty()).map(ToString::to_string);
+    }
+
+    /// The title from the `DITI` chunk.
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Sets the title in the `DITI` chunk, or removes it if this is `None`.
+    pub fn set_title(&mut self, title: Option<&str>) {
+        self.title = title.filter(|t| !t.is_empty()).map(ToString::to_string);
+    }
+
+    /// Writes the `ID3 ` and `DIIN` chunks back to the file the values were read from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self) -> Result<()> {
+        self.write_to_path(&self.path)
+    }
+
+    /// Writes the file the values were read from to `path`, with the `ID3 ` chunk and the artist and title in the
+    /// `DIIN` chunk replaced by the current values. The chunks are kept where they were, or added at the end of the
+    /// file, and are left out if they are empty. Any other chunks in `DIIN`, eg. markers, are kept.
+    /// The new file is written to a temporary file first, so the file is left as it was if the write fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or written.
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(&self.path)
+                .with_context(|| format!("Unable to read {}", self.path.display()))?,
+        );
+        let chunks = read_chunks(&mut reader)?;
+
+        shared::write_file_atomically(path, |writer| {
+            self.write_chunks(&mut reader, &chunks, writer)
+        })
+    }
+
+    /// Writes the chunks to the new file, with the `DIIN` and `ID3 ` chunks replaced by the current values.
+    fn write_chunks(
+        &self,
+        reader: &mut (impl Read + Seek),
+        chunks: &[Chunk],
+        writer: &mut (impl Write + Seek),
+    ) -> Result<()> {
+        writer.write_all(b"FRM8\0\0\0\0\0\0\0\0DSD ")?;
+
+        let mut diin_written = false;
+        let mut id3_written = false;
+        for chunk in chunks {
+            match &chunk.id {
+                b"DIIN" => {
+                    if !diin_written {
+                        let old = chunk.read_data(reader)?;
+                        writer.write_all(&self.encode_diin(&old))?;
+                        diin_written = true;
+                    }
+                }
+                b"ID3 " => {
+                    if !id3_written {
+                        writer.write_all(&encode_chunk(b"ID3 ", self.id3.as_deref()))?;
+                        id3_written = true;
+                    }
+                }
+                _ => {
+                    reader.seek(SeekFrom::Start(chunk.offset - CHUNK_HEADER_LEN))?;
+                    io::copy(&mut reader.by_ref().take(chunk.len()), writer)?;
+                }
+            }
+        }
+        if !diin_written {
+            writer.write_all(&self.encode_diin(&[]))?;
+        }
+        if !id3_written {
+            writer.write_all(&encode_chunk(b"ID3 ", self.id3.as_deref()))?;
+        }
+
+        // The size of the FRM8 chunk is everything after its header
+        let form_size = writer.stream_position()? - CHUNK_HEADER_LEN;
+        writer.seek(SeekFrom::Start(4))?;
+        writer.write_all(&form_size.to_be_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Builds the `DIIN` chunk from the old chunk data, with the `DIAR` and `DITI` chunks replaced.
+    /// Returns nothing if the chunk would be empty.
+    fn encode_diin(&self, old: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (id, chunk) in sub_chunks(old) {
+            if &id != b"DIAR" && &id != b"DITI" {
+                data.extend(encode_chunk(&id, Some(chunk)));
+            }
+        }
+        for (id, text) in [(b"DIAR", &self.artist), (b"DITI", &self.title)] {
+            if let Some(text) = text {
+                data.extend(encode_chunk(id, Some(&encode_text(text))));
+            }
+        }
+
+        if data.is_empty() {
+            Vec::new()
+        } else {
+            encode_chunk(b"DIIN", Some(&data))
+        }
+    }
+}
+
+/// A chunk in the file.
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// The chunk ID, eg. `PROP`.
+    id: [u8; 4],
+
+    /// Where the chunk data starts in the file, after the header.
+    offset: u64,
+
+    /// The size of the chunk data, not including the pad byte.
+    size: u64,
+}
+
+impl Chunk {
+    /// Reads the chunk data. The data is cut short if the file is.
+    fn read_data(&self, reader: &mut (impl Read + Seek)) -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(self.offset))?;
+        let mut data = Vec::new();
+        reader.take(self.size).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// The length of the chunk in the file, including the header and the pad byte.
+    const fn len(&self) -> u64 {
+        CHUNK_HEADER_LEN + self.size + (self.size & 1)
+    }
+}
+
+/// Checks for the `FRM8` chunk of type `DSD ` that a DSDIFF file starts with.
+pub(crate) fn is_dff(buf: &[u8]) -> bool {
+    buf.len() >= 16 && &buf[..4] == b"FRM8" && &buf[12..16] == b"DSD "
+}
+
+/// Reads the chunk headers of a DSDIFF file.
+fn read_chunks(reader: &mut (impl Read + Seek)) -> Result<Vec<Chunk>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut header = [0_u8; 16];
+    reader
+        .read_exact(&mut header)
+        .context("The file is too short.")?;
+    if !is_dff(&header) {
+        bail!("Not a DSDIFF file.");
+    }
+
+    // Ignore anything after the end of the root chunk
+    let form_size = u64::from_be_bytes(header[4..12].try_into()?);
+    let end = file_len.min(CHUNK_HEADER_LEN.saturating_add(form_size));
+
+    let mut chunks = Vec::new();
+    let mut position = 16;
+    while position + CHUNK_HEADER_LEN <= end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_header = [0_u8; 12];
+        reader.read_exact(&mut chunk_header)?;
+
+        let mut id = [0_u8; 4];
+        id.copy_from_slice(&chunk_header[..4]);
+        let chunk = Chunk {
+            id,
+            offset: position + CHUNK_HEADER_LEN,
+            size: u64::from_be_bytes(chunk_header[4..].try_into()?),
+        };
+        position = position.saturating_add(chunk.len());
+        chunks.push(chunk);
+    }
+
+    Ok(chunks)
+}
+
+/// Splits the data of a container chunk (`PROP`, `DIIN`, `DST `) into its sub-chunks.
+fn sub_chunks(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut position = 0;
+    while position + 12 <= data.len() {
+        let mut id = [0_u8; 4];
+        id.copy_from_slice(&data[position..position + 4]);
+        let mut size = [0_u8; 8];
+        size.copy_from_slice(&data[position + 4..position + 12]);
+        let size = usize::try_from(u64::from_be_bytes(size)).unwrap_or(usize::MAX);
+
+        let start = position + 12;
+        let end = data.len().min(start.saturating_add(size));
+        chunks.push((id, &data[start..end]));
+
+        position = end + (size & 1);
+    }
+    chunks
+}
+
+/// Gets the sample rate and the number of channels from the `PROP` chunk.
+fn read_properties(prop: &[u8]) -> Result<DffStreamInfo> {
+    if !prop.starts_with(b"SND ") {
+        bail!("The PROP chunk isn't of type SND.");
+    }
+
+    let mut info = DffStreamInfo::default();
+    for (id, data) in sub_chunks(&prop[4..]) {
+        match &id {
+            b"FS  " if data.len() >= 4 => {
+                info.sample_rate = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            }
+            b"CHNL" if data.len() >= 2 => info.channels = u16::from_be_bytes([data[0], data[1]]),
+            _ => {}
+        }
+    }
+
+    if info.sample_rate == 0 || info.channels == 0 {
+        bail!("The PROP chunk doesn't have a sample rate and channel count.");
+    }
+    Ok(info)
+}
+
+/// Gets the duration of DST compressed audio from the number of frames and the frame rate in the `FRTE` chunk.
+fn dst_duration_ms(dst: &[u8]) -> u64 {
+    sub_chunks(dst)
+        .into_iter()
+        .find(|(id, data)| id == b"FRTE" && data.len() >= 6)
+        .and_then(|(_, data)| {
+            let frames = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            let frame_rate = u16::from_be_bytes([data[4], data[5]]);
+            (u64::from(frames) * 1000).checked_div(u64::from(frame_rate))
+        })
+        .unwrap_or_default()
+}
+
+/// Decodes the text in a `DIAR` or `DITI` chunk: a 4-byte length and the text.
+/// The text is UTF-8, or Latin-1 if it isn't valid UTF-8.
+fn decode_text(data: &[u8]) -> String {
+    let Some(length) = data.get(..4) else {
+        return String::new();
+    };
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let bytes = &data[4..data.len().min(4 + length)];
+
+    String::from_utf8(bytes.to_vec())
+        .unwrap_or_else(|_| bytes.iter().map(|b| char::from(*b)).collect())
+}
+
+/// Encodes the text for a `DIAR` or `DITI` chunk.
+fn encode_text(text: &str) -> Vec<u8> {
+    let mut data = u32::try_from(text.len())
+        .unwrap_or(u32::MAX)
+        .to_be_bytes()
+        .to_vec();
+    data.extend_from_slice(text.as_bytes());
+    data
+}
+
+/// Encodes a chunk, including the header and the pad byte. Returns nothing if there is no data.
+fn encode_chunk(id: &[u8; 4], data: Option<&[u8]>) -> Vec<u8> {
+    let Some(data) = data else {
+        return Vec::new();
+    };
+
+    let mut chunk = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u64).to_be_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::{sample_path, TestFiles};
+    use std::fs;
+
+    /// Builds a chunk with a big-endian 64-bit size.
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        encode_chunk(id, Some(data))
+    }
+
+    #[test]
+    /// Tests the matcher used to recognize the files.
+    fn test_is_dff() {
+        assert!(is_dff(&fs::read(sample_path("silence.dff")).unwrap()));
+        assert!(!is_dff(b"FRM8\0\0\0\0\0\0\0\x10DST "));
+        assert!(!is_dff(b"DSD "));
+    }
+
+    #[test]
+    /// Tests the duration of DST compressed audio.
+    fn test_dst_duration_ms() {
+        let mut frte = 3000_u32.to_be_bytes().to_vec();
+        frte.extend_from_slice(&75_u16.to_be_bytes());
+        let mut dst = chunk(b"FRTE", &frte);
+        dst.extend(chunk(b"DSTF", &[0; 5]));
+
+        assert_eq!(dst_duration_ms(&dst), 40_000);
+        assert_eq!(dst_duration_ms(&[]), 0);
+    }
+
+    #[test]
+    /// Tests reading and writing the file.
+    fn test_read_write() {
+        let files = TestFiles::new();
+        let path = files.copy("silence.dff", "test.dff");
+
+        let mut dff = DffFile::read_from_path(&path).unwrap();
+        assert_eq!(
+            dff.stream_info(),
+            &DffStreamInfo {
+                channels: 2,
+                sample_rate: 2_822_400,
+                compression: DffCompression::Dsd,
+                duration_ms: 100,
+            }
+        );
+        assert_eq!(dff.artist(), Some("Patricia Barber"));
+        assert_eq!(dff.title(), None);
+        assert_eq!(dff.id3_data(), None);
+
+        dff.set_title(Some("Code Cool"));
+        dff.set_artist(None);
+        dff.set_id3_data(Some(b"ID3\x04\0\0\0\0\0\0".to_vec()));
+        dff.save().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(
+            u64::from_be_bytes(bytes[4..12].try_into().unwrap()),
+            bytes.len() as u64 - 12
+        );
+
+        let mut dff = DffFile::read_from_path(&path).unwrap();
+        assert_eq!(dff.stream_info().duration_ms, 100);
+        assert_eq!(dff.artist(), None);
+        assert_eq!(dff.title(), Some("Code Cool"));
+        assert_eq!(dff.id3_data(), Some(&b"ID3\x04\0\0\0\0\0\0"[..]));
+
+        // The marker is kept in the DIIN chunk
+        let chunks = read_chunks(&mut File::open(&path).unwrap()).unwrap();
+        let diin = chunks.iter().find(|c| &c.id == b"DIIN").unwrap();
+        let diin = diin.read_data(&mut File::open(&path).unwrap()).unwrap();
+        let ids: Vec<[u8; 4]> = sub_chunks(&diin).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, [*b"MARK", *b"DITI"]);
+
+        // Removing the tag and the title leaves just the marker in the DIIN chunk
+        dff.set_id3_data(None);
+        dff.set_title(None);
+        dff.save().unwrap();
+        let chunks = read_chunks(&mut File::open(&path).unwrap()).unwrap();
+        assert!(!chunks.iter().any(|c| &c.id == b"ID3 "));
+        assert_eq!(
+            chunks.iter().find(|c| &c.id == b"DIIN").map(|c| c.size),
+            Some(34)
+        );
+    }
+}
@@ -3,6 +3,8 @@
 use serde::Serialize;
 use std::fmt;
 
+use crate::shared::infer_from_path;
+
 /// The types of files we can process
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub enum FileTypes {
@@ -11,6 +13,14 @@ pub enum FileTypes {
     Flac,
     MP3,
     M4A,
+    Ogg,
+    Opus,
+    Wav,
+    Aiff,
+    WavPack,
+    Musepack,
+    Tak,
+    Dff,
 
     #[default]
     Unknown,
@@ -20,7 +30,7 @@ impl FileTypes {
     /// Returns the file type of the file.
     #[must_use]
     pub fn from_filename(filename: &str) -> Self {
-        let file_type = infer::get_from_path(filename);
+        let file_type = infer_from_path(filename);
         match file_type {
             Ok(Some(ft)) => match ft.mime_type() {
                 "audio/x-ape" => Self::Ape,
@@ -28,6 +38,14 @@ impl FileTypes {
                 "audio/x-flac" => Self::Flac,
                 "audio/mpeg" => Self::MP3,
                 "video/mp4" | "audio/m4a" => Self::M4A,
+                "audio/ogg" => Self::Ogg,
+                "audio/opus" => Self::Opus,
+                "audio/x-wav" => Self::Wav,
+                "audio/x-aiff" => Self::Aiff,
+                "audio/x-wavpack" => Self::WavPack,
+                "audio/x-musepack" => Self::Musepack,
+                "audio/x-tak" => Self::Tak,
+                "audio/x-dff" => Self::Dff,
                 _ => Self::Unknown,
             },
             Ok(None) => Self::Unknown,
@@ -47,6 +65,14 @@ impl fmt::Display for FileTypes {
             Self::Flac => "FLAC",
             Self::MP3 => "MP3",
             Self::M4A => "M4A",
+            Self::Ogg => "OGG",
+            Self::Opus => "OPUS",
+            Self::Wav => "WAV",
+            Self::Aiff => "AIFF",
+            Self::WavPack => "WAVPACK",
+            Self::Musepack => "MUSEPACK",
+            Self::Tak => "TAK",
+            Self::Dff => "DFF",
             Self::Unknown => "Unknown",
         })
         .to_string();
@@ -56,7 +82,6 @@ impl fmt::Display for FileTypes {
 }
 
 #[cfg(test)]
-///
 mod tests {
     use super::*;
 
@@ -89,13 +114,16 @@ mod tests {
     }
 
     #[test]
-    ///
     fn test_fmt() {
         assert_eq!(format!("{}", FileTypes::Ape), "APE");
         assert_eq!(format!("{}", FileTypes::Dsf), "DSF");
         assert_eq!(format!("{}", FileTypes::Flac), "FLAC");
         assert_eq!(format!("{}", FileTypes::MP3), "MP3");
         assert_eq!(format!("{}", FileTypes::M4A), "M4A");
+        assert_eq!(format!("{}", FileTypes::WavPack), "WAVPACK");
+        assert_eq!(format!("{}", FileTypes::Musepack), "MUSEPACK");
+        assert_eq!(format!("{}", FileTypes::Tak), "TAK");
+        assert_eq!(format!("{}", FileTypes::Dff), "DFF");
         assert_eq!(format!("{}", FileTypes::Unknown), "Unknown");
     }
 }
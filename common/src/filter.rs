@@ -0,0 +1,453 @@
+//! Filter expressions used to select the files to process by their existing tags, eg.
+//! `genre == "Rock" && date < 1990 && !has(picture_front)`.
+//!
+//! Field names are compared ignoring case, underscores and hyphens, so `album_artist`, `ALBUMARTIST` and
+//! `album-artist` are the same field. Some fields are also known by the names used for the `id3tag` options and
+//! the `id3export` columns, eg. `album_title` for `ALBUM` and `track_count` for `TRACKTOTAL`.
+
+use anyhow::{bail, Result};
+use std::{cmp::Ordering, collections::HashMap, fmt, str::FromStr};
+
+/// Other names for the fields, using the names with the underscores and hyphens removed.
+const FIELD_ALIASES: [(&str, &str); 18] = [
+    ("ALBUMTITLE", "ALBUM"),
+    ("ALBUMSORT", "ALBUMTITLESORT"),
+    ("TRACKARTIST", "ARTIST"),
+    ("TRACKARTISTSORT", "ARTISTSORT"),
+    ("TRACKTITLE", "TITLE"),
+    ("TRACKTITLESORT", "TITLESORT"),
+    ("TRACKGENRE", "GENRE"),
+    ("TRACKCOMPOSER", "COMPOSER"),
+    ("TRACKDATE", "DATE"),
+    ("YEAR", "DATE"),
+    ("TRACK", "TRACKNUMBER"),
+    ("TRACKCOUNT", "TRACKTOTAL"),
+    ("DISC", "DISCNUMBER"),
+    ("DISCCOUNT", "DISCTOTAL"),
+    ("COMMENTS", "COMMENT"),
+    ("DESCRIPTION", "COMMENT"),
+    ("FRONTCOVER", "PICTUREFRONT"),
+    ("BACKCOVER", "PICTUREBACK"),
+];
+
+/// Gets the name a field is stored under: upper case, without underscores and hyphens, and with any alias resolved.
+fn field_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_uppercase();
+
+    FIELD_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, field)| (*field).to_string())
+}
+
+/// The existing values of the fields in a file, used to check the file against a `Filter`.
+#[derive(Debug, Default, Clone)]
+pub struct FilterValues {
+    values: HashMap<String, Vec<String>>,
+}
+
+impl FilterValues {
+    /// Creates an empty set of values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the values of a field. Blank values are left out.
+    pub fn insert<S: AsRef<str>>(&mut self, name: &str, values: impl IntoIterator<Item = S>) {
+        let values = values
+            .into_iter()
+            .map(|v| v.as_ref().trim().to_string())
+            .filter(|v| !v.is_empty());
+        self.values
+            .entry(field_name(name))
+            .or_default()
+            .extend(values);
+    }
+
+    /// Gets the values of a field. Fields without a value give an empty slice.
+    pub fn get(&self, name: &str) -> &[String] {
+        self.values
+            .get(&field_name(name))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The comparison operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Contains => "~=",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Has(String),
+    Compare(String, Operator, String),
+}
+
+/// A filter expression, eg. `genre == "Rock" && date < 1990`, checked against the existing tags of a file.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {token} in the filter \"{s}\".");
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Filter {
+    /// Checks if the values match the filter.
+    pub fn matches(&self, values: &FilterValues) -> bool {
+        evaluate(&self.expr, values)
+    }
+}
+
+/// Evaluates an expression. Comparisons are true if any of the values of the field match, and `!=` is true if none do.
+fn evaluate(expr: &Expr, values: &FilterValues) -> bool {
+    match expr {
+        Expr::And(left, right) => evaluate(left, values) && evaluate(right, values),
+        Expr::Or(left, right) => evaluate(left, values) || evaluate(right, values),
+        Expr::Not(inner) => !evaluate(inner, values),
+        Expr::Has(field) => !values.get(field).is_empty(),
+        Expr::Compare(field, Operator::NotEqual, literal) => !values
+            .get(field)
+            .iter()
+            .any(|v| compare(v, Operator::Equal, literal)),
+        Expr::Compare(field, op, literal) => {
+            values.get(field).iter().any(|v| compare(v, *op, literal))
+        }
+    }
+}
+
+/// Compares a value with a literal, ignoring case. Numbers are compared as numbers, and for `<`, `<=`, `>` and `>=`,
+/// the number at the start of the value is used, so dates like "1987-05-01" and track numbers like "3/12" work too.
+fn compare(value: &str, op: Operator, literal: &str) -> bool {
+    let number = literal.parse::<f64>().ok();
+    let text_order = || value.to_lowercase().cmp(&literal.to_lowercase());
+    let order = || match (leading_number(value), number) {
+        (Some(v), Some(n)) => v.partial_cmp(&n).unwrap_or(Ordering::Equal),
+        _ => text_order(),
+    };
+
+    match op {
+        Operator::Equal | Operator::NotEqual => {
+            let equal = value.to_lowercase() == literal.to_lowercase()
+                || matches!((value.parse::<f64>().ok(), number), (Some(v), Some(n)) if v == n);
+            equal == (op == Operator::Equal)
+        }
+        Operator::Less => order() == Ordering::Less,
+        Operator::LessOrEqual => order() != Ordering::Greater,
+        Operator::Greater => order() == Ordering::Greater,
+        Operator::GreaterOrEqual => order() != Ordering::Less,
+        Operator::Contains => value.to_lowercase().contains(&literal.to_lowercase()),
+    }
+}
+
+/// Gets the number at the start of a value, eg. 1987 from "1987-05-01".
+fn leading_number(value: &str) -> Option<f64> {
+    let end = value
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || *c == '.' || (*i == 0 && *c == '-')))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].parse().ok()
+}
+
+/// The tokens in a filter expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Not,
+    And,
+    Or,
+    Op(Operator),
+    Text(String),
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LeftParen => write!(f, "'('"),
+            Self::RightParen => write!(f, "')'"),
+            Self::Not => write!(f, "'!'"),
+            Self::And => write!(f, "'&&'"),
+            Self::Or => write!(f, "'||'"),
+            Self::Op(op) => write!(f, "'{op}'"),
+            Self::Text(text) => write!(f, "\"{text}\""),
+            Self::Word(word) => write!(f, "'{word}'"),
+        }
+    }
+}
+
+/// Splits a filter expression into tokens. Text in double quotes can contain `\"` and `\\`.
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if_eq(&expected).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Op(Operator::Equal),
+            '!' if next_is('=') => Token::Op(Operator::NotEqual),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Op(Operator::LessOrEqual),
+            '<' => Token::Op(Operator::Less),
+            '>' if next_is('=') => Token::Op(Operator::GreaterOrEqual),
+            '>' => Token::Op(Operator::Greater),
+            '~' if next_is('=') => Token::Op(Operator::Contains),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => bail!("Missing closing quote in the filter \"{s}\"."),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => bail!("Unexpected '{c}' in the filter \"{s}\"."),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Checks if the character can be part of a field name or a value without quotes, eg. `album_artist` or `1990-01-01`.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '/')
+}
+
+/// A recursive descent parser for the tokens. `||` binds less tightly than `&&`, which binds less tightly than `!`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Gets the next token without using it.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Uses the next token.
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Uses the next token if it is the one expected, and returns an error if it isn't.
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == *expected => Ok(()),
+            Some(token) => bail!("Expected {expected} but found {token} in the filter."),
+            None => bail!("Expected {expected} at the end of the filter."),
+        }
+    }
+
+    /// `or := and ('||' and)*`
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    /// `and := unary ('&&' unary)*`
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    /// `unary := '!' unary | '(' or ')' | 'has' '(' field ')' | field operator value`
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::LeftParen) => {
+                let expr = self.or()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Some(Token::Word(word))
+                if word.eq_ignore_ascii_case("has") && self.peek() == Some(&Token::LeftParen) =>
+            {
+                self.next();
+                let Some(Token::Word(field)) = self.next() else {
+                    bail!("Expected a field name in has() in the filter.");
+                };
+                self.expect(&Token::RightParen)?;
+                Ok(Expr::Has(field))
+            }
+            Some(Token::Word(field)) => {
+                let Some(Token::Op(op)) = self.next() else {
+                    bail!("Expected a comparison, eg. ==, after {field} in the filter.");
+                };
+                match self.next() {
+                    Some(Token::Text(value) | Token::Word(value)) => {
+                        Ok(Expr::Compare(field, op, value))
+                    }
+                    _ => bail!("Expected a value after the comparison with {field} in the filter."),
+                }
+            }
+            Some(token) => bail!("Unexpected {token} in the filter."),
+            None => bail!("The filter ends too early."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values for a track with two artists, a date and a back cover.
+    fn values() -> FilterValues {
+        let mut values = FilterValues::new();
+        values.insert("GENRE", ["Rock"]);
+        values.insert("ARTIST", ["Simon", "Garfunkel"]);
+        values.insert("DATE", ["1987-05-01"]);
+        values.insert("TRACKNUMBER", ["03"]);
+        values.insert("album_title", ["Graceland"]);
+        values.insert("PICTUREBACK", ["12345"]);
+        values.insert("COMMENT", [" "]);
+        values
+    }
+
+    /// Parses and checks an expression against the values.
+    fn check(expression: &str) -> bool {
+        expression.parse::<Filter>().unwrap().matches(&values())
+    }
+
+    #[test]
+    /// Tests the comparisons, including for fields with more than one value and fields without a value.
+    fn test_compare() {
+        assert!(check("genre == \"rock\""));
+        assert!(check("genre != Pop"));
+        assert!(check("artist == Garfunkel"));
+        assert!(!check("artist != Garfunkel"));
+        assert!(check("date < 1990"));
+        assert!(!check("date >= 1990"));
+        assert!(check("track == 3"));
+        assert!(check("track_number <= 3"));
+        assert!(check("ALBUM ~= land"));
+        assert!(check("album > Abbey"));
+        assert!(!check("comment == \"\""));
+        assert!(!check("composer == Simon"));
+        assert!(check("composer != Simon"));
+        assert!(!check("title == \"Say \\\"Hi\\\"\""));
+    }
+
+    #[test]
+    /// Tests `has()`, `!`, `&&`, `||` and parentheses.
+    fn test_logic() {
+        assert!(check(
+            "genre == \"Rock\" && date < 1990 && !has(picture_front)"
+        ));
+        assert!(check("has(back_cover) && has(Album-Artist) || has(artist)"));
+        assert!(!check("has(comment)"));
+        assert!(!check("!(genre == Rock || genre == Pop)"));
+        assert!(check("!genre == Pop"));
+    }
+
+    #[test]
+    /// Tests that invalid expressions are rejected.
+    fn test_invalid() {
+        for expression in [
+            "",
+            "genre",
+            "genre ==",
+            "genre = Rock",
+            "(genre == Rock",
+            "genre == Rock)",
+            "has(genre",
+            "genre == \"Rock",
+            "genre == Rock &&",
+            "genre == Rock $",
+        ] {
+            assert!(
+                expression.parse::<Filter>().is_err(),
+                "{expression} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    /// Tests the field names and aliases.
+    fn test_field_name() {
+        assert_eq!(field_name("album_artist"), "ALBUMARTIST");
+        assert_eq!(field_name("MUSICBRAINZ_TRACKID"), "MUSICBRAINZTRACKID");
+        assert_eq!(field_name("year"), "DATE");
+        assert_eq!(field_name("track-count"), "TRACKTOTAL");
+        assert_eq!(field_name("picture_front"), "PICTUREFRONT");
+    }
+}
@@ -2,15 +2,35 @@
 
 #![forbid(unsafe_code)]
 
+mod apev2;
+mod dff;
 mod file_types;
+mod filter;
 mod genres;
 mod log;
 mod main_cli;
+mod ogg;
+mod riff;
 mod shared;
+#[cfg(any(test, feature = "test-files"))]
+pub mod test_files;
+mod tracks;
 
 // Define the file types supported by the id3tools family of programs.
 pub use crate::file_types::FileTypes;
 
+// Reads the stream information from Monkey's Audio, `WavPack`, Musepack and TAK files.
+pub use crate::apev2::ApeStreamInfo;
+
+// Reads the stream information and reads and writes the tags in DSDIFF files.
+pub use crate::dff::{DffCompression, DffFile, DffStreamInfo};
+
+// Reads and writes the comments in Ogg Vorbis and Ogg Opus files.
+pub use crate::ogg::{OggCodec, OggFile, OggPicture, OggStreamInfo};
+
+// Reads the stream information and the `LIST/INFO` chunk in WAV and AIFF files.
+pub use crate::riff::{RiffFile, RiffFormat, RiffStreamInfo, RIFF_INFO_FRAMES};
+
 // Builds the main CLI for the `id3tag` application and also the `id3cli-gen` application.
 pub use crate::main_cli::build_cli;
 
@@ -32,5 +52,11 @@ pub use crate::shared::roman_to_decimal;
 pub use crate::shared::split_val;
 pub use crate::shared::thousand_separated;
 
+// Filter expressions for selecting files by their existing tags.
+pub use crate::filter::{Filter, FilterValues};
+
+// Reads the tags and stream information from music files for `id3export` and `id3show`.
+pub use crate::tracks::{flatten_vec, Reader, Track};
+
 // Define the genres supported by the id3tools family of programs.
 pub use crate::genres::Genre;
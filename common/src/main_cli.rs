@@ -10,6 +10,7 @@ pub fn build_cli(version: &'static str) -> Command {
     let tags_name = "TAGS";
     let operations_name = "OPERATIONS";
     let images_name = "IMAGES";
+    let identifiers_name = "IDENTIFIERS";
 
     Command::new("id3tag")
         .about("A simple application for updating metadata (ID3) information in music files.")
@@ -23,7 +24,7 @@ pub fn build_cli(version: &'static str) -> Command {
                 .help("One or more file(s) to process.")
                 .long_help("One or more files to process.  Wildcards and multiple_occurrences files (e.g. 2019*.flac 2020*.mp3) are supported. Use the ** glob to recurse (eg. **/*.mp3). Note: Case sensitive.")
                 .num_args(1..)
-                .required(true)
+                .required_unless_present_any(["from-csv", "undo"])
                 .action(ArgAction::Append)
         )
         .arg( // Stop on error
@@ -97,22 +98,22 @@ pub fn build_cli(version: &'static str) -> Command {
                 .long("album-artist")
                 .visible_alias("aa")
                 .help("The album artist(s).")
-                .long_help("The name of the album artist(s). Use quotation marks for multi-word entries.")
+                .long_help("The name of the album artist(s). Use quotation marks for multi-word entries. Use more than once for several album artists, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false)
                 .help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Album artist sort
             Arg::new("album-artist-sort")
                 .long("album-artist-sort")
                 .visible_alias("aas")
                 .help("Album artist(s) sort name.")
-                .long_help("The name on which the album artist(s) is sorted. Use quotation marks for multi-word entries. Example: Artist is 'Alicia Keys', but this value may be 'Keys, Alicia'. This is usually set to be the same for all tracks and discs for an album. Use quotation marks for multi-word entries.")
+                .long_help("The name on which the album artist(s) is sorted. Use quotation marks for multi-word entries. Example: Artist is 'Alicia Keys', but this value may be 'Keys, Alicia'. This is usually set to be the same for all tracks and discs for an album. Use quotation marks for multi-word entries. Use more than once for several album artists, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false)
                 .help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Album title
             Arg::new("album-title")
@@ -176,35 +177,35 @@ pub fn build_cli(version: &'static str) -> Command {
                 .long("track-artist")
                 .visible_alias("ta")
                 .help("The track artist.")
-                .long_help("The name of the track artist(s). Use quotation marks for multi-word entries.")
+                .long_help("The name of the track artist(s). Use quotation marks for multi-word entries. Use more than once for several artists, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false)
                 .help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track artist
             Arg::new("track-album-artist")
                 .long("track-album-artist")
                 .visible_alias("taa")
                 .help("Set album and track artist to be the same value.")
-                .long_help("Sets both the album artist and track artist to the value provided.")
+                .long_help("Sets both the album artist and track artist to the value provided. Use more than once for several artists, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false)
                 .conflicts_with("track-artist")
                 .conflicts_with("album-artist")
                 .help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track artist sort
             Arg::new("track-artist-sort")
                 .long("track-artist-sort")
                 .visible_alias("tas")
                 .help("The track artist(s) sort name.")
-                .help("The sort name of the track artist(s). Use quotation marks for multi-word entries. Example: Artist is 'Alicia Keys', but this value may be 'Keys, Alicia'.")
+                .help("The sort name of the track artist(s). Use quotation marks for multi-word entries. Example: Artist is 'Alicia Keys', but this value may be 'Keys, Alicia'. Use more than once for several artists, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false)
                 .help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track title
             Arg::new("track-title")
@@ -260,15 +261,38 @@ pub fn build_cli(version: &'static str) -> Command {
                 .help_heading(tags_name)
                 .action(ArgAction::Set)
         )
+        .arg( // Automatic track numbering
+            Arg::new("auto-track-number")
+                .long("auto-track-number")
+                .visible_alias("atn")
+                .help("Number the tracks in each folder based on their sort order.")
+                .long_help("Sorts the files being processed in each folder and numbers them from 1. The files are sorted on their names, with numbers sorted by value (so '2 Vogue.flac' comes before '10 Frozen.flac'), unless --auto-track-sort is used. The numbering restarts in each folder, so disc subfolders (eg. 'CD1', 'CD2') get their own sequence.")
+                .num_args(0)
+                .conflicts_with("track-number")
+                .help_heading(tags_name)
+                .action(ArgAction::SetTrue)
+        )
+        .arg( // Automatic track numbering sort order
+            Arg::new("auto-track-sort")
+                .long("auto-track-sort")
+                .value_name("KEY")
+                .help("What to sort the tracks on for --auto-track-number: 'name' or a tag name.")
+                .long_help("What to sort the tracks on for --auto-track-number. Use 'name' (the default) to sort on the file name, or the name of a tag (eg. TITLE, or any tag name used with --set) to sort on its existing value. Files without the tag come last, and ties are sorted on the file name.")
+                .num_args(1)
+                .require_equals(false)
+                .requires("auto-track-number")
+                .help_heading(tags_name)
+                .action(ArgAction::Set)
+        )
         .arg( // Track genre
             Arg::new("track-genre")
                 .long("track-genre")
                 .visible_alias("tg")
                 .help("The track music genre.")
-                .long_help("The track music genre (eg. 'Rock', 'R&B', 'Classical'). This is usually set to the same value for all tracks on a disc or album. Use quotation marks for multi-word entries. Cannot be combined with '--cdumber'.")
+                .long_help("The track music genre (eg. 'Rock', 'R&B', 'Classical'). This is usually set to the same value for all tracks on a disc or album. Use quotation marks for multi-word entries. Cannot be combined with '--cdumber'. Use more than once for several genres, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false).help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track genre number
             Arg::new("track-genre-number")
@@ -288,20 +312,20 @@ pub fn build_cli(version: &'static str) -> Command {
                 .long("track-composer")
                 .visible_alias("tc")
                 .help("The composer(s) for the track.")
-                .help("The composer(s) for the track. Use quotation marks for multi-word entries.")
+                .help("The composer(s) for the track. Use quotation marks for multi-word entries. Use more than once for several composers, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false).help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track composer sort
             Arg::new("track-composer-sort")
                 .long("track-composer-sort")
                 .visible_alias("tcs")
                 .help("The sort composer(s) for the track.")
-                .help("The sort composer(s) for the track. Use quotation marks for multi-word entries. For example, if the composer is 'Ludwig van Beethoven', this value could be 'Beethoven, Ludwig van'.")
+                .help("The sort composer(s) for the track. Use quotation marks for multi-word entries. For example, if the composer is 'Ludwig van Beethoven', this value could be 'Beethoven, Ludwig van'. Use more than once for several composers, or see '--multi-value-separator'.")
                 .num_args(1)
                 .require_equals(false).help_heading(tags_name)
-                .action(ArgAction::Set)
+                .action(ArgAction::Append)
         )
         .arg( // Track date
             Arg::new("track-date")
@@ -323,6 +347,124 @@ pub fn build_cli(version: &'static str) -> Command {
                 .require_equals(false).help_heading(tags_name)
                 .action(ArgAction::Set)
         )
+        .arg( // ISRC
+            Arg::new("isrc")
+                .long("isrc")
+                .value_name("ISRC")
+                .help("The International Standard Recording Code (ISRC) for the track.")
+                .long_help("The International Standard Recording Code (ISRC) for the track, e.g. AUAP08000041 or AU-AP0-80-00041. Must be 12 characters: a 2-letter country code, a 3-character registrant code, a 2-digit year and a 5-digit designation code. Written without the hyphens.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // ISWC
+            Arg::new("iswc")
+                .long("iswc")
+                .value_name("ISWC")
+                .help("The International Standard Musical Work Code (ISWC) for the composition.")
+                .long_help("The International Standard Musical Work Code (ISWC) for the composition, e.g. T-034.524.680-1 or T0345246801. Must be the letter T followed by 9 digits and a check digit. Written as T-034.524.680-1.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // Barcode
+            Arg::new("barcode")
+                .long("barcode")
+                .visible_alias("bc")
+                .value_name("BARCODE")
+                .help("The barcode (UPC or EAN) of the release.")
+                .long_help("The barcode (UPC or EAN) of the release, e.g. 075992592626. This is usually set to be the same for all tracks and discs for an album.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // Catalog number
+            Arg::new("catalog-number")
+                .long("catalog-number")
+                .visible_alias("cn")
+                .value_name("CATALOG")
+                .help("The catalog number of the release.")
+                .long_help("The catalog number given to the release by the label, e.g. 9 25926-2. This is usually set to be the same for all tracks and discs for an album.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // MusicBrainz track ID
+            Arg::new("musicbrainz-track-id")
+                .long("musicbrainz-track-id")
+                .visible_alias("mbtid")
+                .value_name("ID")
+                .help("The MusicBrainz recording ID for the track.")
+                .long_help("The MusicBrainz recording ID for the track, e.g. 110f3d90-b0ed-497f-a352-ca581bc8025f.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // MusicBrainz album ID
+            Arg::new("musicbrainz-album-id")
+                .long("musicbrainz-album-id")
+                .visible_alias("mbaid")
+                .value_name("ID")
+                .help("The MusicBrainz release ID for the album.")
+                .long_help("The MusicBrainz release ID for the album. This is usually set to be the same for all tracks on a disc or album.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // MusicBrainz artist ID
+            Arg::new("musicbrainz-artist-id")
+                .long("musicbrainz-artist-id")
+                .visible_alias("mbarid")
+                .value_name("ID")
+                .help("The MusicBrainz artist ID(s) for the track artist(s).")
+                .long_help("The MusicBrainz artist ID(s) for the track artist(s). Use more than once for several artists.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Append)
+        )
+        .arg( // MusicBrainz album artist ID
+            Arg::new("musicbrainz-album-artist-id")
+                .long("musicbrainz-album-artist-id")
+                .visible_alias("mbaaid")
+                .value_name("ID")
+                .help("The MusicBrainz artist ID(s) for the album artist(s).")
+                .long_help("The MusicBrainz artist ID(s) for the album artist(s). Use more than once for several artists.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Append)
+        )
+        .arg( // MusicBrainz release group ID
+            Arg::new("musicbrainz-release-group-id")
+                .long("musicbrainz-release-group-id")
+                .visible_alias("mbrgid")
+                .value_name("ID")
+                .help("The MusicBrainz release group ID for the album.")
+                .long_help("The MusicBrainz release group ID for the album. This is usually set to be the same for all tracks on a disc or album.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
+        .arg( // MusicBrainz disc ID
+            Arg::new("musicbrainz-disc-id")
+                .long("musicbrainz-disc-id")
+                .visible_alias("mbdid")
+                .value_name("ID")
+                .help("The MusicBrainz disc ID for the disc.")
+                .long_help("The MusicBrainz disc ID for the disc, e.g. XzPS7vW.HPHsYemQh0HBUGr8vuU-. This is calculated from the table of contents of the CD.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(identifiers_name)
+                .action(ArgAction::Set)
+        )
         .arg( // Front cover picture candidate
             Arg::new("picture-front-candidate")
                 .long("picture-front-candidate")
@@ -365,16 +507,17 @@ pub fn build_cli(version: &'static str) -> Command {
                 .require_equals(false).help_heading(images_name)
                 .action(ArgAction::Set)
         )
-        // .arg( // Tags (Hidden)
-        //     Arg::new("tags")
-        //         .long("tags")
-        //         .short('t')
-        //         .help("The tags you wish to set in the form `key1=value1, key2=value2`. Note the space between entries!")
-        //         .num_args(1..)
-        //         .require_equals(true)
-        //         .hide(true).help_heading(tags_name)
-        //         .action(ArgAction::Append)
-        // )
+        .arg( // Set arbitrary tags
+            Arg::new("set")
+                .long("set")
+                .value_name("KEY=VALUE")
+                .help("Set any tag, e.g. --set LABEL=\"Blue Note\".")
+                .long_help("Set any tag using its Vorbis comment style name, e.g. --set LABEL=\"Blue Note\" --set ISRC=USRC17607839. Known names such as ARTIST or ALBUM map to the same tag as the corresponding option. Other names are written as a Vorbis comment (FLAC), an item (APE), a native or TXXX frame (MP3) or a freeform atom (MP4). Format-specific keys such as 'TXXX:My Field' or '----:com.apple.iTunes:My Field' are used as-is. Use multiple --set flags for multiple tags.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(tags_name)
+                .action(ArgAction::Append)
+        )
         .arg( // Rename file
             Arg::new("rename-file")
                 .long("rename-file")
@@ -387,4 +530,279 @@ pub fn build_cli(version: &'static str) -> Command {
                 .display_order(1)
                 .action(ArgAction::Set)
         )
+        .arg( // Remove tags
+            Arg::new("remove")
+                .long("remove")
+                .value_name("TAG(S)")
+                .help("Remove the tag(s) listed, e.g. --remove ENCODER,COMMENT,URL")
+                .long_help("Remove the tag(s) listed before any new values are set. Use a comma-separated list or multiple --remove flags, e.g. --remove ENCODER,COMMENT --remove URL. Tag names are the same as for --set.")
+                .num_args(1)
+                .value_delimiter(',')
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(2)
+                .action(ArgAction::Append)
+        )
+        .arg( // Keep tags
+            Arg::new("keep")
+                .long("keep")
+                .value_name("TAG(S)")
+                .help("Remove all tags except the ones listed, e.g. --keep ARTIST,TITLE")
+                .long_help("Remove all existing tags except the ones listed before any new values are set. Use a comma-separated list or multiple --keep flags, e.g. --keep ARTIST,TITLE --keep PICTUREFRONT. Tag names are the same as for --set.")
+                .num_args(1)
+                .value_delimiter(',')
+                .require_equals(false)
+                .conflicts_with("clear-all")
+                .help_heading(operations_name)
+                .display_order(3)
+                .action(ArgAction::Append)
+        )
+        .arg( // Clear all tags
+            Arg::new("clear-all")
+                .long("clear-all")
+                .help("Remove all existing tags before setting any new values.")
+                .long_help("Remove all existing tags, including cover art, before setting any new values. Combine with other options to replace all the tags in the file.")
+                .num_args(0)
+                .help_heading(operations_name)
+                .display_order(4)
+                .action(ArgAction::SetTrue)
+        )
+        .arg( // Search and replace in tag values
+            Arg::new("replace")
+                .long("replace")
+                .value_names(["TAG", "EXPRESSION"])
+                .help("Search and replace in the value of a tag, e.g. --replace ARTIST 's/ ft\\. / feat. /'")
+                .long_help("Search and replace in the existing value of a tag using a sed-style expression, s/pattern/replacement/flags. The pattern is a regular expression, capture groups can be used as \\1 or $1 in the replacement, and the flags are g (replace all) and i (ignore case). Can be used multiple times, e.g. --replace TITLE 's/ \\(Remastered \\d*\\)//' --replace LABEL 's/^EMI$/Parlophone/'. Tag names are the same as for --set.")
+                .num_args(2)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(5)
+                .action(ArgAction::Append)
+        )
+        .arg( // Change the capitalisation of tags
+            Arg::new("case")
+                .long("case")
+                .value_name("CASE")
+                .help("Change the capitalisation of the artist, album artist, album and title tags.")
+                .long_help("Change the capitalisation of the existing values of the artist, album artist, album and title tags, or the tags given with --case-fields. Title case keeps small words such as \"of\" and \"the\" in lower case, except at the start and end.")
+                .num_args(1)
+                .value_parser(["title", "sentence", "upper", "lower"])
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(6)
+                .action(ArgAction::Set)
+        )
+        .arg( // Tags to change the capitalisation of
+            Arg::new("case-fields")
+                .long("case-fields")
+                .value_name("TAG(S)")
+                .help("The tags to change with --case, e.g. --case-fields ARTIST,TITLE")
+                .long_help("The tags to change the capitalisation of with --case. Use a comma-separated list or multiple --case-fields flags. Defaults to ARTIST, ALBUMARTIST, ALBUM and TITLE. Tag names are the same as for --set.")
+                .num_args(1)
+                .value_delimiter(',')
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(7)
+                .action(ArgAction::Append)
+        )
+        .arg( // Small words for title case
+            Arg::new("case-small-words")
+                .long("case-small-words")
+                .value_name("WORD(S)")
+                .help("Words to keep in lower case in title case, e.g. --case-small-words of,the,a")
+                .long_help("Words to keep in lower case in title case, unless they are the first or last word. Use a comma-separated list or multiple --case-small-words flags. Replaces the default list: a, an, and, as, at, but, by, for, from, in, into, nor, of, on, or, over, the, to, vs, with.")
+                .num_args(1)
+                .value_delimiter(',')
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(8)
+                .action(ArgAction::Append)
+        )
+        .arg( // Words with fixed capitalisation
+            Arg::new("case-exceptions")
+                .long("case-exceptions")
+                .value_name("WORD(S)")
+                .help("Words to always write as given, e.g. --case-exceptions AC/DC,ABBA")
+                .long_help("Words to always write as given, regardless of --case. Matched ignoring case, so \"abba\" becomes \"ABBA\". Use a comma-separated list or multiple --case-exceptions flags.")
+                .num_args(1)
+                .value_delimiter(',')
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(9)
+                .action(ArgAction::Append)
+        )
+        .arg( // Read tags from a CSV file
+            Arg::new("from-csv")
+                .long("from-csv")
+                .value_name("FILE")
+                .help("Read tag values from a CSV file, e.g. one written by id3export.")
+                .long_help("Read tag values from a CSV file, e.g. one written by id3export -c. Rows are matched to the music files using the 'path' column. The id3export columns (album_artist, title, track_number, etc.) set the corresponding tags, and any other columns are used as tag names, the same as for --set. Blank cells leave the tag as-is, and cells containing <delete> remove the tag. If no files are given, the files listed in the CSV are processed.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(10)
+                .action(ArgAction::Set)
+        )
+        .arg( // Get tags from the filename
+            Arg::new("tags-from-filename")
+                .long("tags-from-filename")
+                .visible_alias("tff")
+                .value_name("PATTERN")
+                .help("Get tag values from the path of the file. Example: \"%aa/%at/%dn-%tn %tt\"")
+                .long_help("Get tag values from the path of the file, the reverse of --rename-file. Uses the same placeholders as --rename-file, and '/' to separate the folders. The pattern is matched against the end of the path, without the extension, so \"%aa/%at/%dn-%tn %tt\" gets the album artist and title from the folders, and the disc number, track number and title from \"01-03 Express Yourself.flac\". Disc and track numbers only match digits. Any other options take precedence over the values from the filename.")
+                .num_args(1)
+                .require_equals(false)
+                .conflicts_with("tags-from-filename-regex")
+                .help_heading(operations_name)
+                .display_order(11)
+                .action(ArgAction::Set)
+        )
+        .arg( // Get tags from the filename with a regex
+            Arg::new("tags-from-filename-regex")
+                .long("tags-from-filename-regex")
+                .value_name("REGEX")
+                .help("Get tag values from the path of the file using a regular expression with named groups.")
+                .long_help("Get tag values from the path of the file using a regular expression with named groups, e.g. '(?P<TRACKNUMBER>\\d+) - (?P<TITLE>.+)$'. The group names are tag names, the same as for --set. The expression is matched against the path, without the extension, using '/' to separate the folders.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(12)
+                .action(ArgAction::Set)
+        )
+        .arg( // Read tags from a CUE sheet
+            Arg::new("cue")
+                .long("cue")
+                .value_name("FILE")
+                .help("Read album and track values from a CUE sheet. Without a file name, looks for one next to each music file.")
+                .long_help("Read album and track values from a CUE sheet, e.g. one written by EAC or XLD. Without a file name, looks for a .cue file next to each music file, and for FLAC files without one, uses the cue sheet embedded in the file. Tracks are matched to the music files by the FILE entries in the cue sheet, or by track order if the number of files and tracks match. Sets the album title, album artist, catalog number, date, genre, track title, artist, composer, ISRC and track number. Any other options take precedence over the values from the cue sheet. Note that this is specified WITHOUT the '=', eg. --cue album.cue")
+                .num_args(..=1)
+                .require_equals(false)
+                .default_missing_value("auto")
+                .help_heading(operations_name)
+                .display_order(13)
+                .action(ArgAction::Set)
+        )
+        .arg( // Copy tags from another file
+            Arg::new("copy-tags-from")
+                .long("copy-tags-from")
+                .value_name("SOURCE")
+                .help("Copy the tags and cover art from another music file, or from the matching file in a folder.")
+                .long_help("Copy the tags and cover art from another music file, which can be of a different type, e.g. from a FLAC master to an MP3 or M4A transcode. If SOURCE is a folder, each file is matched with the file with the same name (ignoring the extension) in the folder, or with the file with the same track number. Folders given as the files to process are expanded to the music files in them, so --copy-tags-from flac/ mp3/ copies the tags for a whole album. Any other options take precedence over the copied values.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(14)
+                .action(ArgAction::Set)
+        )
+        .arg( // Separator for multi-value fields
+            Arg::new("multi-value-separator")
+                .long("multi-value-separator")
+                .visible_alias("mvs")
+                .value_name("SEPARATOR")
+                .help("Split values on this separator into fields with more than one value, e.g. --mvs ';' for 'Simon; Garfunkel'.")
+                .long_help("Split the new tag values on this separator into fields with more than one value, e.g. --mvs ';' --track-artist 'Simon; Garfunkel' sets two artists. Applies to values from all the options, the config file, the CSV file, etc. FLAC and MP4 files get one field per value, while MP3, DSF and APE files get the values in one field, separated the way the format expects. Cover art file names are not split.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(15)
+                .action(ArgAction::Set)
+        )
+        .arg( // What to do with the LIST/INFO chunk in WAV files
+            Arg::new("riff-info")
+                .long("riff-info")
+                .value_name("SETTING")
+                .help("What to do with the LIST/INFO chunk in WAV files: sync (default), keep or remove.")
+                .long_help("What to do with the RIFF LIST/INFO chunk in WAV files when the ID3 tag is written. 'sync' (the default) copies any title, artist, album, date, genre, track number, comment, copyright or software values that are only in the INFO chunk into the ID3 tag, and then writes the ID3 values back to the INFO chunk. 'keep' leaves the INFO chunk as-is, and 'remove' copies the values into the ID3 tag and removes the INFO chunk.")
+                .num_args(1)
+                .value_parser(["sync", "keep", "remove"])
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(16)
+                .action(ArgAction::Set)
+        )
+        .arg( // Which ID3v2 version to write
+            Arg::new("id3-version")
+                .long("id3-version")
+                .value_name("VERSION")
+                .help("The ID3v2 version to write: 2.3 or 2.4 (default).")
+                .long_help("The version of the ID3v2 tags written to MP3, DSF, DSDIFF, WAV and AIFF files. '2.4' is the default. '2.3' is read by more car stereos and older players. The dates are then written to the TYER, TDAT, TIME and TORY frames instead of TDRC and TDOR, fields with more than one value are separated by '/', and text is written as UTF-16.")
+                .num_args(1)
+                .value_parser(["2.3", "2.4"])
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(17)
+                .action(ArgAction::Set)
+        )
+        .arg( // What to do with ID3v1 tags
+            Arg::new("id3v1")
+                .long("id3v1")
+                .value_name("SETTING")
+                .help("What to do with the ID3v1 tag in MP3 files: keep (default), update, write or remove.")
+                .long_help("What to do with the ID3v1 tag at the end of MP3 files when the tags are written. 'keep' (the default) leaves it as-is. 'update' writes the new title, artist, album, year, comment, track number and genre to the ID3v1 tag if the file has one, and 'write' does the same but also adds an ID3v1 tag to files that don't have one. 'remove' removes the ID3v1 tag. Files that only have an ID3v1 tag always get an ID3v2 tag with the same values.")
+                .num_args(1)
+                .value_parser(["keep", "update", "write", "remove"])
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(18)
+                .action(ArgAction::Set)
+        )
+        .arg( // Where to write the undo journal
+            Arg::new("journal")
+                .long("journal")
+                .value_name("FILE")
+                .help("The file to write the undo journal to.")
+                .long_help("The file to write the undo journal to. Every run that isn't a dry run records the old and new values of the tags and covers it changes, and the old and new names of the files it renames, so the changes can be undone with --undo. By default, a new journal is written to ~/.config/id3tag/journals for each run.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(19)
+                .action(ArgAction::Set)
+        )
+        .arg( // Undo the changes in a journal
+            Arg::new("undo")
+                .long("undo")
+                .value_name("JOURNAL")
+                .help("Undo the changes recorded in a journal file.")
+                .long_help("Undo the changes recorded in a journal file written by an earlier run: the old tag values are set again, tags that were added are removed, and renamed files get their old names back. The old front and back covers are read from the folder written next to the journal, eg. retag.covers for retag.json. No other files are processed. Can be combined with --dry-run to see what would be undone.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(20)
+                .action(ArgAction::Set)
+        )
+        .arg( // How to show the dry-run preview
+            Arg::new("diff-format")
+                .long("diff-format")
+                .value_name("FORMAT")
+                .help("How a dry run shows the changes: text (default) or json.")
+                .long_help("How a dry run shows the changes that would be made. 'text' (the default) shows a table of the old and new values of the tags and covers that would change, and the old and new names of files that would be renamed, for each file. 'json' shows the same changes in the format used for the undo journal. Not shown if --detail-off is set, in which case the new values are logged instead.")
+                .num_args(1)
+                .value_parser(["text", "json"])
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(21)
+                .action(ArgAction::Set)
+        )
+        .arg( // Only fill in empty fields
+            Arg::new("only-if-empty")
+                .long("only-if-empty")
+                .help("Only set fields that are empty in the file.")
+                .long_help("Only set fields that have no value, or a blank value, in the file. Values from the command line, the config file, a CUE sheet, a CSV file or the file name are left out for fields that already have a value. Use the fill_only list in the config file to do this for some fields only.")
+                .num_args(0)
+                .help_heading(operations_name)
+                .display_order(22)
+                .action(ArgAction::SetTrue)
+        )
+        .arg( // Only process the files that match a filter
+            Arg::new("where")
+                .long("where")
+                .value_name("EXPRESSION")
+                .help("Only process the files whose existing tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990'")
+                .long_help("Only process the files whose existing tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990 && !has(picture_front)'. Fields are compared with ==, !=, <, <=, >, >= and ~= (contains), ignoring case, and has(field) checks if a field has a value. Combine with &&, || and !, and use parentheses to group. Files that don't match are skipped.")
+                .num_args(1)
+                .require_equals(false)
+                .help_heading(operations_name)
+                .display_order(23)
+                .action(ArgAction::Set)
+        )
 }
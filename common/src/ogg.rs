@@ -0,0 +1,872 @@
+//! Reads and writes the Vorbis comments in Ogg Vorbis and Ogg Opus files.
+//!
+//! Only the comment header is rewritten when the file is saved. The audio pages are copied as-is, apart from
+//! their page sequence numbers and checksums if the new comment header takes up a different number of pages.
+//! Files with more than one logical stream, ie. chained or multiplexed files, are read but can't be written.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::shared;
+
+/// The name of the comment holding a picture, eg. the front cover.
+const PICTURE_KEY: &str = "METADATA_BLOCK_PICTURE";
+
+/// The most segments an Ogg page can hold.
+const MAX_SEGMENTS: usize = 255;
+
+/// The page header flag for the first page of a stream.
+const BEGINNING_OF_STREAM: u8 = 0x02;
+
+/// The codecs we can read and write the comments for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OggCodec {
+    Vorbis,
+    Opus,
+}
+
+impl OggCodec {
+    /// The number of header packets at the start of the stream, including the comment header.
+    const fn header_count(self) -> usize {
+        match self {
+            Self::Vorbis => 3,
+            Self::Opus => 2,
+        }
+    }
+
+    /// The bytes at the start of the comment header packet.
+    const fn comment_magic(self) -> &'static [u8] {
+        match self {
+            Self::Vorbis => b"\x03vorbis",
+            Self::Opus => b"OpusTags",
+        }
+    }
+}
+
+impl fmt::Display for OggCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vorbis => write!(f, "Vorbis"),
+            Self::Opus => write!(f, "Opus"),
+        }
+    }
+}
+
+/// The stream information from the identification header and the last page of the stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OggStreamInfo {
+    /// The number of channels.
+    pub channels: u8,
+
+    /// The sample rate in Hz. For Opus this is the sample rate of the original audio, as Opus always decodes at 48 kHz.
+    pub sample_rate: u32,
+
+    /// The nominal (Vorbis) or average (Opus) bitrate in bits per second.
+    pub bitrate: Option<u32>,
+
+    /// The duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// A picture stored in a `METADATA_BLOCK_PICTURE` comment. Uses the same layout as a FLAC picture block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OggPicture {
+    /// The picture type, eg. `OggPicture::FRONT_COVER` or `OggPicture::BACK_COVER`.
+    pub picture_type: u32,
+
+    /// The MIME type, eg. `image/jpeg`.
+    pub mime_type: String,
+
+    /// The description of the picture.
+    pub description: String,
+
+    /// The width in pixels, or 0 if unknown.
+    pub width: u32,
+
+    /// The height in pixels, or 0 if unknown.
+    pub height: u32,
+
+    /// The colour depth in bits per pixel, or 0 if unknown.
+    pub depth: u32,
+
+    /// The number of colours in indexed pictures, or 0.
+    pub colors: u32,
+
+    /// The picture data.
+    pub data: Vec<u8>,
+}
+
+impl OggPicture {
+    /// The picture type used for the front cover, as defined by ID3v2 `APIC` frames and FLAC picture blocks.
+    pub const FRONT_COVER: u32 = 3;
+
+    /// The picture type used for the back cover.
+    pub const BACK_COVER: u32 = 4;
+}
+
+/// The Vorbis comments and stream information of an Ogg Vorbis or Ogg Opus file.
+#[derive(Debug, Clone)]
+pub struct OggFile {
+    path: PathBuf,
+    codec: OggCodec,
+    info: OggStreamInfo,
+    vendor: String,
+    comments: Vec<(String, String)>,
+    pictures: Vec<OggPicture>,
+}
+
+impl OggFile {
+    /// Reads the comments and stream information from the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't an Ogg Vorbis or Ogg Opus file.
+    pub fn read_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let stream = Stream::parse(&bytes)?;
+
+        let (vendor, all_comments) = decode_comments(stream.codec, &stream.packets[1])?;
+        let mut comments = Vec::new();
+        let mut pictures = Vec::new();
+        for (key, value) in all_comments {
+            if key.eq_ignore_ascii_case(PICTURE_KEY) {
+                match decode_picture(&value) {
+                    Ok(picture) => pictures.push(picture),
+                    Err(err) => log::warn!("Unable to read a picture in {}: {err}", path.display()),
+                }
+            } else {
+                comments.push((key, value));
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            codec: stream.codec,
+            info: stream.info(&bytes),
+            vendor,
+            comments,
+            pictures,
+        })
+    }
+
+    /// The codec used in the file.
+    #[must_use]
+    pub const fn codec(&self) -> OggCodec {
+        self.codec
+    }
+
+    /// The stream information, eg. channels and duration.
+    #[must_use]
+    pub const fn stream_info(&self) -> &OggStreamInfo {
+        &self.info
+    }
+
+    /// The name of the encoder that wrote the file.
+    #[must_use]
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    /// The comments in the order they appear in the file, not including pictures.
+    #[must_use]
+    pub fn comments(&self) -> &[(String, String)] {
+        &self.comments
+    }
+
+    /// The values of a comment. Comment names are not case sensitive.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Vec<&str> {
+        self.comments
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Replaces the values of a comment, adding one comment per value.
+    pub fn set<S: AsRef<str>>(&mut self, key: &str, values: impl IntoIterator<Item = S>) {
+        self.remove(key);
+        for value in values {
+            self.comments
+                .push((key.to_string(), value.as_ref().to_string()));
+        }
+    }
+
+    /// Removes all the values of a comment.
+    pub fn remove(&mut self, key: &str) {
+        self.comments.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+
+    /// The pictures in the file.
+    #[must_use]
+    pub fn pictures(&self) -> &[OggPicture] {
+        &self.pictures
+    }
+
+    /// Adds a picture, replacing any picture of the same type.
+    pub fn add_picture(&mut self, picture: OggPicture) {
+        self.remove_picture_type(picture.picture_type);
+        self.pictures.push(picture);
+    }
+
+    /// Removes the pictures of the given type.
+    pub fn remove_picture_type(&mut self, picture_type: u32) {
+        self.pictures.retain(|p| p.picture_type != picture_type);
+    }
+
+    /// Writes the comments and pictures back to the file they were read from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or written, or has changed in a way that means the
+    /// comment header can't be replaced.
+    pub fn save(&self) -> Result<()> {
+        self.write_to_path(&self.path)
+    }
+
+    /// Writes the audio from the file the comments were read from, with the current comments and pictures,
+    /// to the given file. The file is written to a temporary file first, so it is left as it was if the write fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files can't be read or written.
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("Unable to read {}", self.path.display()))?;
+        let stream = Stream::parse(&bytes)?;
+
+        let mut comments = self.comments.clone();
+        comments.extend(
+            self.pictures
+                .iter()
+                .map(|p| (PICTURE_KEY.to_string(), encode_picture(p))),
+        );
+        let comment_packet = encode_comments(self.codec, &self.vendor, &comments);
+
+        let output = stream.rewrite(&bytes, comment_packet)?;
+        shared::write_file_atomically(path.as_ref(), |writer| Ok(writer.write_all(&output)?))
+    }
+}
+
+/// An Ogg page, pointing into the bytes of the file.
+#[derive(Debug, Clone)]
+struct Page {
+    /// Where the page starts in the file.
+    offset: usize,
+
+    /// The length of the page, including the header.
+    len: usize,
+
+    /// The flags for continued packets and the beginning and end of the stream.
+    header_type: u8,
+
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+
+    /// The lacing values, ie. the length of each segment.
+    segments: Vec<u8>,
+}
+
+impl Page {
+    /// Reads the page starting at the offset.
+    fn parse(bytes: &[u8], offset: usize) -> Result<Self> {
+        let header = bytes
+            .get(offset..offset + 27)
+            .context("Truncated Ogg page")?;
+        if &header[..4] != b"OggS" {
+            bail!("No Ogg page found at offset {offset}");
+        }
+        let segment_count = usize::from(header[26]);
+        let segments = bytes
+            .get(offset + 27..offset + 27 + segment_count)
+            .context("Truncated Ogg page")?
+            .to_vec();
+        let len = 27 + segment_count + segments.iter().map(|s| usize::from(*s)).sum::<usize>();
+        if offset + len > bytes.len() {
+            bail!("Truncated Ogg page at offset {offset}");
+        }
+
+        Ok(Self {
+            offset,
+            len,
+            header_type: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into()?),
+            serial: u32::from_le_bytes(header[14..18].try_into()?),
+            sequence: u32::from_le_bytes(header[18..22].try_into()?),
+            segments,
+        })
+    }
+
+    /// The data in the page, after the header and lacing values.
+    fn data<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        &bytes[self.offset + 27 + self.segments.len()..self.offset + self.len]
+    }
+
+    /// Builds a page, including the checksum.
+    fn build(
+        header_type: u8,
+        granule: u64,
+        serial: u32,
+        sequence: u32,
+        segments: &[u8],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut page = Vec::with_capacity(27 + segments.len() + data.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(header_type);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        #[allow(clippy::cast_possible_truncation)] // There are never more than 255 segments
+        page.push(segments.len() as u8);
+        page.extend_from_slice(segments);
+        page.extend_from_slice(data);
+        set_checksum(&mut page);
+        page
+    }
+}
+
+/// The header packets of the first logical stream in the file and the pages holding them.
+#[derive(Debug)]
+struct Stream {
+    codec: OggCodec,
+    serial: u32,
+
+    /// The identification, comment and (for Vorbis) setup headers.
+    packets: Vec<Vec<u8>>,
+
+    /// The pages holding the header packets.
+    header_pages: Vec<Page>,
+
+    /// Where the first page after the header pages starts.
+    audio_offset: usize,
+}
+
+impl Stream {
+    /// Reads the header packets at the start of the file.
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let first = Page::parse(bytes, 0).context("Not an Ogg file")?;
+        let first_data = first.data(bytes);
+        let codec = if first_data.starts_with(b"\x01vorbis") {
+            OggCodec::Vorbis
+        } else if first_data.starts_with(b"OpusHead") {
+            OggCodec::Opus
+        } else {
+            bail!("Unsupported Ogg codec. Only Vorbis and Opus are supported.");
+        };
+        let serial = first.serial;
+
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        let mut packet = Vec::new();
+        let mut header_pages = Vec::new();
+        let mut offset = 0;
+        while packets.len() < codec.header_count() {
+            let page = Page::parse(bytes, offset)?;
+            offset += page.len;
+            if page.serial != serial {
+                bail!("Ogg files with more than one stream are not supported.");
+            }
+
+            let mut data = page.data(bytes);
+            for (idx, &segment) in page.segments.iter().enumerate() {
+                let (segment_data, rest) = data.split_at(usize::from(segment));
+                packet.extend_from_slice(segment_data);
+                data = rest;
+                if segment < 255 {
+                    packets.push(std::mem::take(&mut packet));
+                    if packets.len() == codec.header_count() && idx + 1 < page.segments.len() {
+                        bail!("The {codec} headers don't end on a page boundary.");
+                    }
+                }
+            }
+            header_pages.push(page);
+        }
+        if header_pages[0]
+            .segments
+            .iter()
+            .filter(|s| **s < 255)
+            .count()
+            != 1
+        {
+            bail!("The {codec} identification header must be on a page of its own.");
+        }
+        if !packets[1].starts_with(codec.comment_magic()) {
+            bail!("No {codec} comment header found.");
+        }
+
+        Ok(Self {
+            codec,
+            serial,
+            packets,
+            header_pages,
+            audio_offset: offset,
+        })
+    }
+
+    /// Gets the stream information from the identification header and the granule position of the last page.
+    fn info(&self, bytes: &[u8]) -> OggStreamInfo {
+        let ident = &self.packets[0];
+        let read_u32 = |at: usize| {
+            ident
+                .get(at..at + 4)
+                .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let last_granule = self.last_granule(bytes);
+        let mut info = OggStreamInfo::default();
+        let samples = match self.codec {
+            OggCodec::Vorbis => {
+                info.channels = ident.get(11).copied().unwrap_or_default();
+                info.sample_rate = read_u32(12);
+                let nominal = read_u32(20);
+                info.bitrate = (nominal > 0 && nominal < u32::MAX / 2).then_some(nominal);
+                (last_granule, u64::from(info.sample_rate))
+            }
+            OggCodec::Opus => {
+                info.channels = ident.get(9).copied().unwrap_or_default();
+                let pre_skip = ident
+                    .get(10..12)
+                    .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]));
+                info.sample_rate = read_u32(12);
+                (last_granule.saturating_sub(u64::from(pre_skip)), 48_000)
+            }
+        };
+        info.duration_ms = samples
+            .0
+            .saturating_mul(1000)
+            .checked_div(samples.1)
+            .unwrap_or_default();
+        if info.bitrate.is_none() && info.duration_ms > 0 {
+            let audio_bits = (bytes.len() - self.audio_offset) as u64 * 8 * 1000;
+            info.bitrate = u32::try_from(audio_bits / info.duration_ms).ok();
+        }
+
+        info
+    }
+
+    /// Gets the granule position of the last page in the stream, ie. the number of samples.
+    fn last_granule(&self, bytes: &[u8]) -> u64 {
+        let mut granule = 0;
+        let mut offset = self.audio_offset;
+        while let Ok(page) = Page::parse(bytes, offset) {
+            if page.serial == self.serial && page.granule != u64::MAX {
+                granule = page.granule;
+            }
+            offset += page.len;
+        }
+        granule
+    }
+
+    /// Checks that the pages after the headers all belong to this stream. Chained files (streams one after the other)
+    /// and multiplexed files (streams interleaved) can't be rewritten, as only the first stream would be renumbered.
+    fn check_single_stream(&self, bytes: &[u8]) -> Result<()> {
+        let mut offset = self.audio_offset;
+        while offset < bytes.len() {
+            let page = Page::parse(bytes, offset)?;
+            if page.serial != self.serial || page.header_type & BEGINNING_OF_STREAM != 0 {
+                bail!("Ogg files with more than one stream, eg. chained or multiplexed files, are not supported.");
+            }
+            offset += page.len;
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Builds the file with a new comment header. The identification header page is kept as-is,
+    /// the comment (and setup) headers are written to new pages, and the following pages are renumbered.
+    fn rewrite(&self, bytes: &[u8], comment_packet: Vec<u8>) -> Result<Vec<u8>> {
+        self.check_single_stream(bytes)?;
+
+        let first = &self.header_pages[0];
+        let mut packets = self.packets[1..].to_vec();
+        packets[0] = comment_packet;
+
+        let new_pages = paginate(&packets, self.serial, first.sequence + 1);
+        let old_count = u32::try_from(self.header_pages.len() - 1)?;
+        let new_count = u32::try_from(new_pages.len())?;
+
+        let mut output = Vec::with_capacity(bytes.len() + 1024);
+        output.extend_from_slice(&bytes[first.offset..first.offset + first.len]);
+        for page in new_pages {
+            output.extend_from_slice(&page);
+        }
+
+        let mut offset = self.audio_offset;
+        while offset < bytes.len() {
+            let page = Page::parse(bytes, offset)?;
+            let mut raw = bytes[offset..offset + page.len].to_vec();
+            if new_count != old_count {
+                let sequence = page
+                    .sequence
+                    .wrapping_add(new_count)
+                    .wrapping_sub(old_count);
+                raw[18..22].copy_from_slice(&sequence.to_le_bytes());
+                set_checksum(&mut raw);
+            }
+            output.extend_from_slice(&raw);
+            offset += page.len;
+        }
+
+        Ok(output)
+    }
+}
+
+/// Splits the packets into pages. The last page ends with the last packet.
+fn paginate(packets: &[Vec<u8>], serial: u32, first_sequence: u32) -> Vec<Vec<u8>> {
+    // The lacing values for each packet
+    let mut lacing: Vec<(u8, bool)> = Vec::new();
+    for packet in packets {
+        let full = packet.len() / 255;
+        lacing.extend(std::iter::repeat_n((255, false), full));
+        #[allow(clippy::cast_possible_truncation)] // Always less than 255
+        lacing.push(((packet.len() % 255) as u8, true));
+    }
+    let data: Vec<u8> = packets.concat();
+
+    let mut pages = Vec::new();
+    let mut data_offset = 0;
+    let mut continued = false;
+    for (sequence, chunk) in (first_sequence..).zip(lacing.chunks(MAX_SEGMENTS)) {
+        let segments: Vec<u8> = chunk.iter().map(|(s, _)| *s).collect();
+        let len: usize = segments.iter().map(|s| usize::from(*s)).sum();
+        let packet_ends = chunk.iter().any(|(_, end)| *end);
+        let granule = if packet_ends { 0 } else { u64::MAX };
+        let header_type = u8::from(continued);
+
+        pages.push(Page::build(
+            header_type,
+            granule,
+            serial,
+            sequence,
+            &segments,
+            &data[data_offset..data_offset + len],
+        ));
+        data_offset += len;
+        continued = chunk.last().is_some_and(|(_, end)| !end);
+    }
+
+    pages
+}
+
+/// Sets the checksum of a page. The checksum is calculated with the checksum field set to zero.
+fn set_checksum(page: &mut [u8]) {
+    page[22..26].copy_from_slice(&[0; 4]);
+    let crc = crc32(page);
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// The CRC-32 used by Ogg: polynomial 0x04c11db7, no reflection, initial value and final XOR of zero.
+fn crc32(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, byte| {
+        let mut crc = crc ^ (u32::from(*byte) << 24);
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x04c1_1db7
+            };
+        }
+        crc
+    })
+}
+
+/// Reads the vendor string and the comments from a comment header packet.
+fn decode_comments(codec: OggCodec, packet: &[u8]) -> Result<(String, Vec<(String, String)>)> {
+    let mut reader = Reader::new(&packet[codec.comment_magic().len()..]);
+    let vendor = reader.string_le()?;
+    let count = reader.u32_le()?;
+
+    let mut comments = Vec::new();
+    for _ in 0..count {
+        let comment = reader.string_le()?;
+        match comment.split_once('=') {
+            Some((key, value)) => comments.push((key.to_string(), value.to_string())),
+            None => log::warn!("Skipping comment without a name: {comment}"),
+        }
+    }
+
+    Ok((vendor, comments))
+}
+
+/// Builds a comment header packet.
+fn encode_comments(codec: OggCodec, vendor: &str, comments: &[(String, String)]) -> Vec<u8> {
+    let mut packet = codec.comment_magic().to_vec();
+    push_string_le(&mut packet, vendor);
+    push_u32_le(&mut packet, comments.len());
+    for (key, value) in comments {
+        push_string_le(&mut packet, &format!("{key}={value}"));
+    }
+    if codec == OggCodec::Vorbis {
+        // Framing bit
+        packet.push(1);
+    }
+    packet
+}
+
+/// Reads a picture from the base64 encoded value of a `METADATA_BLOCK_PICTURE` comment.
+fn decode_picture(value: &str) -> Result<OggPicture> {
+    let bytes = base64_decode(value)?;
+    let mut reader = Reader::new(&bytes);
+    let picture_type = reader.u32_be()?;
+    let mime_len = reader.u32_be()?;
+    let mime_type = String::from_utf8_lossy(reader.take(mime_len)?).to_string();
+    let description_len = reader.u32_be()?;
+    let description = String::from_utf8_lossy(reader.take(description_len)?).to_string();
+    let width = reader.u32_be()?;
+    let height = reader.u32_be()?;
+    let depth = reader.u32_be()?;
+    let colors = reader.u32_be()?;
+    let data_len = reader.u32_be()?;
+    let data = reader.take(data_len)?.to_vec();
+
+    Ok(OggPicture {
+        picture_type,
+        mime_type,
+        description,
+        width,
+        height,
+        depth,
+        colors,
+        data,
+    })
+}
+
+/// Builds the base64 encoded value of a `METADATA_BLOCK_PICTURE` comment.
+fn encode_picture(picture: &OggPicture) -> String {
+    let mut bytes = Vec::with_capacity(picture.data.len() + 64);
+    let mut push_u32_be = |v: u32| bytes.extend_from_slice(&v.to_be_bytes());
+    push_u32_be(picture.picture_type);
+    push_u32_be(u32::try_from(picture.mime_type.len()).unwrap_or_default());
+    bytes.extend_from_slice(picture.mime_type.as_bytes());
+    bytes.extend_from_slice(
+        &u32::try_from(picture.description.len())
+            .unwrap_or_default()
+            .to_be_bytes(),
+    );
+    bytes.extend_from_slice(picture.description.as_bytes());
+    for value in [picture.width, picture.height, picture.depth, picture.colors] {
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    bytes.extend_from_slice(
+        &u32::try_from(picture.data.len())
+            .unwrap_or_default()
+            .to_be_bytes(),
+    );
+    bytes.extend_from_slice(&picture.data);
+
+    base64_encode(&bytes)
+}
+
+/// Appends a length-prefixed string.
+fn push_string_le(packet: &mut Vec<u8>, value: &str) {
+    push_u32_le(packet, value.len());
+    packet.extend_from_slice(value.as_bytes());
+}
+
+/// Appends a length or count as a little-endian 32-bit number.
+fn push_u32_le(packet: &mut Vec<u8>, value: usize) {
+    packet.extend_from_slice(&u32::try_from(value).unwrap_or_default().to_le_bytes());
+}
+
+/// Reads numbers and strings from a packet, failing if it is too short.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: u32) -> Result<&'a [u8]> {
+        let len = usize::try_from(len)?;
+        if len > self.bytes.len() {
+            bail!("Unexpected end of the comment header");
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32_le(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u32_be(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string_le(&mut self) -> Result<String> {
+        let len = self.u32_le()?;
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+}
+
+/// The characters used for base64.
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes the bytes as base64, with padding.
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if i <= chunk.len() {
+                encoded.push(char::from(BASE64_CHARS[((n >> shift) & 0x3f) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes base64, ignoring whitespace and padding.
+fn base64_decode(value: &str) -> Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(value.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in value
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let Some(index) = BASE64_CHARS.iter().position(|b| *b == c) else {
+            bail!("Invalid base64 character '{}'", char::from(c));
+        };
+        buffer = (buffer << 6) | u32::try_from(index)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push(u8::try_from((buffer >> bits) & 0xff)?);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TestFiles;
+
+    /// Checks that every page in the file has the right checksum and that the sequence numbers follow on.
+    fn check_pages(bytes: &[u8]) -> usize {
+        let mut offset = 0;
+        let mut count = 0;
+        while offset < bytes.len() {
+            let page = Page::parse(bytes, offset).unwrap();
+            let mut raw = bytes[offset..offset + page.len].to_vec();
+            let crc = u32::from_le_bytes(raw[22..26].try_into().unwrap());
+            set_checksum(&mut raw);
+            assert_eq!(crc.to_le_bytes(), raw[22..26]);
+            assert_eq!(page.sequence, u32::try_from(count).unwrap());
+            offset += page.len;
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    /// Tests the Ogg checksum against a known value.
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0x89a1_897f);
+    }
+
+    #[test]
+    /// Tests base64 encoding and decoding, with and without padding.
+    fn test_base64() {
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+        assert_eq!(base64_encode(b"Ma"), "TWE=");
+        assert_eq!(base64_encode(b"M"), "TQ==");
+        assert_eq!(base64_decode("TWFu").unwrap(), b"Man");
+        assert_eq!(base64_decode("TWE=").unwrap(), b"Ma");
+        assert_eq!(base64_decode("TQ==").unwrap(), b"M");
+        assert!(base64_decode("T*E=").is_err());
+    }
+
+    #[test]
+    /// Tests that comments and pictures are written and read back, and that the audio pages are renumbered
+    /// when the comment header grows.
+    fn test_read_write() {
+        let files = TestFiles::new();
+        let path = files.copy("silence.ogg", "test.ogg");
+        assert_eq!(check_pages(&fs::read(&path).unwrap()), 4);
+
+        let mut ogg = OggFile::read_from_path(&path).unwrap();
+        assert_eq!(ogg.codec(), OggCodec::Vorbis);
+        assert_eq!(ogg.vendor(), "test encoder");
+        assert_eq!(ogg.get("artist"), vec!["Simon"]);
+        let info = ogg.stream_info();
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 44_100);
+        assert_eq!(info.bitrate, Some(128_000));
+        assert_eq!(info.duration_ms, 2000);
+
+        ogg.set("ARTIST", ["Simon", "Garfunkel"]);
+        ogg.set("TITLE", ["The Boxer"]);
+        ogg.add_picture(OggPicture {
+            picture_type: OggPicture::FRONT_COVER,
+            mime_type: "image/jpeg".to_string(),
+            data: vec![0xff; 100_000],
+            ..OggPicture::default()
+        });
+        ogg.save().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert!(check_pages(&bytes) > 4);
+        let ogg = OggFile::read_from_path(&path).unwrap();
+        assert_eq!(ogg.get("ARTIST"), vec!["Simon", "Garfunkel"]);
+        assert_eq!(ogg.get("TITLE"), vec!["The Boxer"]);
+        assert_eq!(ogg.pictures().len(), 1);
+        assert_eq!(ogg.pictures()[0].data.len(), 100_000);
+        assert_eq!(ogg.stream_info().duration_ms, 2000);
+
+        // And back to a smaller header
+        let mut ogg = ogg;
+        ogg.remove_picture_type(OggPicture::FRONT_COVER);
+        ogg.remove("TITLE");
+        ogg.save().unwrap();
+        assert_eq!(check_pages(&fs::read(&path).unwrap()), 4);
+        let ogg = OggFile::read_from_path(&path).unwrap();
+        assert!(ogg.pictures().is_empty());
+        assert!(ogg.get("TITLE").is_empty());
+    }
+
+    #[test]
+    /// Tests that files with more than one stream are left alone.
+    fn test_more_than_one_stream() {
+        let files = TestFiles::new();
+        let path = files.copy("silence.ogg", "test.ogg");
+        let single = fs::read(&path).unwrap();
+
+        // Another stream after the first one, or interleaved with it
+        let other = Page::build(BEGINNING_OF_STREAM, 0, 5678, 0, &[8], b"OpusHead");
+        let chained = [single.clone(), other.clone()].concat();
+        let audio = Stream::parse(&single).unwrap().audio_offset;
+        let multiplexed = [&single[..audio], &other, &single[audio..]].concat();
+
+        for bytes in [chained, multiplexed] {
+            fs::write(&path, &bytes).unwrap();
+            let mut ogg = OggFile::read_from_path(&path).unwrap();
+            assert_eq!(ogg.get("ARTIST"), vec!["Simon"]);
+            ogg.set("TITLE", ["The Boxer"]);
+            let err = ogg.save().unwrap_err();
+            assert!(err.to_string().contains("more than one stream"), "{err}");
+            assert_eq!(fs::read(&path).unwrap(), bytes);
+        }
+    }
+}
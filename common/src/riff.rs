@@ -0,0 +1,570 @@
+//! Reads the stream information from WAV and AIFF files, and reads and writes the `LIST/INFO` chunk in WAV files.
+//!
+//! The `ID3 ` chunk is read and written using the `id3` crate. When the `LIST/INFO` chunk is saved, the other
+//! chunks are copied to a new file as-is, so the audio data is never held in memory or changed.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::shared;
+
+/// The `LIST/INFO` items and the ID3 frames that hold the same values.
+/// `COMM` is the key `id3tag` uses for the comment, and `TRCK` holds the track number only.
+pub const RIFF_INFO_FRAMES: [(&str, &str); 9] = [
+    ("INAM", "TIT2"),
+    ("IART", "TPE1"),
+    ("IPRD", "TALB"),
+    ("ICRD", "TDRC"),
+    ("IGNR", "TCON"),
+    ("ITRK", "TRCK"),
+    ("ICMT", "COMM"),
+    ("ICOP", "TCOP"),
+    ("ISFT", "TSSE"),
+];
+
+/// The length of a chunk header: a 4-byte ID and a 4-byte size.
+const CHUNK_HEADER_LEN: u64 = 8;
+
+/// The chunk container formats we can read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RiffFormat {
+    /// A RIFF `WAVE` file. Chunk sizes are little-endian.
+    Wav,
+
+    /// An IFF `AIFF` or `AIFC` file. Chunk sizes are big-endian.
+    Aiff,
+}
+
+impl RiffFormat {
+    /// Reads a chunk size in the byte order used by the format.
+    const fn size(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Self::Wav => u32::from_le_bytes(bytes),
+            Self::Aiff => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+impl fmt::Display for RiffFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wav => write!(f, "WAV"),
+            Self::Aiff => write!(f, "AIFF"),
+        }
+    }
+}
+
+/// The stream information from the `fmt ` (WAV) or `COMM` (AIFF) chunk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiffStreamInfo {
+    /// The number of channels.
+    pub channels: u16,
+
+    /// The sample rate in Hz.
+    pub sample_rate: u32,
+
+    /// The number of bits per sample.
+    pub bits_per_sample: u16,
+
+    /// The duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// The stream information and `LIST/INFO` items of a WAV or AIFF file.
+#[derive(Debug, Clone)]
+pub struct RiffFile {
+    path: PathBuf,
+    format: RiffFormat,
+    info: RiffStreamInfo,
+    items: Vec<(String, String)>,
+}
+
+impl RiffFile {
+    /// Reads the stream information and, for WAV files, the `LIST/INFO` items from the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a WAV or AIFF file.
+    pub fn read_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Unable to read {}", path.display()))?,
+        );
+        let (format, chunks) = read_chunks(&mut reader)
+            .with_context(|| format!("Unable to read the chunks in {}", path.display()))?;
+
+        let chunk_data = |reader: &mut BufReader<File>, id: &[u8; 4]| -> Result<Option<Vec<u8>>> {
+            chunks
+                .iter()
+                .find(|c| &c.id == id)
+                .map(|c| c.read_data(reader))
+                .transpose()
+        };
+
+        let info = match format {
+            RiffFormat::Wav => {
+                let Some(fmt) = chunk_data(&mut reader, b"fmt ")? else {
+                    bail!("No fmt chunk found in {}", path.display());
+                };
+                let data_size = chunks
+                    .iter()
+                    .find(|c| &c.id == b"data")
+                    .map_or(0, |c| c.size);
+                wav_stream_info(&fmt, data_size)?
+            }
+            RiffFormat::Aiff => {
+                let Some(comm) = chunk_data(&mut reader, b"COMM")? else {
+                    bail!("No COMM chunk found in {}", path.display());
+                };
+                aiff_stream_info(&comm)?
+            }
+        };
+
+        let mut items = Vec::new();
+        if format == RiffFormat::Wav {
+            if let Some(chunk) = chunks.iter().find(|c| c.is_info) {
+                items = decode_info(&chunk.read_data(&mut reader)?);
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            info,
+            items,
+        })
+    }
+
+    /// The container format of the file.
+    #[must_use]
+    pub const fn format(&self) -> RiffFormat {
+        self.format
+    }
+
+    /// The stream information, eg. channels and duration.
+    #[must_use]
+    pub const fn stream_info(&self) -> &RiffStreamInfo {
+        &self.info
+    }
+
+    /// The `LIST/INFO` items in the order they appear in the file, eg. `("INAM", "Vogue")`.
+    #[must_use]
+    pub fn info_items(&self) -> &[(String, String)] {
+        &self.items
+    }
+
+    /// The value of a `LIST/INFO` item, eg. `INAM`.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.items
+            .iter()
+            .find(|(k, _)| k == id)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets the value of a `LIST/INFO` item, replacing any existing value.
+    pub fn set(&mut self, id: &str, value: &str) {
+        if let Some(item) = self.items.iter_mut().find(|(k, _)| k == id) {
+            item.1 = value.to_string();
+        } else {
+            self.items.push((id.to_string(), value.to_string()));
+        }
+    }
+
+    /// Removes a `LIST/INFO` item.
+    pub fn remove(&mut self, id: &str) {
+        self.items.retain(|(k, _)| k != id);
+    }
+
+    /// Removes all the `LIST/INFO` items. The chunk is removed from the file when it is saved.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Writes the `LIST/INFO` items back to the file the values were read from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is an AIFF file, or if the file can't be written.
+    pub fn save(&self) -> Result<()> {
+        self.write_to_path(&self.path)
+    }
+
+    /// Writes the file the values were read from to `path`, with the `LIST/INFO` chunk replaced by the current items.
+    /// The chunk is kept where it was, or added at the end of the file, and is left out if there are no items.
+    /// The file is read again, so any changes made to it since it was read, eg. a new `ID3 ` chunk, are kept.
+    /// The new file is written to a temporary file first, so the file is left as it was if the write fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is an AIFF file, or if the file can't be read or written.
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        if self.format == RiffFormat::Aiff {
+            bail!("AIFF files don't have a LIST/INFO chunk.");
+        }
+
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(&self.path)
+                .with_context(|| format!("Unable to read {}", self.path.display()))?,
+        );
+        let (_, chunks) = read_chunks(&mut reader)?;
+
+        // Nothing to do if there is no chunk to remove or write
+        if self.items.is_empty() && !chunks.iter().any(|c| c.is_info) {
+            if path != self.path {
+                fs::copy(&self.path, path)
+                    .with_context(|| format!("Unable to write {}", path.display()))?;
+            }
+            return Ok(());
+        }
+
+        let info_chunk = encode_info(&self.items);
+        shared::write_file_atomically(path, |writer| {
+            write_chunks(&mut reader, &chunks, &info_chunk, writer)
+        })
+    }
+}
+
+/// A chunk in the file.
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// The chunk ID, eg. `fmt `.
+    id: [u8; 4],
+
+    /// Where the chunk data starts in the file, after the header.
+    offset: u64,
+
+    /// The size of the chunk data, not including the pad byte.
+    size: u32,
+
+    /// `true` if this is a `LIST` chunk of type `INFO`.
+    is_info: bool,
+}
+
+impl Chunk {
+    /// Reads the chunk data. The data is cut short if the file is.
+    fn read_data(&self, reader: &mut (impl Read + Seek)) -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(self.offset))?;
+        let mut data = Vec::new();
+        reader.take(u64::from(self.size)).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// The length of the chunk in the file, including the header and the pad byte.
+    const fn len(&self) -> u64 {
+        CHUNK_HEADER_LEN + self.size as u64 + (self.size & 1) as u64
+    }
+}
+
+/// Reads the format and the chunk headers of a WAV or AIFF file.
+fn read_chunks(reader: &mut (impl Read + Seek)) -> Result<(RiffFormat, Vec<Chunk>)> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut header = [0_u8; 12];
+    reader
+        .read_exact(&mut header)
+        .context("The file is too short.")?;
+    let format = match (&header[..4], &header[8..]) {
+        (b"RIFF", b"WAVE") => RiffFormat::Wav,
+        (b"FORM", b"AIFF" | b"AIFC") => RiffFormat::Aiff,
+        _ => bail!("Not a WAV or AIFF file."),
+    };
+
+    // Ignore anything after the end of the root chunk
+    let root_size = format.size([header[4], header[5], header[6], header[7]]);
+    let end = file_len.min(CHUNK_HEADER_LEN + u64::from(root_size));
+
+    let mut chunks = Vec::new();
+    let mut position = 12;
+    while position + CHUNK_HEADER_LEN <= end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_header = [0_u8; 8];
+        reader.read_exact(&mut chunk_header)?;
+
+        let mut id = [0_u8; 4];
+        id.copy_from_slice(&chunk_header[..4]);
+        let size = format.size([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]);
+
+        let mut list_type = [0_u8; 4];
+        let is_info = format == RiffFormat::Wav
+            && &id == b"LIST"
+            && size >= 4
+            && reader.read_exact(&mut list_type).is_ok()
+            && &list_type == b"INFO";
+
+        let chunk = Chunk {
+            id,
+            offset: position + CHUNK_HEADER_LEN,
+            size,
+            is_info,
+        };
+        position += chunk.len();
+        chunks.push(chunk);
+    }
+
+    Ok((format, chunks))
+}
+
+/// Writes the chunks to the new WAV file, with the `LIST/INFO` chunk replaced by `info_chunk`.
+/// The chunk is left out if `info_chunk` is empty.
+fn write_chunks(
+    reader: &mut (impl Read + Seek),
+    chunks: &[Chunk],
+    info_chunk: &[u8],
+    writer: &mut (impl Write + Seek),
+) -> Result<()> {
+    writer.write_all(b"RIFF\0\0\0\0WAVE")?;
+
+    let mut info_written = false;
+    for chunk in chunks {
+        if chunk.is_info {
+            if !info_written {
+                writer.write_all(info_chunk)?;
+                info_written = true;
+            }
+        } else {
+            reader.seek(SeekFrom::Start(chunk.offset - CHUNK_HEADER_LEN))?;
+            io::copy(&mut reader.by_ref().take(chunk.len()), writer)?;
+        }
+    }
+    if !info_written {
+        writer.write_all(info_chunk)?;
+    }
+
+    // The size of the RIFF chunk is everything after its header
+    let riff_size = writer.stream_position()? - CHUNK_HEADER_LEN;
+    let Ok(riff_size) = u32::try_from(riff_size) else {
+        bail!("The file is too large for a RIFF chunk.");
+    };
+    writer.seek(SeekFrom::Start(4))?;
+    writer.write_all(&riff_size.to_le_bytes())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Gets the stream information from the `fmt ` chunk, and the duration from the size of the `data` chunk.
+fn wav_stream_info(fmt: &[u8], data_size: u32) -> Result<RiffStreamInfo> {
+    if fmt.len() < 16 {
+        bail!("The fmt chunk is too short.");
+    }
+
+    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let byte_rate = u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]);
+    let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+    let duration_ms = (u64::from(data_size) * 1000)
+        .checked_div(u64::from(byte_rate))
+        .unwrap_or_default();
+
+    Ok(RiffStreamInfo {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        duration_ms,
+    })
+}
+
+/// Gets the stream information from the `COMM` chunk.
+fn aiff_stream_info(comm: &[u8]) -> Result<RiffStreamInfo> {
+    if comm.len() < 18 {
+        bail!("The COMM chunk is too short.");
+    }
+
+    let channels = u16::from_be_bytes([comm[0], comm[1]]);
+    let frames = u32::from_be_bytes([comm[2], comm[3], comm[4], comm[5]]);
+    let bits_per_sample = u16::from_be_bytes([comm[6], comm[7]]);
+    let mut rate = [0_u8; 10];
+    rate.copy_from_slice(&comm[8..18]);
+    let sample_rate = extended_to_u32(rate);
+    let duration_ms = (u64::from(frames) * 1000)
+        .checked_div(u64::from(sample_rate))
+        .unwrap_or_default();
+
+    Ok(RiffStreamInfo {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        duration_ms,
+    })
+}
+
+/// Converts the 80-bit extended precision number used for the AIFF sample rate into a whole number.
+fn extended_to_u32(bytes: [u8; 10]) -> u32 {
+    let exponent = i32::from(u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff);
+    let mut mantissa = [0_u8; 8];
+    mantissa.copy_from_slice(&bytes[2..]);
+    let mantissa = u64::from_be_bytes(mantissa);
+
+    // The value is mantissa * 2^(exponent - 16383 - 63). Sample rates are never large enough to need a left shift.
+    let shift = 16383 + 63 - exponent;
+    if mantissa == 0 || !(0..64).contains(&shift) {
+        return 0;
+    }
+    u32::try_from(mantissa >> shift).unwrap_or(u32::MAX)
+}
+
+/// Decodes the items in a `LIST/INFO` chunk. The values are UTF-8, or Latin-1 if they aren't valid UTF-8.
+fn decode_info(data: &[u8]) -> Vec<(String, String)> {
+    let mut items = Vec::new();
+    let mut position = 4; // Skip "INFO"
+    while position + 8 <= data.len() {
+        let id = String::from_utf8_lossy(&data[position..position + 4]).to_string();
+        let size = u32::from_le_bytes([
+            data[position + 4],
+            data[position + 5],
+            data[position + 6],
+            data[position + 7],
+        ]) as usize;
+        let start = position + 8;
+        let end = data.len().min(start + size);
+
+        let bytes = &data[start..end];
+        let bytes = &bytes[..bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len())];
+        let value = String::from_utf8(bytes.to_vec())
+            .unwrap_or_else(|_| bytes.iter().map(|b| char::from(*b)).collect());
+        items.push((id, value));
+
+        position = start + size + (size & 1);
+    }
+    items
+}
+
+/// Encodes the items into a `LIST/INFO` chunk, including the chunk header. Returns nothing if there are no items.
+fn encode_info(items: &[(String, String)]) -> Vec<u8> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let mut data = b"INFO".to_vec();
+    for (id, value) in items {
+        let mut id_bytes = [b' '; 4];
+        for (i, b) in id.bytes().take(4).enumerate() {
+            id_bytes[i] = b;
+        }
+
+        // Values are nul-terminated, and padded to an even length
+        let size = value.len() + 1;
+        data.extend_from_slice(&id_bytes);
+        data.extend_from_slice(&u32::try_from(size).unwrap_or(u32::MAX).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+        data.push(0);
+        if size % 2 == 1 {
+            data.push(0);
+        }
+    }
+
+    let mut chunk = b"LIST".to_vec();
+    chunk.extend_from_slice(&u32::try_from(data.len()).unwrap_or(u32::MAX).to_le_bytes());
+    chunk.extend_from_slice(&data);
+    chunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_files::TestFiles;
+
+    #[test]
+    /// Tests the conversion of the AIFF sample rate.
+    fn test_extended_to_u32() {
+        assert_eq!(
+            extended_to_u32([0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]),
+            44_100
+        );
+        assert_eq!(
+            extended_to_u32([0x40, 0x0f, 0xbb, 0x80, 0, 0, 0, 0, 0, 0]),
+            96_000
+        );
+        assert_eq!(extended_to_u32([0; 10]), 0);
+    }
+
+    #[test]
+    /// Tests reading the stream information from a `COMM` chunk.
+    fn test_aiff_stream_info() {
+        let mut comm = Vec::new();
+        comm.extend_from_slice(&2_u16.to_be_bytes());
+        comm.extend_from_slice(&88_200_u32.to_be_bytes());
+        comm.extend_from_slice(&24_u16.to_be_bytes());
+        comm.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+
+        let info = aiff_stream_info(&comm).unwrap();
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 44_100);
+        assert_eq!(info.bits_per_sample, 24);
+        assert_eq!(info.duration_ms, 2000);
+        assert!(aiff_stream_info(&comm[..10]).is_err());
+    }
+
+    #[test]
+    /// Tests reading and writing the `INFO` chunk, and that the other chunks are kept as-is.
+    fn test_read_write() {
+        let files = TestFiles::new();
+        let path = files.copy("silence.wav", "test.wav");
+
+        let mut wav = RiffFile::read_from_path(&path).unwrap();
+        assert_eq!(wav.format(), RiffFormat::Wav);
+        assert_eq!(
+            wav.stream_info(),
+            &RiffStreamInfo {
+                channels: 2,
+                sample_rate: 44_100,
+                bits_per_sample: 16,
+                duration_ms: 100,
+            }
+        );
+        assert_eq!(wav.get("INAM"), Some("Vogue"));
+        assert_eq!(wav.get("IENG"), Some("Björn"));
+
+        wav.set("INAM", "Like a Prayer");
+        wav.set("ICRD", "1989");
+        wav.remove("IART");
+        wav.save().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(
+            u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize,
+            bytes.len() - 8
+        );
+        assert!(bytes.ends_with(&[0; 17_640]));
+
+        let mut wav = RiffFile::read_from_path(&path).unwrap();
+        let items: Vec<(&str, &str)> = wav
+            .info_items()
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                ("INAM", "Like a Prayer"),
+                ("IENG", "Björn"),
+                ("ICRD", "1989")
+            ]
+        );
+        assert_eq!(wav.stream_info().duration_ms, 100);
+
+        // Removing all the items removes the chunk
+        wav.clear();
+        wav.save().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert!(!bytes.windows(4).any(|w| w == b"LIST"));
+        assert_eq!(bytes.len(), 12 + 24 + 8 + 17_640);
+        assert!(RiffFile::read_from_path(&path)
+            .unwrap()
+            .info_items()
+            .is_empty());
+    }
+}
@@ -1,13 +1,16 @@
 //! Struct(s) and functions used across several other modules.
 
 use std::ffi::OsStr;
+use std::io;
 use std::path::Path;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
 use anyhow::{bail, Context, Result};
-use infer::{MatcherType, Type};
+use infer::{Infer, MatcherType, Type};
 
+use crate::apev2;
+use crate::dff;
 use crate::file_types::FileTypes;
 
 /// Expand glob patterns in a list of file arguments into actual file paths.
@@ -131,7 +134,7 @@ where
 /// - If we can't infer the file type from path, we give an error
 pub fn get_mime_type(filename: &str) -> Result<String> {
     // Read the file and check the mime type
-    let Some(file_type) = infer::get_from_path(filename)? else {
+    let Some(file_type) = infer_from_path(filename)? else {
         bail!("File type not supported");
     };
 
@@ -157,7 +160,7 @@ pub fn get_extension(filename: &str) -> String {
 /// - `infer::get_from_path()` fails
 pub fn get_file_type(filename: &str) -> Result<FileTypes> {
     // return the file type
-    let file_type = infer::get_from_path(filename)?;
+    let file_type = infer_from_path(filename)?;
     log::debug!("File type = {file_type:?}");
     let Some(file_type) = file_type else {
         bail!("File type not supported");
@@ -167,6 +170,7 @@ pub fn get_file_type(filename: &str) -> Result<FileTypes> {
 
     if file_type.matcher_type() == MatcherType::Audio
         || file_type.matcher_type() == MatcherType::Video
+        || file_type.matcher_type() == MatcherType::Custom
     {
         ft = audio_file_type(file_type);
         log::debug!("File type is {ft}");
@@ -201,10 +205,35 @@ fn audio_file_type(ft: Type) -> FileTypes {
         "audio/x-flac" => FileTypes::Flac,
         "audio/mpeg" => FileTypes::MP3,
         "audio/m4a" | "video/mp4" => FileTypes::M4A,
+        "audio/ogg" => FileTypes::Ogg,
+        "audio/opus" => FileTypes::Opus,
+        "audio/x-wav" => FileTypes::Wav,
+        "audio/x-aiff" => FileTypes::Aiff,
+        "audio/x-wavpack" => FileTypes::WavPack,
+        "audio/x-musepack" => FileTypes::Musepack,
+        "audio/x-tak" => FileTypes::Tak,
+        "audio/x-dff" => FileTypes::Dff,
         _ => FileTypes::Unknown,
     }
 }
 
+/// Infers the file type from the start of the file.
+/// The formats that the Infer crate doesn't know about, ie. DSDIFF and some of the `APEv2`-tagged formats,
+/// are added as custom matchers.
+///
+/// # Errors
+///
+/// - The file can't be read
+pub(crate) fn infer_from_path(filename: &str) -> io::Result<Option<Type>> {
+    let mut info = Infer::new();
+    info.add("audio/x-wavpack", "wv", apev2::is_wavpack);
+    info.add("audio/x-musepack", "mpc", apev2::is_musepack);
+    info.add("audio/x-tak", "tak", apev2::is_tak);
+    info.add("audio/x-dff", "dff", dff::is_dff);
+
+    info.get_from_path(filename)
+}
+
 /// Checks that the new filename pattern results in a unique file.
 ///
 /// Not perfect since the track title can occur multiple times on the same album.
@@ -473,6 +502,53 @@ pub fn path_to_string(p: std::path::PathBuf) -> String {
 // TESTS
 //------------------------------------------------------------------------------------
 
+/// Writes a file by writing to a temporary file in the same folder and then renaming it over `path`, so the
+/// original file is left as it was if the write fails halfway, eg. when the disk is full.
+/// The temporary file gets the permissions of the file it replaces.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file can't be written or renamed. The temporary file is removed.
+pub(crate) fn write_file_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut io::BufWriter<std::fs::File>) -> Result<()>,
+) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("No file name in {}", path.display()))?
+        .to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let written = write_temp_file(path, &temp_path, write)
+        .and_then(|()| std::fs::rename(&temp_path, path).map_err(anyhow::Error::from));
+    if written.is_err() {
+        std::fs::remove_file(&temp_path).ok();
+    }
+    written.with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// Writes the temporary file for `write_file_atomically` and makes sure it is on disk.
+fn write_temp_file(
+    path: &Path,
+    temp_path: &Path,
+    write: impl FnOnce(&mut io::BufWriter<std::fs::File>) -> Result<()>,
+) -> Result<()> {
+    let file = std::fs::File::create(temp_path)?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+
+    let mut writer = io::BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+    file.sync_all()?;
+
+    // Return safely
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -836,7 +912,7 @@ mod tests {
             let _ = std::fs::remove_file(&link_path);
             return;
         };
-        
+
         // Guard removes the symlink even if the assertion or expand_file_args panics.
         let _guard = TempPathGuard(link_path);
         let result = expand_file_args(std::iter::once(link_str_owned.as_str()));
@@ -846,4 +922,25 @@ mod tests {
             "dangling symlink with brackets in name was silently dropped"
         );
     }
+
+    #[test]
+    /// Tests that the file is replaced when the write succeeds, and left as it was when it fails.
+    fn test_write_file_atomically() {
+        use std::io::Write;
+
+        let files = crate::test_files::TestFiles::new();
+        let path = files.path("music.ogg");
+        std::fs::write(&path, b"old").unwrap();
+
+        write_file_atomically(&path, |writer| Ok(writer.write_all(b"new")?)).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+
+        let failed = write_file_atomically(&path, |writer| {
+            writer.write_all(b"half")?;
+            bail!("Disk full")
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(files.dir()).unwrap().count(), 1);
+    }
 }
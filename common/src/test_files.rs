@@ -0,0 +1,71 @@
+//! Gives the tests their own copies of the sample files in `testdata/`, so they can change them freely.
+//!
+//! The copies are made in a temporary folder that is removed when the `TestFiles` goes out of scope,
+//! including when an assert in the test fails.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The folder with the sample files.
+const TESTDATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata");
+
+/// A temporary folder with copies of the sample files.
+#[derive(Debug)]
+pub struct TestFiles {
+    /// The folder the copies are made in. Removed when dropped.
+    dir: tempfile::TempDir,
+}
+
+impl TestFiles {
+    /// Creates an empty temporary folder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the folder can't be created.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            dir: tempfile::tempdir().expect("Unable to create a temporary folder"),
+        }
+    }
+
+    /// The temporary folder.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The path of a file in the temporary folder. The file doesn't have to exist.
+    #[must_use]
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Copies a sample file from `testdata/` into the temporary folder with the given name, and returns its path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sample file can't be copied.
+    #[must_use]
+    pub fn copy(&self, sample: &str, name: &str) -> PathBuf {
+        let path = self.path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Unable to create the folder for the copy");
+        }
+        fs::copy(sample_path(sample), &path)
+            .unwrap_or_else(|err| panic!("Unable to copy {sample}: {err}"));
+        path
+    }
+}
+
+impl Default for TestFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The path of a sample file in `testdata/`.
+#[must_use]
+pub fn sample_path(sample: &str) -> PathBuf {
+    Path::new(TESTDATA).join(sample)
+}
@@ -0,0 +1,1299 @@
+use crate::{
+    need_split, ApeStreamInfo, DffFile, FileTypes, FilterValues, OggCodec, OggFile, OggPicture,
+    RiffFile, RiffFormat,
+};
+use anyhow::{bail, Result};
+use id3::{Tag, TagLike};
+use metaflac::block;
+use mp4ameta::{Data, Fourcc, FreeformIdent, Ident, Tag as Mp4Tag};
+use serde::Serialize;
+
+macro_rules! mp3_tags {
+    ($tags:ident, $field:ident, $self_ref:ident, $self_field:ident) => {
+        if let Some(field) = $tags.$field() {
+            $self_ref.$self_field = some_values(field.iter().map(ToString::to_string).collect());
+        }
+    };
+    ($tags:ident, $field:literal, $self_ref:ident, $self_field:ident) => {
+        if let Some(field) = $tags.get($field).and_then(|f| f.content().text_values()) {
+            $self_ref.$self_field = some_values(field.map(ToString::to_string).collect());
+        }
+    };
+}
+
+macro_rules! mp3_tag {
+    ($tags:ident, $field:ident, $self_ref:ident, $self_field:ident) => {
+        if let Some(field) = $tags.$field() {
+            $self_ref.$self_field = Some(field.to_string());
+        }
+    };
+    ($tags:ident, $field:literal, $self_ref:ident, $self_field:ident) => {
+        if let Some(field) = $tags.get($field) {
+            $self_ref.$self_field = Some(field.to_string());
+        }
+    };
+}
+
+macro_rules! mp3_tag_string {
+    ($tags:ident, $field:ident, $self_ref:ident, $self_field:ident) => {
+        if let Some(field) = $tags.$field() {
+            $self_ref.$self_field = Some(field.to_string());
+        }
+    };
+}
+
+macro_rules! ape_tags {
+    ($tags:ident, $items:literal, $self_ref:ident, $self_field:ident) => {
+        let values = ape_values(&$tags, $items);
+        if !values.is_empty() {
+            $self_ref.$self_field = flatten_vec(&values);
+        }
+    };
+}
+
+macro_rules! ape_values {
+    ($tags:ident, $items:literal, $self_ref:ident, $self_field:ident) => {
+        $self_ref.$self_field = some_values(ape_values(&$tags, $items));
+    };
+}
+
+macro_rules! mp4_tags {
+    ($tags:ident, $binding:literal, $self_ref:ident, $self_field:ident) => {
+        $self_ref.$self_field = flatten_vec(&mp4_values(&$tags, &Fourcc(*$binding)));
+    };
+}
+
+macro_rules! mp4_values {
+    ($tags:ident, $binding:literal, $self_ref:ident, $self_field:ident) => {
+        $self_ref.$self_field = some_values(mp4_values(&$tags, &Fourcc(*$binding)));
+    };
+}
+
+/// The identifier fields, using the generic (Vorbis comment style) names, and the description used for them
+/// in ID3 `TXXX` frames and MP4 freeform atoms.
+const IDENTIFIERS: [(&str, &str); 10] = [
+    ("ISRC", "ISRC"),
+    ("ISWC", "ISWC"),
+    ("BARCODE", "BARCODE"),
+    ("CATALOGNUMBER", "CATALOGNUMBER"),
+    ("MUSICBRAINZ_TRACKID", "MusicBrainz Track Id"),
+    ("MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id"),
+    ("MUSICBRAINZ_ARTISTID", "MusicBrainz Artist Id"),
+    ("MUSICBRAINZ_ALBUMARTISTID", "MusicBrainz Album Artist Id"),
+    ("MUSICBRAINZ_RELEASEGROUPID", "MusicBrainz Release Group Id"),
+    ("MUSICBRAINZ_DISCID", "MusicBrainz Disc Id"),
+];
+
+/// A struct to hold track information.
+///
+/// The artist, composer, genre and MusicBrainz artist ID fields can have more than one value, eg. one per artist.
+/// Other fields with more than one value are joined with "; ".
+#[derive(Serialize, Default, Debug)]
+#[allow(clippy::struct_field_names)]
+pub struct Track {
+    /// Path to the audio file.
+    pub path: Option<String>,
+
+    /// File format.
+    pub file_format: Option<FileTypes>,
+
+    /// File size in bytes
+    pub file_size: Option<u64>,
+
+    /// album artist
+    pub album_artist: Option<Vec<String>>,
+
+    /// default name on which album artist is sorted. Example: Artist is "Alicia Keys", but `artist_sort` may be "Keys, Alicia".
+    pub album_artist_sort: Option<Vec<String>>,
+
+    /// Album title.
+    pub album_title: Option<String>,
+
+    /// Album title sort.
+    pub album_title_sort: Option<String>,
+
+    /// Disc number, usually 1.
+    pub disc_number: Option<String>,
+
+    /// Total number of discs that comprise album, usually 1.
+    pub disc_count: Option<String>,
+
+    /// Track artist.
+    pub artist: Option<Vec<String>>,
+
+    /// Track artist sort.
+    pub artist_sort: Option<Vec<String>>,
+
+    /// Track title.
+    pub title: Option<String>,
+
+    /// Track title sort.
+    pub title_sort: Option<String>,
+
+    /// Track number.
+    pub track_number: Option<String>,
+
+    /// Total number of tracks.
+    pub track_count: Option<String>,
+
+    /// Track's genre.
+    pub genre: Option<Vec<String>>,
+
+    /// Track's composer(s).
+    pub composer: Option<Vec<String>>,
+
+    /// Track's composer sort.
+    pub composer_sort: Option<Vec<String>>,
+
+    /// Track date(s).
+    pub date: Option<String>,
+
+    /// Track comments.
+    pub comments: Option<String>,
+
+    /// International Standard Recording Code, eg. `AUAP08000041`.
+    pub isrc: Option<String>,
+
+    /// International Standard Musical Work Code, eg. `T-034.524.680-1`.
+    pub iswc: Option<String>,
+
+    /// Release barcode (UPC or EAN).
+    pub barcode: Option<String>,
+
+    /// Release catalog number.
+    pub catalog_number: Option<String>,
+
+    /// MusicBrainz recording ID.
+    pub musicbrainz_track_id: Option<String>,
+
+    /// MusicBrainz release ID.
+    pub musicbrainz_album_id: Option<String>,
+
+    /// MusicBrainz artist ID(s) of the track artist(s).
+    pub musicbrainz_artist_id: Option<Vec<String>>,
+
+    /// MusicBrainz artist ID(s) of the album artist(s).
+    pub musicbrainz_album_artist_id: Option<Vec<String>>,
+
+    /// MusicBrainz release group ID.
+    pub musicbrainz_release_group_id: Option<String>,
+
+    /// MusicBrainz disc ID.
+    pub musicbrainz_disc_id: Option<String>,
+
+    /// Duration in milliseconds
+    pub duration_ms: Option<u64>,
+
+    /// Bitrate.
+    pub bitrate: Option<u32>,
+
+    /// Track bits per sample.
+    pub bits_per_sample: Option<u8>,
+
+    /// Track sample rate.
+    pub sample_rate: Option<u32>,
+
+    /// Track channels.
+    pub channels: Option<u8>,
+
+    /// Track replaygain.
+    pub replaygain: Option<f32>,
+
+    /// Track replaygain peak.
+    pub replaygain_peak: Option<f32>,
+
+    /// Track MD5 sum.
+    pub md5: Option<String>,
+
+    /// The size of the front cover in bytes. Only used for the `--where` filter, and not exported.
+    #[serde(skip)]
+    pub front_cover_size: Option<usize>,
+
+    /// The size of the back cover in bytes. Only used for the `--where` filter, and not exported.
+    #[serde(skip)]
+    pub back_cover_size: Option<usize>,
+}
+
+impl Track {
+    /// Creates a new, empty `Track` struct.
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `Track` struct with a path to an audio file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the audio file.
+    ///
+    /// # Returns
+    ///
+    /// A `Track` struct with the path set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use common::Track;
+    /// let track = Track::from_path("/path/to/audio.flac".to_string());
+    /// ```
+    #[allow(dead_code)]
+    pub fn from_path(path: String) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the identifier fields, eg. ISRC and the MusicBrainz IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Gets the values of a field from its generic name and its `TXXX`/freeform description, eg. `("MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id")`.
+    fn read_identifiers(&mut self, values: impl Fn(&str, &str) -> Vec<String>) {
+        let [isrc, iswc, barcode, catalog_number, track_id, album_id, artist_id, album_artist_id, release_group_id, disc_id] =
+            IDENTIFIERS.map(|(generic, description)| values(generic, description));
+
+        self.isrc = flatten_vec(&isrc);
+        self.iswc = flatten_vec(&iswc);
+        self.barcode = flatten_vec(&barcode);
+        self.catalog_number = flatten_vec(&catalog_number);
+        self.musicbrainz_track_id = flatten_vec(&track_id);
+        self.musicbrainz_album_id = flatten_vec(&album_id);
+        self.musicbrainz_artist_id = some_values(artist_id);
+        self.musicbrainz_album_artist_id = some_values(album_artist_id);
+        self.musicbrainz_release_group_id = flatten_vec(&release_group_id);
+        self.musicbrainz_disc_id = flatten_vec(&disc_id);
+    }
+
+    /// Gets the values used to check the track against a `--where` filter: the exported fields, using the
+    /// column names, and the sizes of the cover art as `picture_front` and `picture_back`.
+    pub fn filter_values(&self) -> FilterValues {
+        let mut values = FilterValues::new();
+
+        values.insert("path", self.path.as_ref());
+        values.insert("file_format", self.file_format.map(|f| f.to_string()));
+        values.insert("file_size", self.file_size.map(|s| s.to_string()));
+        values.insert("album_artist", self.album_artist.iter().flatten());
+        values.insert("album_artist_sort", self.album_artist_sort.iter().flatten());
+        values.insert("album_title", self.album_title.as_ref());
+        values.insert("album_title_sort", self.album_title_sort.as_ref());
+        values.insert("disc_number", self.disc_number.as_ref());
+        values.insert("disc_count", self.disc_count.as_ref());
+        values.insert("artist", self.artist.iter().flatten());
+        values.insert("artist_sort", self.artist_sort.iter().flatten());
+        values.insert("title", self.title.as_ref());
+        values.insert("title_sort", self.title_sort.as_ref());
+        values.insert("track_number", self.track_number.as_ref());
+        values.insert("track_count", self.track_count.as_ref());
+        values.insert("genre", self.genre.iter().flatten());
+        values.insert("composer", self.composer.iter().flatten());
+        values.insert("composer_sort", self.composer_sort.iter().flatten());
+        values.insert("date", self.date.as_ref());
+        values.insert("comments", self.comments.as_ref());
+        values.insert("isrc", self.isrc.as_ref());
+        values.insert("iswc", self.iswc.as_ref());
+        values.insert("barcode", self.barcode.as_ref());
+        values.insert("catalog_number", self.catalog_number.as_ref());
+        values.insert("musicbrainz_track_id", self.musicbrainz_track_id.as_ref());
+        values.insert("musicbrainz_album_id", self.musicbrainz_album_id.as_ref());
+        values.insert(
+            "musicbrainz_artist_id",
+            self.musicbrainz_artist_id.iter().flatten(),
+        );
+        values.insert(
+            "musicbrainz_album_artist_id",
+            self.musicbrainz_album_artist_id.iter().flatten(),
+        );
+        values.insert(
+            "musicbrainz_release_group_id",
+            self.musicbrainz_release_group_id.as_ref(),
+        );
+        values.insert("musicbrainz_disc_id", self.musicbrainz_disc_id.as_ref());
+        values.insert("duration_ms", self.duration_ms.map(|d| d.to_string()));
+        values.insert("bitrate", self.bitrate.map(|b| b.to_string()));
+        values.insert(
+            "bits_per_sample",
+            self.bits_per_sample.map(|b| b.to_string()),
+        );
+        values.insert("sample_rate", self.sample_rate.map(|r| r.to_string()));
+        values.insert("channels", self.channels.map(|c| c.to_string()));
+        values.insert("replaygain", self.replaygain.map(|r| r.to_string()));
+        values.insert(
+            "replaygain_peak",
+            self.replaygain_peak.map(|p| p.to_string()),
+        );
+        values.insert("md5", self.md5.as_ref());
+        values.insert(
+            "picture_front",
+            self.front_cover_size.map(|s| s.to_string()),
+        );
+        values.insert("picture_back", self.back_cover_size.map(|s| s.to_string()));
+
+        values
+    }
+
+    /// Sets the sizes of the front and back covers from the pictures in an ID3 tag.
+    fn read_id3_pictures(&mut self, tag: &Tag) {
+        let size = |picture_type| {
+            tag.pictures()
+                .find(|p| p.picture_type == picture_type)
+                .map(|p| p.data.len())
+        };
+        self.front_cover_size = size(id3::frame::PictureType::CoverFront);
+        self.back_cover_size = size(id3::frame::PictureType::CoverBack);
+    }
+
+    /// Sets the tag fields from an ID3 tag, as found in MP3, DSDIFF, WAV and AIFF files.
+    fn read_id3_tag(&mut self, tag: &Tag) {
+        mp3_tags!(tag, "TPE2", self, album_artist);
+        mp3_tags!(tag, "TSO2", self, album_artist_sort);
+        mp3_tag!(tag, album, self, album_title);
+        mp3_tag!(tag, "TSOA", self, album_title_sort);
+        mp3_tag_string!(tag, disc, self, disc_number);
+        mp3_tag_string!(tag, total_discs, self, disc_count);
+        mp3_tags!(tag, artists, self, artist);
+        mp3_tags!(tag, "TSOP", self, artist_sort);
+        mp3_tag!(tag, title, self, title);
+        mp3_tag!(tag, "TSOT", self, title_sort);
+        mp3_tag_string!(tag, track, self, track_number);
+        mp3_tag_string!(tag, total_tracks, self, track_count);
+        mp3_tags!(tag, genres, self, genre);
+        mp3_tags!(tag, "TCOM", self, composer);
+        mp3_tags!(tag, "TSOC", self, composer_sort);
+        mp3_tag!(tag, "TDRL", self, date);
+        mp3_tag!(tag, "COMM", self, comments);
+        self.read_identifiers(|generic, description| id3_values(tag, generic, description));
+        self.read_id3_pictures(tag);
+    }
+
+    /// Sets the fields that are still empty from the `LIST/INFO` chunk of a WAV file.
+    fn read_riff_info(&mut self, riff: &RiffFile) {
+        let value = |id: &str| riff.get(id).map(str::trim).filter(|v| !v.is_empty());
+
+        if self.title.is_none() {
+            self.title = value("INAM").map(ToString::to_string);
+        }
+        if self.artist.is_none() {
+            self.artist = value("IART").map(|v| vec![v.to_string()]);
+        }
+        if self.album_title.is_none() {
+            self.album_title = value("IPRD").map(ToString::to_string);
+        }
+        if self.date.is_none() {
+            self.date = value("ICRD").map(ToString::to_string);
+        }
+        if self.genre.is_none() {
+            self.genre = value("IGNR").map(|v| vec![v.to_string()]);
+        }
+        if self.comments.is_none() {
+            self.comments = value("ICMT").map(ToString::to_string);
+        }
+        if self.track_number.is_none() {
+            if let Some(track) = value("ITRK") {
+                let (number, count) = track.split_once('/').unwrap_or((track, ""));
+                self.track_number = Some(number.trim().to_string());
+                if self.track_count.is_none() && !count.trim().is_empty() {
+                    self.track_count = Some(count.trim().to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Handles reading of various audio file formats.
+pub trait Reader {
+    fn read(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    // May move the following functions out of the trait so the trait stays simple.
+
+    fn read_flac(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_mp3(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_mp4(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_ape(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_dsf(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_dff(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_ogg(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+
+    fn read_riff(&mut self) -> Result<()>
+    where
+        Self: std::marker::Sized;
+}
+
+impl Reader for Track {
+    /// Reads an audio file. The function will determine the file format and call the appropriate
+    /// detailed function to read the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file type is not supported.
+    ///
+    fn read(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+
+        let metadata = std::fs::metadata(path)?;
+        self.file_size = Some(metadata.len());
+
+        let file_type = FileTypes::from_filename(path);
+        log::debug!("File type: {file_type}");
+        match file_type {
+            FileTypes::Flac => self.read_flac()?,
+            FileTypes::MP3 => self.read_mp3()?,
+            FileTypes::M4A => self.read_mp4()?,
+            FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+                self.read_ape()?;
+            }
+            FileTypes::Dsf => self.read_dsf()?,
+            FileTypes::Dff => self.read_dff()?,
+            FileTypes::Ogg | FileTypes::Opus => self.read_ogg()?,
+            FileTypes::Wav | FileTypes::Aiff => self.read_riff()?,
+            FileTypes::Unknown => {
+                self.file_format = Some(FileTypes::Unknown);
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from a FLAC file.
+    fn read_flac(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let tags = metaflac::Tag::read_from_path(path)?;
+
+        self.file_format = Some(FileTypes::Flac);
+
+        for block in tags.blocks() {
+            match block {
+                block::Block::VorbisComment(vc) => {
+                    let vcc = &vc.comments;
+                    log::debug!("Vorbis comments: {vcc:?}");
+
+                    // While there are native functions for some of these (e.g. vc.album_artist()), they
+                    // don't return the values in the format expected, so the values would need to be converted.
+                    // It is just easier to do it this way. This may change in the future.
+                    self.album_artist =
+                        some_values(vcc.get("ALBUMARTIST").cloned().unwrap_or_default());
+                    self.album_artist_sort =
+                        some_values(vcc.get("ALBUMARTISTSORT").cloned().unwrap_or_default());
+                    self.album_title = flatten_vec(&vcc.get("ALBUM").cloned().unwrap_or_default());
+                    self.album_title_sort =
+                        flatten_vec(&vcc.get("ALBUMSORT").cloned().unwrap_or_default());
+                    self.disc_number =
+                        flatten_vec(&vcc.get("DISCNUMBER").cloned().unwrap_or_default());
+                    self.disc_count =
+                        flatten_vec(&vcc.get("DISCTOTAL").cloned().unwrap_or_default());
+                    self.artist = some_values(vcc.get("ARTIST").cloned().unwrap_or_default());
+                    self.artist_sort =
+                        some_values(vcc.get("ARTISTSORT").cloned().unwrap_or_default());
+                    self.title = flatten_vec(&vcc.get("TITLE").cloned().unwrap_or_default());
+                    self.title_sort =
+                        flatten_vec(&vcc.get("TITLESORT").cloned().unwrap_or_default());
+                    self.track_number =
+                        flatten_vec(&vcc.get("TRACKNUMBER").cloned().unwrap_or_default());
+                    self.track_count =
+                        flatten_vec(&vcc.get("TRACKTOTAL").cloned().unwrap_or_default());
+                    self.genre = some_values(vcc.get("GENRE").cloned().unwrap_or_default());
+                    self.composer = some_values(vcc.get("COMPOSER").cloned().unwrap_or_default());
+                    self.composer_sort =
+                        some_values(vcc.get("COMPOSERSORT").cloned().unwrap_or_default());
+                    self.date = flatten_vec(&vcc.get("DATE").cloned().unwrap_or_default());
+                    self.comments = flatten_vec(&vcc.get("COMMENT").cloned().unwrap_or_default());
+                    self.read_identifiers(|generic, _| {
+                        vcc.get(generic).cloned().unwrap_or_default()
+                    });
+                    log::debug!("Track after comments: {self:?}");
+                }
+                block::Block::StreamInfo(si) => {
+                    log::debug!("StreamInfo: {si:?}");
+
+                    self.duration_ms =
+                        Some(duration_from_samples(si.total_samples, si.sample_rate));
+
+                    self.bits_per_sample = Some(si.bits_per_sample);
+                    self.channels = Some(si.num_channels);
+                    self.sample_rate = Some(si.sample_rate);
+
+                    // For some reason, doing a straight "String::from_utf8(si.md5.clone())?"
+                    // results in "invalid utf-8 sequence of 1 bytes from index 0" in my tests.
+                    // This is a workaround. ¯\_(ツ)_/¯
+                    {
+                        let md5 = utf8_to_string(&si.md5);
+                        if md5 == "00000000000000000000000000000000" {
+                            self.md5 = None;
+                        } else {
+                            self.md5 = Some(md5);
+                        }
+                        log::debug!("MD5: {:?}", self.md5);
+                    }
+
+                    log::debug!("Track after StreamInfo: {self:?}");
+                }
+                block::Block::Application(_) => {
+                    log::trace!("Application block");
+                }
+                block::Block::CueSheet(_) => {
+                    log::trace!("CueSheet block");
+                }
+                block::Block::Padding(_) => {
+                    log::trace!("Padding block");
+                }
+                block::Block::Picture(pic) => {
+                    log::trace!("Picture block");
+                    match pic.picture_type {
+                        block::PictureType::CoverFront => {
+                            self.front_cover_size = Some(pic.data.len());
+                        }
+                        block::PictureType::CoverBack => {
+                            self.back_cover_size = Some(pic.data.len());
+                        }
+                        _ => {}
+                    }
+                }
+                block::Block::SeekTable(_) => {
+                    log::trace!("SeekTable block");
+                }
+                block::Block::Unknown(_) => {
+                    log::trace!("Unknown block");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from an MP3 file.
+    fn read_mp3(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let meta = match mp3_metadata::read_from_file(path) {
+            Ok(m) => m,
+            Err(e) => {
+                // mp3_metadata::Error does not implement std::error::Error so it cannot be
+                // chained as an anyhow source. Format it into the message as the next best option.
+                bail!("Error reading MP3 metadata from {path}: {e}");
+            }
+        };
+
+        self.file_format = Some(FileTypes::MP3);
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.duration_ms = Some(meta.duration.as_millis() as u64);
+        }
+
+        let frames = meta.frames;
+
+        if frames.is_empty() {
+            bail!("No frames found");
+        }
+
+        self.bitrate = Some(u32::from(frames[0].bitrate));
+        self.channels = if frames[0].ms_stereo {
+            Some(2)
+        } else {
+            Some(1)
+        };
+
+        self.bits_per_sample = Some(16); // MP3 is always 16-bit
+
+        // This should probably be something like the mode of the sample rates, but this is fine for now.
+        self.sample_rate = Some(u32::from(frames[0].sampling_freq));
+
+        // Use a different crate to get the metadata
+        let tag = Tag::read_from_path(path)?;
+        self.read_id3_tag(&tag);
+
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from an MP4 file.
+    fn read_mp4(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let tags = Mp4Tag::read_from_path(path)?;
+        let audio = tags.audio_info();
+
+        self.file_format = Some(FileTypes::M4A);
+
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.duration_ms = Some(audio.duration.as_millis() as u64);
+        }
+        self.channels = Some(
+            audio
+                .channel_config
+                .unwrap_or(mp4ameta::ChannelConfig::Stereo)
+                .channel_count(),
+        );
+        self.bits_per_sample = Some(16);
+        self.sample_rate = Some(
+            audio
+                .sample_rate
+                .unwrap_or(mp4ameta::SampleRate::Hz44100)
+                .hz(),
+        );
+        self.bitrate = Some(audio.avg_bitrate.unwrap_or(0));
+
+        // Gather all the Utf8 and Utf16 values. Single-value fields are joined into one string.
+        mp4_values!(tags, b"aART", self, album_artist);
+        mp4_values!(tags, b"soaa", self, album_artist_sort);
+        mp4_tags!(tags, b"\xa9alb", self, album_title);
+        mp4_tags!(tags, b"soal", self, album_title_sort);
+        mp4_values!(tags, b"\xa9ART", self, artist);
+        mp4_values!(tags, b"soar", self, artist_sort);
+        mp4_tags!(tags, b"\xa9nam", self, title);
+        mp4_tags!(tags, b"sonm", self, title_sort);
+        mp4_values!(tags, b"\xa9gen", self, genre);
+        mp4_values!(tags, b"\xa9wrt", self, composer);
+        mp4_values!(tags, b"soco", self, composer_sort);
+        mp4_tags!(tags, b"\xa9day", self, date);
+        mp4_tags!(tags, b"\xa9cmt", self, comments);
+        self.read_identifiers(|_, description| {
+            mp4_values(
+                &tags,
+                &FreeformIdent::new_borrowed("com.apple.iTunes", description),
+            )
+        });
+
+        // ReplayGain values are held in freeform atoms, usually with lower-case names
+        let replaygain = |name: &str| {
+            [name.to_lowercase(), name.to_uppercase()]
+                .iter()
+                .find_map(|n| {
+                    mp4_values(&tags, &FreeformIdent::new_borrowed("com.apple.iTunes", n))
+                        .into_iter()
+                        .next()
+                })
+                .and_then(|value| parse_gain(&value))
+        };
+        // The first image is the front cover and the second the back cover
+        let mut artworks = tags.artworks().map(|img| img.data.len());
+        self.front_cover_size = artworks.next();
+        self.back_cover_size = artworks.next();
+
+        self.replaygain = replaygain("replaygain_track_gain");
+        self.replaygain_peak = replaygain("replaygain_track_peak");
+
+        let default_value = 0;
+        self.track_number = Some(tags.track_number().unwrap_or(default_value).to_string());
+        self.track_count = Some(tags.total_tracks().unwrap_or(default_value).to_string());
+        self.disc_number = Some(tags.disc_number().unwrap_or(default_value).to_string());
+        self.disc_count = Some(tags.total_discs().unwrap_or(default_value).to_string());
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from a file with `APEv2` tags: Monkey's Audio (APE), `WavPack`, Musepack or TAK.
+    /// The `ape` crate only reads the tags, so the duration, sample rate, etc. are read from the audio header.
+    fn read_ape(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let info = ApeStreamInfo::read_from_path(path)?;
+        log::debug!("Stream info: {info:?}");
+
+        self.file_format = Some(info.format);
+        self.duration_ms = Some(info.duration_ms);
+        self.channels = u8::try_from(info.channels).ok();
+        self.bits_per_sample = info.bits_per_sample.and_then(|b| u8::try_from(b).ok());
+        self.sample_rate = Some(info.sample_rate);
+
+        // The average bitrate of the compressed audio
+        self.bitrate = self
+            .file_size
+            .and_then(|size| (size * 8 * 1000).checked_div(info.duration_ms))
+            .and_then(|bitrate| u32::try_from(bitrate).ok());
+
+        let tags = match ape::read_from_path(path) {
+            Ok(tags) => tags,
+            Err(ape::Error::TagNotFound) => {
+                log::debug!("No APE tag found in {path}");
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        log::debug!("APE tags: {tags:?}");
+
+        ape_values!(tags, "ALBUMARTIST", self, album_artist);
+        ape_values!(tags, "ALBUMARTISTSORT", self, album_artist_sort);
+        ape_tags!(tags, "ALBUM", self, album_title);
+        ape_tags!(tags, "ALBUMSORT", self, album_title_sort);
+        ape_tags!(tags, "DISCNUMBER", self, disc_number);
+        ape_tags!(tags, "DISCTOTAL", self, disc_count);
+        ape_values!(tags, "ARTIST", self, artist);
+        ape_values!(tags, "ARTISTSORT", self, artist_sort);
+        ape_tags!(tags, "TITLE", self, title);
+        ape_tags!(tags, "TITLESORT", self, title_sort);
+        ape_tags!(tags, "TRACKNUMBER", self, track_number);
+        ape_tags!(tags, "TRACKTOTAL", self, track_count);
+        ape_values!(tags, "GENRE", self, genre);
+        ape_values!(tags, "COMPOSER", self, composer);
+        ape_values!(tags, "COMPOSERSORT", self, composer_sort);
+        ape_tags!(tags, "DATE", self, date);
+        ape_tags!(tags, "COMMENT", self, comments);
+        self.read_identifiers(|generic, _| ape_values(&tags, generic));
+
+        // Cover art items hold a description, a null and the image
+        let size = |key| tags.item(key).map(|item| Vec::<u8>::from(item).len());
+        self.front_cover_size = size("Cover Art (Front)");
+        self.back_cover_size = size("Cover Art (Back)");
+        Ok(())
+    }
+
+    fn read_dsf(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let filepath = std::path::Path::new(path);
+        let dsf_file = dsf::DsfFile::open(filepath)?;
+        log::debug!("DSF file metadata: {dsf_file}");
+
+        self.file_format = Some(FileTypes::Dsf);
+
+        // Get the basic file information first.
+        let format = dsf_file.fmt_chunk();
+        self.sample_rate = Some(format.sampling_frequency());
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.channels = Some(format.channel_num() as u8);
+            self.bits_per_sample = Some(format.bits_per_sample() as u8);
+        }
+        self.duration_ms = Some(duration_from_samples(
+            format.sample_count(),
+            format.sampling_frequency(),
+        ));
+
+        // Get the ID3 tag.
+        let tag = if dsf_file.id3_tag().is_some() {
+            <std::option::Option<id3::Tag> as Clone>::clone(dsf_file.id3_tag())
+                .expect("ID3 tag should exist")
+        } else {
+            log::warn!("No ID3 tag found");
+            bail!("No ID3 tag found");
+        };
+
+        log::debug!("Tag: {tag:?}");
+        for frame in tag.frames() {
+            log::debug!("  {} = {}", frame.id(), frame.content());
+        }
+
+        tag.frames().for_each(|frame| {
+            log::debug!("Frame: {frame:?}");
+            match frame.id() {
+                "TPE2" => self.album_artist = frame_values(frame),
+                "TSO2" => self.album_artist_sort = frame_values(frame),
+                "TALB" => self.album_title = Some(frame.content().to_string()),
+                "TSOA" => self.album_title_sort = Some(frame.content().to_string()),
+                "TPOS" => {
+                    let pos = frame.content().to_string();
+                    if need_split(&pos) {
+                        if let Ok((disc_num, disc_count)) = crate::split_val(&pos) {
+                            self.disc_number = Some(disc_num.to_string());
+                            self.disc_count = Some(disc_count.to_string());
+                        } else {
+                            self.disc_number = Some(pos);
+                        }
+                    } else {
+                        self.disc_number = Some(pos);
+                    }
+                }
+                "TPOS-T" => self.disc_count = Some(frame.content().to_string()),
+                "TPE1" => self.artist = frame_values(frame),
+                "TSOP" => self.artist_sort = frame_values(frame),
+                "TIT2" => self.title = Some(frame.content().to_string()),
+                "TSOT" => self.title_sort = Some(frame.content().to_string()),
+                "TRCK" => {
+                    let pos = frame.content().to_string();
+                    if need_split(&pos) {
+                        if let Ok((track_num, track_count)) = crate::split_val(&pos) {
+                            self.track_number = Some(track_num.to_string());
+                            self.track_count = Some(track_count.to_string());
+                        } else {
+                            self.track_number = Some(pos);
+                        }
+                    } else {
+                        self.track_number = Some(pos);
+                    }
+                }
+                "TRCK-T" => self.track_count = Some(frame.content().to_string()),
+                "TCON" => self.genre = frame_values(frame),
+                "TCOM" => self.composer = frame_values(frame),
+                "TSOC" => self.composer_sort = frame_values(frame),
+                "TDRC" => self.date = Some(frame.content().to_string()),
+                "COMM" => self.comments = Some(frame.content().to_string()),
+                _ => log::debug!("Unknown frame: {}", frame.id()),
+            }
+        });
+        self.read_identifiers(|generic, description| id3_values(&tag, generic, description));
+        self.read_id3_pictures(&tag);
+
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from an Ogg Vorbis or Ogg Opus file.
+    fn read_ogg(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let ogg = OggFile::read_from_path(path)?;
+        log::debug!("Ogg comments: {:?}", ogg.comments());
+
+        self.file_format = Some(match ogg.codec() {
+            OggCodec::Vorbis => FileTypes::Ogg,
+            OggCodec::Opus => FileTypes::Opus,
+        });
+
+        let info = ogg.stream_info();
+        self.duration_ms = Some(info.duration_ms);
+        self.channels = Some(info.channels);
+        self.sample_rate = Some(info.sample_rate);
+        self.bitrate = info.bitrate;
+
+        let values = |key: &str| -> Vec<String> {
+            ogg.get(key).into_iter().map(ToString::to_string).collect()
+        };
+        self.album_artist = some_values(values("ALBUMARTIST"));
+        self.album_artist_sort = some_values(values("ALBUMARTISTSORT"));
+        self.album_title = flatten_vec(&values("ALBUM"));
+        self.album_title_sort = flatten_vec(&values("ALBUMSORT"));
+        self.disc_number = flatten_vec(&values("DISCNUMBER"));
+        self.disc_count = flatten_vec(&values("DISCTOTAL"));
+        self.artist = some_values(values("ARTIST"));
+        self.artist_sort = some_values(values("ARTISTSORT"));
+        self.title = flatten_vec(&values("TITLE"));
+        self.title_sort = flatten_vec(&values("TITLESORT"));
+        self.track_number = flatten_vec(&values("TRACKNUMBER"));
+        self.track_count = flatten_vec(&values("TRACKTOTAL"));
+        self.genre = some_values(values("GENRE"));
+        self.composer = some_values(values("COMPOSER"));
+        self.composer_sort = some_values(values("COMPOSERSORT"));
+        self.date = flatten_vec(&values("DATE"));
+        self.comments = flatten_vec(&values("COMMENT"));
+        self.read_identifiers(|generic, _| values(generic));
+
+        let size = |picture_type| {
+            ogg.pictures()
+                .iter()
+                .find(|p| p.picture_type == picture_type)
+                .map(|p| p.data.len())
+        };
+        self.front_cover_size = size(OggPicture::FRONT_COVER);
+        self.back_cover_size = size(OggPicture::BACK_COVER);
+
+        Ok(())
+    }
+
+    /// Builds a `Track` struct from a WAV or AIFF file. The tags are read from the ID3 tag, and for WAV files,
+    /// any fields not in the ID3 tag are read from the `LIST/INFO` chunk.
+    fn read_dff(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let dff = DffFile::read_from_path(path)?;
+
+        self.file_format = Some(FileTypes::Dff);
+
+        let info = dff.stream_info();
+        log::debug!("Stream info: {info:?}");
+        self.duration_ms = Some(info.duration_ms);
+        self.channels = u8::try_from(info.channels).ok();
+        self.bits_per_sample = Some(1);
+        self.sample_rate = Some(info.sample_rate);
+
+        // The bitrate of the one-bit DSD stream
+        self.bitrate = info.sample_rate.checked_mul(u32::from(info.channels));
+
+        if let Some(data) = dff.id3_data() {
+            let tag = Tag::read_from2(std::io::Cursor::new(data))?;
+            self.read_id3_tag(&tag);
+        } else {
+            log::debug!("No ID3 chunk found in {path}");
+        }
+
+        // Fall back on the artist and title in the DIIN chunk
+        if self.artist.is_none() {
+            self.artist = dff.artist().map(|v| vec![v.to_string()]);
+        }
+        if self.title.is_none() {
+            self.title = dff.title().map(ToString::to_string);
+        }
+
+        Ok(())
+    }
+
+    fn read_riff(&mut self) -> Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            bail!("No path provided");
+        };
+        let riff = RiffFile::read_from_path(path)?;
+        log::debug!("RIFF INFO: {:?}", riff.info_items());
+
+        self.file_format = Some(match riff.format() {
+            RiffFormat::Wav => FileTypes::Wav,
+            RiffFormat::Aiff => FileTypes::Aiff,
+        });
+
+        let info = riff.stream_info();
+        self.duration_ms = Some(info.duration_ms);
+        self.channels = u8::try_from(info.channels).ok();
+        self.bits_per_sample = u8::try_from(info.bits_per_sample).ok();
+        self.sample_rate = Some(info.sample_rate);
+
+        // The bitrate of uncompressed audio
+        self.bitrate = info
+            .sample_rate
+            .checked_mul(u32::from(info.bits_per_sample) * u32::from(info.channels));
+
+        match Tag::read_from_path(path) {
+            Ok(tag) => self.read_id3_tag(&tag),
+            Err(err) if matches!(err.kind, id3::ErrorKind::NoTag) => {
+                log::debug!("No ID3 tag found in {path}");
+            }
+            Err(err) => return Err(err.into()),
+        }
+        self.read_riff_info(&riff);
+
+        Ok(())
+    }
+}
+
+/// Converts samples to a duration in milliseconds using the sample rate.
+///
+/// # Arguments
+///
+/// * `samples` - Number of samples.
+/// * `sample_rate` - Sample rate in Hz.
+///
+/// # Returns
+///
+/// An u64 representing the duration of the track in milliseconds.
+///
+///
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn duration_from_samples(samples: u64, sample_rate: u32) -> u64 {
+    if sample_rate == 0 {
+        return 0;
+    }
+    ((samples as f64 / f64::from(sample_rate)) * 1000.0).trunc() as u64
+}
+
+/// Converts a byte slice to its hexadecimal string representation.
+///
+/// Each byte is formatted as a two-character lowercase hex value
+/// and concatenated into a single string (e.g., `[0x48, 0x65]` → `"4865"`).
+///
+/// # Arguments
+///
+/// * `utf8` - A slice of bytes to convert.
+///
+/// # Examples
+///
+/// ```ignore
+/// let bytes = &[0x48, 0x65, 0x6c, 0x6c, 0x6f];
+/// let hex = utf8_to_string(bytes);
+/// assert_eq!(hex, "48656c6c6f");
+/// ```
+fn utf8_to_string(utf8: &[u8]) -> String {
+    log::debug!("UTF8: {utf8:?}");
+
+    let hex = utf8.iter().fold(String::new(), |mut acc, b| {
+        use std::fmt::Write;
+        let _ = write!(acc, "{b:02x}");
+        acc
+    });
+
+    log::debug!("utf8 to string: {hex:?}");
+
+    hex
+}
+
+/// Flatten a `Vec<String>` into a single `String`.
+/// The strings are separated by a semicolon and a space.
+///
+/// # Arguments
+///
+/// * `vec` - A `Vec<String>` to flatten.
+///
+/// # Returns
+///
+/// A `String` with the contents of the `Vec<String>`.
+///
+/// # Examples
+///
+/// ```
+/// # use common::flatten_vec;
+/// let vec = vec!["One".to_string(), "Two".to_string(), "Three".to_string()];
+/// let string = flatten_vec(&vec);
+/// assert_eq!(string, Some("One; Two; Three".to_string()));
+/// ```
+///
+/// # Notes
+///
+/// The function trims the resulting string.
+pub fn flatten_vec(vec: &[String]) -> Option<String> {
+    if vec.is_empty() {
+        return None;
+    }
+
+    Some(vec.join("; "))
+}
+
+/// Returns the values of a multi-value field, or `None` if there aren't any.
+fn some_values(values: Vec<String>) -> Option<Vec<String>> {
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(values)
+}
+
+/// Gets the values of an APE item. APE items hold multi-value fields as values separated by nulls.
+fn ape_values(tags: &ape::Tag, key: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for item in tags.items(key) {
+        if let Ok(strings) = Vec::<&str>::try_from(item) {
+            values.extend(strings.into_iter().map(ToString::to_string));
+        } else {
+            log::debug!("Unexpected item type for {key}: {item:?}");
+        }
+    }
+
+    values
+}
+
+/// Gets the Utf8 and Utf16 values of an MP4 atom, eg. `Fourcc(*b"aART")` or a freeform atom.
+fn mp4_values(tags: &Mp4Tag, ident: &impl Ident) -> Vec<String> {
+    tags.data_of(ident)
+        .filter_map(|value| match value {
+            Data::Utf8(s) | Data::Utf16(s) => Some(s.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses a ReplayGain value, eg. "-6.54 dB" or "0.988553".
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+
+    value.trim().parse().ok()
+}
+
+/// Gets the values of an ID3 text frame. ID3v2.4 frames hold multi-value fields as values separated by nulls.
+fn frame_values(frame: &id3::Frame) -> Option<Vec<String>> {
+    frame
+        .content()
+        .text_values()
+        .and_then(|values| some_values(values.map(ToString::to_string).collect()))
+}
+
+/// Gets the values of an identifier from an ID3 tag. The ISRC is held in the `TSRC` frame and the MusicBrainz
+/// track ID in the MusicBrainz `UFID` frame. The others are held in `TXXX` frames with the given description.
+fn id3_values(tag: &Tag, generic: &str, description: &str) -> Vec<String> {
+    match generic {
+        "ISRC" => tag
+            .get("TSRC")
+            .and_then(|frame| frame.content().text_values())
+            .map(|values| values.map(ToString::to_string).collect())
+            .unwrap_or_default(),
+        "MUSICBRAINZ_TRACKID" => tag
+            .unique_file_identifiers()
+            .filter(|ufid| ufid.owner_identifier == "http://musicbrainz.org")
+            .map(|ufid| String::from_utf8_lossy(&ufid.identifier).to_string())
+            .collect(),
+        _ => tag
+            .extended_texts()
+            .filter(|text| text.description.eq_ignore_ascii_case(description))
+            .flat_map(|text| text.value.split('\0').map(ToString::to_string))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let track = Track::new();
+
+        assert_eq!(track.path, None);
+        assert_eq!(track.album_artist, None);
+        assert_eq!(track.album_artist_sort, None);
+        assert_eq!(track.album_title, None);
+        assert_eq!(track.album_title_sort, None);
+        assert_eq!(track.disc_number, None);
+        assert_eq!(track.disc_count, None);
+        assert_eq!(track.artist, None);
+        assert_eq!(track.artist_sort, None);
+        assert_eq!(track.title, None);
+        assert_eq!(track.title_sort, None);
+        assert_eq!(track.track_number, None);
+        assert_eq!(track.track_count, None);
+        assert_eq!(track.genre, None);
+        assert_eq!(track.composer, None);
+        assert_eq!(track.composer_sort, None);
+        assert_eq!(track.date, None);
+        assert_eq!(track.comments, None);
+    }
+
+    #[test]
+    fn test_from_path() {
+        let path = String::from("/path/to/audio.flac");
+        let track = Track::from_path(path.clone());
+
+        assert_eq!(track.path, Some(path));
+        assert_eq!(track.album_artist, None);
+        // Add more assertions for other fields
+    }
+
+    #[test]
+    /// Tests the filter values with the example from the `--where` help text.
+    fn test_filter_values() {
+        let filter: crate::Filter = r#"genre == "Rock" && date < 1990 && !has(picture_front)"#
+            .parse()
+            .unwrap();
+        let mut track = Track {
+            genre: Some(vec!["Pop".to_string(), "Rock".to_string()]),
+            date: Some("1975".to_string()),
+            bitrate: Some(320),
+            ..Track::default()
+        };
+        assert!(filter.matches(&track.filter_values()));
+
+        // A front cover makes it fail, but a back cover doesn't
+        track.back_cover_size = Some(1017);
+        assert!(filter.matches(&track.filter_values()));
+        track.front_cover_size = Some(1017);
+        assert!(!filter.matches(&track.filter_values()));
+        track.front_cover_size = None;
+
+        track.date = Some("1990".to_string());
+        assert!(!filter.matches(&track.filter_values()));
+        track.date = Some("1989".to_string());
+        track.genre = Some(vec!["Jazz".to_string()]);
+        assert!(!filter.matches(&track.filter_values()));
+
+        let values = track.filter_values();
+        assert_eq!(values.get("bitrate"), ["320"]);
+        assert_eq!(values.get("picture_back"), ["1017"]);
+        assert!(values.get("picture_front").is_empty());
+        assert!(values.get("title").is_empty());
+    }
+
+    #[test]
+    fn test_read() {
+        // Skip if test music files are not available (e.g. in CI)
+        let test_path = "../t_flac/CD 1 - Stuff/01-01 Slavonic Dances, Series II, Op 72 (B 147, 1886\u{2013}87) No 7 in C major Presto.flac";
+        if !std::path::Path::new(test_path).exists() {
+            return;
+        }
+        // FLAC
+        let mut track = Track::from_path(test_path.to_string());
+        track.read().expect("Uh oh...");
+
+        assert_eq!(
+            track.album_artist,
+            Some(vec!["Various Artists".to_string()])
+        );
+
+        // MP3
+    }
+
+    #[test]
+    fn test_read_flac_with_path() {
+        let test_path = "../t_flac/CD 1 - Stuff/01-01 Slavonic Dances, Series II, Op 72 (B 147, 1886\u{2013}87) No 7 in C major Presto.flac";
+        // Skip if test music files are not available (e.g. in CI)
+        if !std::path::Path::new(test_path).exists() {
+            return;
+        }
+        let mut track = Track {
+            path: Some(test_path.to_string()),
+            ..Track::default()
+        };
+
+        if let Err(err) = track.read_flac() {
+            panic!("Error reading FLAC: {err}");
+        }
+
+        // Assert that the track fields are populated correctly
+        assert_eq!(
+            track.album_artist,
+            Some(vec!["Various Artists".to_string()])
+        );
+        assert_eq!(track.album_artist_sort, None);
+        assert_eq!(
+            track.album_title,
+            Some("The Many Loves of Antonín Dvořák (CD1)".to_string())
+        );
+        assert_eq!(track.album_title_sort, None);
+        assert_eq!(track.disc_number, Some("01".to_string()));
+        assert_eq!(track.disc_count, Some("03".to_string()));
+        assert_eq!(
+            track.artist,
+            Some(vec!["Czech Philharmonic Orchestra, Karel Šejna".to_string()])
+        );
+        assert_eq!(track.artist_sort, None);
+        assert_eq!(
+            track.title,
+            Some(
+                "Slavonic Dances, Series II, Op. 72 (B 147, 1886–87) No. 7 in C major. Presto"
+                    .to_string()
+            )
+        );
+        assert_eq!(track.title_sort, None);
+        assert_eq!(track.track_number, Some("1".to_string()));
+        assert_eq!(track.track_count, Some("05".to_string()));
+        assert_eq!(track.genre, Some(vec!["Classical".to_string()]));
+        assert_eq!(track.composer, Some(vec!["Someone".to_string()]));
+        assert_eq!(track.composer_sort, None);
+        assert_eq!(track.date, Some("1959".to_string()));
+        assert_eq!(track.comments, Some("Recorded: 18th June 1959. The Dvořák Hall of Rudolfinum, Prague. First release: 1960".to_string()));
+    }
+
+    #[test]
+    fn test_read_flac_without_path() {
+        let mut track = Track::default();
+
+        assert!(track.read_flac().is_err());
+    }
+
+    #[test]
+    fn test_parse_gain() {
+        assert_eq!(parse_gain("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_gain(" +1.20 db"), Some(1.2));
+        assert_eq!(parse_gain("0.988553"), Some(0.988_553));
+        assert_eq!(parse_gain("loud"), None);
+    }
+}
@@ -48,6 +48,28 @@ pub fn build_cli() -> Command {
                 .display_order(2)
                 .action(clap::ArgAction::Set)
         )
+        .arg( // Joiner for multi-value fields in the CSV file
+            Arg::new("csv-joiner")
+                .long("csv-joiner")
+                .value_name("JOINER")
+                .help("The string used to join fields with more than one value in the CSV file, e.g. several artists.")
+                .long_help("The string used to join fields with more than one value in the CSV file, e.g. several artists or genres. The JSON file keeps these as arrays.")
+                .num_args(1)
+                .default_value("; ")
+                .display_order(2)
+                .action(clap::ArgAction::Set)
+        )
+        .arg( // JSON detail output file name
+            Arg::new("json-file")
+                .short('j')
+                .long("json-file")
+                .help("The name of the JSON file into which the detailed information for each file is to be written.")
+                .long_help("The name of the JSON file into which the detailed information for each file is to be written. Default is 'details.json' if not specified. Fields with more than one value, e.g. several artists, are written as arrays.")
+                .num_args(0..)
+                .default_missing_value("details.json")
+                .display_order(2)
+                .action(clap::ArgAction::Set)
+        )
         .arg( // CSV summary output file name
             Arg::new("summary-file")
                 .short('s')
@@ -59,4 +81,14 @@ pub fn build_cli() -> Command {
                 .display_order(2)
                 .action(clap::ArgAction::Set)
         )
+        .arg( // Only export the files that match a filter
+            Arg::new("where")
+                .long("where")
+                .value_name("EXPRESSION")
+                .help("Only export the files whose tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990'")
+                .long_help("Only export the files whose tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990 && !has(picture_front)'. The fields are the exported columns, e.g. album_artist or bitrate, and picture_front and picture_back for the cover art. Uses the same expressions as id3tag --where.")
+                .num_args(1)
+                .display_order(3)
+                .action(clap::ArgAction::Set)
+        )
 }
@@ -3,13 +3,13 @@
 
 mod build_cli;
 mod stats;
-mod tracks;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 
-use crate::{stats::calc_avg, tracks::Reader};
+use crate::stats::calc_avg;
 use build_cli::build_cli;
 use clap::parser::ValueSource;
+use common::{Reader, Track};
 use env_logger::{Builder, Target};
 use log::LevelFilter;
 use stats::update_stats;
@@ -42,11 +42,21 @@ fn run() -> Result<()> {
     let show_detail = cli_args.get_flag("show-detail");
     let print_summary = cli_args.get_flag("print-summary");
     let write_detail = cli_args.value_source("csv-file") == Some(ValueSource::CommandLine);
+    let write_json = cli_args.value_source("json-file") == Some(ValueSource::CommandLine);
     let write_summary = cli_args.value_source("summary-file") == Some(ValueSource::CommandLine);
+    let filter = cli_args
+        .get_one::<String>("where")
+        .map(|expression| {
+            expression
+                .parse::<common::Filter>()
+                .with_context(|| format!("Unable to use the filter {expression}"))
+        })
+        .transpose()?;
 
     // Initialize counters for total files, skipped and processed.
     let mut files_processed = 0;
     let mut files_skipped = 0;
+    let mut files_filtered = 0;
     let mut file_count = 0;
     let mut stats = stats::StatsMap::new();
 
@@ -59,7 +69,7 @@ fn run() -> Result<()> {
     );
     log::debug!("Files: {filenames:?}");
 
-    let mut tracks = Vec::<tracks::Track>::new();
+    let mut tracks = Vec::<Track>::new();
     for filename in &filenames {
         log::debug!("Processing file: {filename}");
 
@@ -68,7 +78,7 @@ fn run() -> Result<()> {
         }
 
         log::debug!("Track: {filename}");
-        let mut track_info = tracks::Track::from_path(filename.to_owned());
+        let mut track_info = Track::from_path(filename.to_owned());
 
         let res = track_info.read();
 
@@ -78,6 +88,14 @@ fn run() -> Result<()> {
             continue;
         }
 
+        if let Some(filter) = &filter {
+            if !filter.matches(&track_info.filter_values()) {
+                log::debug!("{filename} doesn't match the filter. Skipping.");
+                files_filtered += 1;
+                continue;
+            }
+        }
+
         files_processed += 1;
         log::debug!("Track info: {track_info:?}");
 
@@ -99,7 +117,18 @@ fn run() -> Result<()> {
         let csv_file = cli_args
             .get_one::<String>("csv-file")
             .unwrap_or(&default_name);
-        write_csv(csv_file, tracks)?;
+        let joiner = cli_args
+            .get_one::<String>("csv-joiner")
+            .map_or("; ", String::as_str);
+        write_csv(csv_file, &tracks, joiner)?;
+    }
+
+    if write_json {
+        let default_name = String::from("details.json");
+        let json_file = cli_args
+            .get_one::<String>("json-file")
+            .unwrap_or(&default_name);
+        write_json_file(json_file, &tracks)?;
     }
 
     calc_avg(&mut stats);
@@ -115,7 +144,11 @@ fn run() -> Result<()> {
     if print_summary {
         println!("Total files     : {file_count:5}");
         println!("Files processed : {files_processed:5}");
-        println!("Files skipped   : {files_skipped:5}\n");
+        println!("Files skipped   : {files_skipped:5}");
+        if filter.is_some() {
+            println!("Files filtered  : {files_filtered:5}");
+        }
+        println!();
         stats::print_stats(&stats);
     }
 
@@ -137,15 +170,62 @@ fn main() {
     });
 }
 
-fn write_csv(filename: &str, tracks: Vec<tracks::Track>) -> Result<()> {
+/// Writes the tracks to a CSV file, one row per track. Fields with more than one value are joined with `joiner`.
+fn write_csv(filename: &str, tracks: &[Track], joiner: &str) -> Result<()> {
     let mut wtr = csv::WriterBuilder::new().from_path(filename)?;
 
-    for track in tracks {
+    for (idx, track) in tracks.iter().enumerate() {
         log::debug!("Writing track: {track:?}");
-        wtr.serialize(track)?;
-        // Since most fields can have more than one entry, we need to handle them separately.
+        let serde_json::Value::Object(fields) = serde_json::to_value(track)? else {
+            bail!("Unable to convert the track to a row: {track:?}");
+        };
+
+        if idx == 0 {
+            wtr.write_record(fields.keys())?;
+        }
+        wtr.write_record(fields.values().map(|value| csv_value(value, joiner)))?;
     }
 
     wtr.flush()?;
     Ok(())
 }
+
+/// Converts a field to a CSV value. Arrays, ie. fields with more than one value, are joined with `joiner`.
+fn csv_value(value: &serde_json::Value, joiner: &str) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|v| csv_value(v, joiner))
+            .collect::<Vec<String>>()
+            .join(joiner),
+        other => other.to_string(),
+    }
+}
+
+/// Writes the tracks to a JSON file as an array of objects. Fields with more than one value are written as arrays.
+fn write_json_file(filename: &str, tracks: &[Track]) -> Result<()> {
+    let file =
+        std::fs::File::create(filename).with_context(|| format!("Unable to create {filename}"))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), tracks)
+        .with_context(|| format!("Unable to write to {filename}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that fields with more than one value are joined and that empty fields are blank.
+    fn test_csv_value() {
+        let artists = serde_json::json!(["Simon", "Garfunkel"]);
+        assert_eq!(csv_value(&artists, "; "), "Simon; Garfunkel");
+        assert_eq!(csv_value(&artists, "|"), "Simon|Garfunkel");
+        assert_eq!(csv_value(&serde_json::Value::Null, "; "), "");
+        assert_eq!(csv_value(&serde_json::json!("Bookends"), "; "), "Bookends");
+        assert_eq!(csv_value(&serde_json::json!(44100), "; "), "44100");
+    }
+}
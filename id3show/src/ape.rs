@@ -1,9 +1,12 @@
-//! Read the contents of an APE file and show the metadata.
+//! Read the contents of a file with `APEv2` tags (APE, `WavPack`, Musepack or TAK) and show the metadata.
 
 use anyhow::Result;
 use ape::{self, ItemType};
+use common::ApeStreamInfo;
 
-/// Show the metadata of an APE file.
+use crate::flac::format_duration;
+
+/// Show the metadata of an APE, `WavPack`, Musepack or TAK file.
 /// If `show_detail` is true, show more detailed information such as binary data (items and lengths) and locator data (items and lengths).
 ///
 /// # Arguments
@@ -24,7 +27,30 @@ use ape::{self, ItemType};
 /// assert!(res.is_ok());
 /// ```
 pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
-    let tags = ape::read_from_path(filename)?;
+    let info = ApeStreamInfo::read_from_path(filename)?;
+    #[allow(clippy::cast_precision_loss)]
+    let duration = format_duration(info.duration_ms as f64 / 1000.0);
+
+    if show_detail {
+        println!("  Audio Info:");
+        println!("    Format: {}", info.format);
+        println!("    Channels: {}", info.channels);
+        println!("    Sample Rate: {} Hz", info.sample_rate);
+        if let Some(bits_per_sample) = info.bits_per_sample {
+            println!("    Bits per Sample: {bits_per_sample}");
+        }
+        println!("    Duration: {duration}");
+    }
+
+    // Freshly encoded files often don't have a tag yet
+    let tags = match ape::read_from_path(filename) {
+        Ok(tags) => tags,
+        Err(ape::Error::TagNotFound) => {
+            log::debug!("No APE tag found in {filename}");
+            ape::Tag::new()
+        }
+        Err(err) => return Err(err.into()),
+    };
 
     for item in tags.iter() {
         match item.get_type() {
@@ -50,6 +76,10 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
         }
     }
 
+    if !show_detail {
+        println!("  Duration: {duration}");
+    }
+
     // Return safely
     Ok(())
 }
@@ -38,4 +38,14 @@ pub fn build_cli() -> Command {
                 .display_order(2)
                 .action(clap::ArgAction::Set)
         )
+        .arg( // Only show the files that match a filter
+            Arg::new("where")
+                .long("where")
+                .value_name("EXPRESSION")
+                .help("Only show the files whose tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990'")
+                .long_help("Only show the files whose tags match the expression, e.g. --where 'genre == \"Rock\" && date < 1990 && !has(picture_front)'. The fields are the ones exported by id3export, e.g. album_artist or bitrate, and picture_front and picture_back for the cover art. Uses the same expressions as id3tag --where.")
+                .num_args(1)
+                .display_order(3)
+                .action(clap::ArgAction::Set)
+        )
 }
@@ -0,0 +1,52 @@
+//! Show DSDIFF metadata.
+
+use anyhow::Result;
+use common::DffFile;
+use id3::Tag;
+use std::io::Cursor;
+
+use crate::flac::format_duration;
+use crate::mp3::{show_frames, show_tag_versions};
+
+/// Shows the stream info, ID3 tag and `DIIN` items in the file provided.
+pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
+    let dff = DffFile::read_from_path(filename)?;
+    let info = dff.stream_info();
+    #[allow(clippy::cast_precision_loss)]
+    let duration = format_duration(info.duration_ms as f64 / 1000.0);
+
+    if show_detail {
+        println!("  Audio Info:");
+        println!("    Format: {}", info.compression);
+        println!("    Channels: {}", info.channels);
+        println!("    Sample Rate: {} Hz", info.sample_rate);
+        println!("    Duration: {duration}");
+    }
+
+    // Files without an ID3 chunk are fine - there may still be a DIIN chunk
+    if let Some(data) = dff.id3_data() {
+        let tag = Tag::read_from2(Cursor::new(data))?;
+        show_tag_versions(Some(&tag), false);
+        show_frames(filename, &tag, show_detail)?;
+    } else {
+        log::debug!("No ID3 chunk found in {filename}");
+        show_tag_versions(None, false);
+    }
+
+    if dff.artist().is_some() || dff.title().is_some() {
+        println!("  DSDIFF INFO:");
+        if let Some(artist) = dff.artist() {
+            println!("    Artist: {artist}");
+        }
+        if let Some(title) = dff.title() {
+            println!("    Title: {title}");
+        }
+    }
+
+    if !show_detail {
+        println!("  Duration: {duration}");
+    }
+
+    // Return safely
+    Ok(())
+}
@@ -3,6 +3,8 @@ use anyhow::{bail, Context, Result};
 use dsf::{self, DsfFile};
 use std::path::Path;
 
+use crate::mp3::show_tag_versions;
+
 /// Performs the actual processing of DSF files.
 pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
     log::debug!("Filename: {filename}");
@@ -11,6 +13,7 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
     if show_detail {
         match DsfFile::open(path) {
             Ok(dsf_file) => {
+                show_tag_versions(dsf_file.id3_tag().as_ref(), false);
                 println!("DSF file metadata:\n\n{dsf_file}");
             }
             Err(error) => {
@@ -19,6 +22,7 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
         }
     } else if let Some(tag) = DsfFile::open(path)?.id3_tag().clone() {
         log::debug!("Tag: {tag:?}");
+        show_tag_versions(Some(&tag), false);
         for frame in tag.frames() {
             println!("  {}: {}", frame.id(), frame.content());
         }
@@ -226,7 +226,7 @@ fn calc_duration_string(samples: u64, sample_rate: u32) -> Result<String> {
 
 /// Format a duration given in seconds as `mm:ss` or `hh:mm:ss` (zero-padded).
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn format_duration(secs: f64) -> String {
+pub(crate) fn format_duration(secs: f64) -> String {
     let hours = (secs / 3600.0) as u32;
     let minutes = ((secs % 3600.0) / 60.0) as u32;
     let seconds = (secs % 60.0) as u32;
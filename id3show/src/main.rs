@@ -7,12 +7,16 @@ mod build_cli;
 use build_cli::build_cli;
 
 mod ape;
+mod dff;
 mod dsf;
 mod flac;
 mod mp3;
 mod mp4;
+mod ogg;
+mod wav;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use common::{Reader, Track};
 
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// This is where the magic happens.
@@ -33,6 +37,7 @@ fn run() -> Result<()> {
     // Initialize counters for total files, skipped and processed.
     let mut files_processed = 0;
     let mut files_skipped = 0;
+    let mut files_filtered = 0;
 
     // Expand glob patterns and create a list of files to process
     let filenames = common::expand_file_args(
@@ -45,15 +50,49 @@ fn run() -> Result<()> {
 
     let show_detail = cli_args.get_flag("show-detail");
     let print_summary = cli_args.get_flag("print-summary");
+    let filter = cli_args
+        .get_one::<String>("where")
+        .map(|expression| {
+            expression
+                .parse::<common::Filter>()
+                .with_context(|| format!("Unable to use the filter {expression}"))
+        })
+        .transpose()?;
 
     for filename in &filenames {
+        if let Some(filter) = &filter {
+            match matches_filter(filename, filter) {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::debug!("{filename} doesn't match the filter. Skipping.");
+                    files_filtered += 1;
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("{filename}: Unable to check against the filter. Error: {err}");
+                    files_skipped += 1;
+                    continue;
+                }
+            }
+        }
+
         println!("{filename}");
         let proc_res = match common::get_file_type(filename)? {
-            common::FileTypes::Ape => ape::show_metadata(filename, show_detail),
+            common::FileTypes::Ape
+            | common::FileTypes::WavPack
+            | common::FileTypes::Musepack
+            | common::FileTypes::Tak => ape::show_metadata(filename, show_detail),
+            common::FileTypes::Dff => dff::show_metadata(filename, show_detail),
             common::FileTypes::Dsf => dsf::show_metadata(filename, show_detail),
             common::FileTypes::Flac => flac::show_metadata(filename, show_detail),
             common::FileTypes::MP3 => mp3::show_metadata(filename, show_detail),
             common::FileTypes::M4A => mp4::show_metadata(filename, show_detail),
+            common::FileTypes::Ogg | common::FileTypes::Opus => {
+                ogg::show_metadata(filename, show_detail)
+            }
+            common::FileTypes::Wav | common::FileTypes::Aiff => {
+                wav::show_metadata(filename, show_detail)
+            }
             common::FileTypes::Unknown => {
                 println!("  Unknown file type. Skipping.");
                 Ok(())
@@ -74,6 +113,9 @@ fn run() -> Result<()> {
         println!(
             "\nTotal number of files: {file_count}\n  Processed: {files_processed}\n  Skipped: {files_skipped}"
         );
+        if filter.is_some() {
+            println!("  Not matching the filter: {files_filtered}");
+        }
         println!("Total time: {} ms", now.elapsed().as_millis());
     }
 
@@ -81,6 +123,14 @@ fn run() -> Result<()> {
     Ok(())
 } // fn run()
 
+/// Checks if the tags in the file match the `--where` filter. The tags are read the same way as by `id3export`.
+fn matches_filter(filename: &str, filter: &common::Filter) -> Result<bool> {
+    let mut track = Track::from_path(filename.to_string());
+    track.read()?;
+
+    Ok(filter.matches(&track.filter_values()))
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// The actual executable function that gets called when the program in invoked.
 fn main() {
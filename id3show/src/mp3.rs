@@ -1,5 +1,6 @@
 use anyhow::{bail, Result};
 use id3::frame;
+use id3::v1;
 use id3::Content;
 use id3::Tag;
 use mp3_metadata::MP3Metadata;
@@ -70,16 +71,66 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
         show_frame_data(&meta);
     }
 
-    // Read the tag - bomb out if it doesn't work.
-    let tag = Tag::read_from_path(filename)?;
+    // Files with only an ID3v1 tag have their values shown as ID3v2 frames
+    let tag = match Tag::read_from_path(filename) {
+        Ok(tag) => Some(tag),
+        Err(err) if matches!(err.kind, id3::ErrorKind::NoTag) => None,
+        Err(err) => return Err(err.into()),
+    };
+    let v1_tag = v1::Tag::read_from_path(filename).ok();
+    show_tag_versions(tag.as_ref(), v1_tag.is_some());
+
+    if let Some(tag) = tag.or_else(|| v1_tag.map(Tag::from)) {
+        show_frames(filename, &tag, show_detail)?;
+    }
+
+    if !show_detail {
+        println!("  Duration: {duration_string} (Calc)");
+    }
 
+    // return safely
+    Ok(())
+}
+
+/// Shows which ID3 tag versions are in the file, eg. "ID3v2.3, ID3v1".
+pub fn show_tag_versions(tag: Option<&Tag>, has_v1: bool) {
+    let versions = tag
+        .map(|t| t.version().to_string())
+        .into_iter()
+        .chain(has_v1.then(|| "ID3v1".to_string()))
+        .collect::<Vec<String>>();
+
+    if versions.is_empty() {
+        println!("  ID3 Tags: None");
+    } else {
+        println!("  ID3 Tags: {}", versions.join(", "));
+    }
+}
+
+/// Shows the frames in an ID3 tag, as found in MP3, WAV and AIFF files.
+/// Pictures, lyrics, etc. are only shown if `show_detail` is set.
+pub fn show_frames(filename: &str, tag: &Tag, show_detail: bool) -> Result<()> {
     for item in tag.frames() {
         match item.content() {
             Content::Text(t) => {
-                println!("  {}: {t} (Text)", item.name());
+                // Frames with more than one value have the values separated by nulls
+                println!("  {}: {} (Text)", item.name(), t.replace('\0', "; "));
             }
             Content::ExtendedText(et) => {
-                println!("  {}: {et} (Extended Text)", item.name());
+                println!(
+                    "  {}: {} (Extended Text)",
+                    item.name(),
+                    et.to_string().replace('\0', "; ")
+                );
+            }
+            Content::UniqueFileIdentifier(ufid) => {
+                // Eg. the MusicBrainz recording ID, which is owned by "http://musicbrainz.org"
+                println!(
+                    "  {}: {}: {} (Unique File Identifier)",
+                    item.name(),
+                    ufid.owner_identifier,
+                    String::from_utf8_lossy(&ufid.identifier)
+                );
             }
             Content::Link(l) => {
                 println!("  {}: {l} (Link)", item.name());
@@ -136,10 +187,6 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
         }
     }
 
-    if !show_detail {
-        println!("  Duration: {duration_string} (Calc)");
-    }
-
     // return safely
     Ok(())
 }
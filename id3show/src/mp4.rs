@@ -1,5 +1,5 @@
 use anyhow::Result;
-use mp4ameta::Tag;
+use mp4ameta::{ImgFmt, ImgRef, Tag};
 
 /// Show the MP4 metadata
 pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
@@ -19,25 +19,10 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
             mp4ameta::Data::Utf16(d) => {
                 println!("  {data_ident}: {d} (UTF-16)");
             }
-            mp4ameta::Data::Jpeg(jpeg) => {
-                if show_detail {
-                    println!("  {}: {} bytes (JPEG)", data_ident, jpeg.len());
-                }
-            }
-            mp4ameta::Data::Png(png) => {
-                if show_detail {
-                    println!("  {}: {} bytes (PNG)", data_ident, png.len());
-                }
-            }
+            // The images are shown below, in the order they are in the file
+            mp4ameta::Data::Jpeg(_) | mp4ameta::Data::Png(_) | mp4ameta::Data::Bmp(_) => {}
             mp4ameta::Data::BeSigned(bes) => {
-                if show_detail {
-                    println!("  {}: {} bytes (Big-Endian Signed)", data_ident, bes.len());
-                }
-            }
-            mp4ameta::Data::Bmp(bmp) => {
-                if show_detail {
-                    println!("  {}: {} bytes (BMP)", data_ident, bmp.len());
-                }
+                println!("  {data_ident}: {} (Integer)", be_signed(bes));
             }
             mp4ameta::Data::Unknown { code, data } => {
                 if show_detail {
@@ -50,6 +35,55 @@ pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
         }
     }
 
+    if show_detail {
+        for (index, img) in tag.artworks().enumerate() {
+            show_artwork(index, &img);
+        }
+    }
+
     // return safely
     Ok(())
 }
+
+/// Gets the value of an integer atom, eg. `tmpo` or `cpil`. These are 1, 2, 4 or 8 bytes long.
+fn be_signed(bytes: &[u8]) -> i64 {
+    let value = bytes.iter().fold(0_i64, |n, b| (n << 8) | i64::from(*b));
+
+    // Negative values are sign-extended from the length of the data
+    match bytes.len() {
+        1..=7 if bytes[0] & 0x80 != 0 => value - (1_i64 << (bytes.len() * 8)),
+        _ => value,
+    }
+}
+
+/// Show an image in the `covr` atom. The first image is the front cover and the second the back cover.
+fn show_artwork(index: usize, img: &ImgRef) {
+    let cover = match index {
+        0 => " (Front Cover)",
+        1 => " (Back Cover)",
+        _ => "",
+    };
+    let format = match img.fmt {
+        ImgFmt::Bmp => "BMP",
+        ImgFmt::Jpeg => "JPEG",
+        ImgFmt::Png => "PNG",
+    };
+
+    println!("  Artwork {}{cover}:", index + 1);
+    println!("    Format: {format}");
+    println!("    Size: {} bytes", img.data.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests reading the integer atoms, eg. `cpil` (1 byte) and `tmpo` (2 bytes).
+    fn test_be_signed() {
+        assert_eq!(be_signed(&[1]), 1);
+        assert_eq!(be_signed(&[0, 120]), 120);
+        assert_eq!(be_signed(&[0xff, 0xfe]), -2);
+        assert_eq!(be_signed(&[0, 0, 1, 0]), 256);
+    }
+}
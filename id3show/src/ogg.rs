@@ -0,0 +1,58 @@
+//! Show Ogg Vorbis and Ogg Opus metadata.
+
+use anyhow::Result;
+use common::{OggFile, OggPicture};
+
+use crate::flac::format_duration;
+
+/// Shows the stream info, Vorbis comments and pictures in the file provided.
+pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
+    let ogg = OggFile::read_from_path(filename)?;
+    let info = ogg.stream_info();
+    #[allow(clippy::cast_precision_loss)]
+    let duration = format_duration(info.duration_ms as f64 / 1000.0);
+
+    if show_detail {
+        println!("  Audio Info:");
+        println!("    Codec: {}", ogg.codec());
+        println!("    Channels: {}", info.channels);
+        println!("    Sample Rate: {} Hz", info.sample_rate);
+        if let Some(bitrate) = info.bitrate {
+            println!("    Bitrate: {} kbps", bitrate / 1000);
+        }
+        println!("    Duration: {duration}");
+    }
+
+    println!("  Vorbis Comments:");
+    if show_detail {
+        println!("    Vendor: {}", ogg.vendor());
+    }
+    for (key, value) in ogg.comments() {
+        println!("    {key}: {value}");
+    }
+    if !show_detail {
+        println!("    Duration: {duration}");
+    }
+
+    if show_detail {
+        for picture in ogg.pictures() {
+            show_picture(picture);
+        }
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Show the fields of a `METADATA_BLOCK_PICTURE` comment
+fn show_picture(pic: &OggPicture) {
+    println!("  Picture:");
+    println!("    Picture Type: {}", pic.picture_type);
+    println!("    MIME Type: {}", pic.mime_type);
+    println!("    Description: {}", pic.description);
+    println!("    Width: {} px", pic.width);
+    println!("    Height: {} px", pic.height);
+    println!("    Color Depth: {} bits", pic.depth);
+    println!("    Color Count: {} colors", pic.colors);
+    println!("    Picture Size: {} bytes", pic.data.len());
+}
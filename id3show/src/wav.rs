@@ -0,0 +1,52 @@
+//! Show WAV and AIFF metadata.
+
+use anyhow::Result;
+use common::RiffFile;
+use id3::Tag;
+
+use crate::flac::format_duration;
+use crate::mp3::{show_frames, show_tag_versions};
+
+/// Shows the stream info, ID3 tag and `LIST/INFO` items in the file provided.
+pub fn show_metadata(filename: &str, show_detail: bool) -> Result<()> {
+    let riff = RiffFile::read_from_path(filename)?;
+    let info = riff.stream_info();
+    #[allow(clippy::cast_precision_loss)]
+    let duration = format_duration(info.duration_ms as f64 / 1000.0);
+
+    if show_detail {
+        println!("  Audio Info:");
+        println!("    Format: {}", riff.format());
+        println!("    Channels: {}", info.channels);
+        println!("    Sample Rate: {} Hz", info.sample_rate);
+        println!("    Bits per Sample: {}", info.bits_per_sample);
+        println!("    Duration: {duration}");
+    }
+
+    // Files without an ID3 tag are fine - there may still be a LIST/INFO chunk
+    match Tag::read_from_path(filename) {
+        Ok(tag) => {
+            show_tag_versions(Some(&tag), false);
+            show_frames(filename, &tag, show_detail)?;
+        }
+        Err(err) if matches!(err.kind, id3::ErrorKind::NoTag) => {
+            log::debug!("No ID3 tag found in {filename}");
+            show_tag_versions(None, false);
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    if !riff.info_items().is_empty() {
+        println!("  RIFF INFO:");
+        for (id, value) in riff.info_items() {
+            println!("    {id}: {value}");
+        }
+    }
+
+    if !show_detail {
+        println!("  Duration: {duration}");
+    }
+
+    // Return safely
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Reads tag values from a CSV file, such as one written by `id3export`, so they can be applied to the music files.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// A cell with this value means the tag should be removed from the file.
+pub const DELETE_MARKER: &str = "<delete>";
+
+/// The `id3export` columns and the (Vorbis comment style) tags they map to.
+const TRACK_COLUMNS: [(&str, &str); 27] = [
+    ("album_artist", "ALBUMARTIST"),
+    ("album_artist_sort", "ALBUMARTISTSORT"),
+    ("album_title", "ALBUM"),
+    ("album_title_sort", "ALBUMTITLESORT"),
+    ("disc_number", "DISCNUMBER"),
+    ("disc_count", "DISCTOTAL"),
+    ("artist", "ARTIST"),
+    ("artist_sort", "ARTISTSORT"),
+    ("title", "TITLE"),
+    ("title_sort", "TITLESORT"),
+    ("track_number", "TRACKNUMBER"),
+    ("track_count", "TRACKTOTAL"),
+    ("genre", "GENRE"),
+    ("composer", "COMPOSER"),
+    ("composer_sort", "COMPOSERSORT"),
+    ("date", "DATE"),
+    ("comments", "DESCRIPTION"),
+    ("isrc", "ISRC"),
+    ("iswc", "ISWC"),
+    ("barcode", "BARCODE"),
+    ("catalog_number", "CATALOGNUMBER"),
+    ("musicbrainz_track_id", "MUSICBRAINZ_TRACKID"),
+    ("musicbrainz_album_id", "MUSICBRAINZ_ALBUMID"),
+    ("musicbrainz_artist_id", "MUSICBRAINZ_ARTISTID"),
+    ("musicbrainz_album_artist_id", "MUSICBRAINZ_ALBUMARTISTID"),
+    ("musicbrainz_release_group_id", "MUSICBRAINZ_RELEASEGROUPID"),
+    ("musicbrainz_disc_id", "MUSICBRAINZ_DISCID"),
+];
+
+/// The `id3export` columns that describe the file rather than the tags. These are ignored.
+const READ_ONLY_COLUMNS: [&str; 10] = [
+    "file_format",
+    "file_size",
+    "duration_ms",
+    "bitrate",
+    "bits_per_sample",
+    "sample_rate",
+    "channels",
+    "replaygain",
+    "replaygain_peak",
+    "md5",
+];
+
+/// The tag values read from a CSV file, keyed on the path of the music file.
+#[derive(Debug, Default, Clone)]
+pub struct CsvTags {
+    /// The paths of the music files, in the order they appear in the CSV.
+    paths: Vec<String>,
+
+    /// The values for each file, keyed on the Vorbis comment style tag name (eg. `ARTIST`).
+    /// Blank cells are left out.
+    rows: HashMap<PathBuf, HashMap<String, String>>,
+}
+
+impl CsvTags {
+    /// Reads the CSV file with the given name.
+    pub fn load(filename: &str) -> Result<Self> {
+        let file = fs::File::open(filename)
+            .with_context(|| format!("Unable to open CSV file {filename}"))?;
+        Self::from_reader(file).with_context(|| format!("Unable to read CSV file {filename}"))
+    }
+
+    /// Reads the CSV from a reader. The first row must hold the column names, one of which must be `path`.
+    ///
+    /// The `id3export` columns (eg. `album_artist`, `track_number`) are mapped to the tags they were read from,
+    /// and columns that describe the file (eg. `file_size`, `bitrate`) are ignored. Any other columns are
+    /// used as tag names, the same way as for `--set`.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+
+        let headers = rdr.headers()?.clone();
+        let Some(path_idx) = headers.iter().position(|h| h.trim() == "path") else {
+            bail!("No \"path\" column found.");
+        };
+
+        // The tag name for each column. `None` for the columns we don't use.
+        let tag_names: Vec<Option<String>> = headers
+            .iter()
+            .enumerate()
+            .map(|(idx, header)| column_tag(header).filter(|_| idx != path_idx))
+            .collect();
+        log::debug!("CsvTags::from_reader::tag_names = {tag_names:?}");
+
+        let mut csv_tags = Self::default();
+        for record in rdr.records() {
+            let record = record?;
+            let Some(path) = record
+                .get(path_idx)
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+            else {
+                log::warn!("Skipping CSV row without a path: {record:?}");
+                continue;
+            };
+
+            let values: HashMap<String, String> = record
+                .iter()
+                .zip(&tag_names)
+                .filter_map(|(value, tag)| tag.as_ref().map(|t| (t, value.trim())))
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(tag, value)| (tag.clone(), value.to_string()))
+                .collect();
+
+            csv_tags.paths.push(path.to_string());
+            csv_tags.rows.insert(normalize(path), values);
+        }
+
+        Ok(csv_tags)
+    }
+
+    /// The paths of the music files in the CSV, in the order they appear.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Gets the values for the music file, if it is in the CSV.
+    pub fn values(&self, filename: &str) -> Option<&HashMap<String, String>> {
+        self.rows.get(&normalize(filename))
+    }
+}
+
+/// Returns the tag name for a CSV column, or `None` if the column should be ignored.
+fn column_tag(header: &str) -> Option<String> {
+    let header = header.trim();
+    if header.is_empty() || READ_ONLY_COLUMNS.contains(&header) {
+        return None;
+    }
+
+    let tag = TRACK_COLUMNS
+        .iter()
+        .find(|(column, _)| *column == header)
+        .map_or(header, |(_, tag)| tag);
+
+    Some(tag.to_string())
+}
+
+/// Gets the full path to the file so relative and absolute paths to the same file match.
+/// Uses the path as-is if the file doesn't exist.
+fn normalize(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that the `id3export` columns are mapped to tags, and that blank cells and read-only columns are left out.
+    fn test_from_reader() {
+        let csv = "\
+path,file_format,file_size,album_artist,title,track_number,comments,LABEL
+music/01.flac,Flac,1234,Madonna,Vogue,1,,Sire
+music/02.mp3,MP3,5678,,Frozen,<delete>,Great song,
+,Flac,0,Nobody,Nothing,,,
+";
+        let csv_tags = CsvTags::from_reader(csv.as_bytes()).unwrap();
+        assert_eq!(csv_tags.paths(), &["music/01.flac", "music/02.mp3"]);
+
+        let first = csv_tags.values("music/01.flac").unwrap();
+        assert_eq!(first.len(), 4);
+        assert_eq!(first.get("ALBUMARTIST").unwrap(), "Madonna");
+        assert_eq!(first.get("TITLE").unwrap(), "Vogue");
+        assert_eq!(first.get("TRACKNUMBER").unwrap(), "1");
+        assert_eq!(first.get("LABEL").unwrap(), "Sire");
+
+        let second = csv_tags.values("music/02.mp3").unwrap();
+        assert_eq!(second.len(), 3);
+        assert!(!second.contains_key("ALBUMARTIST"));
+        assert_eq!(second.get("TRACKNUMBER").unwrap(), DELETE_MARKER);
+        assert_eq!(second.get("DESCRIPTION").unwrap(), "Great song");
+
+        assert!(csv_tags.values("music/03.flac").is_none());
+
+        // The path column is required
+        assert!(CsvTags::from_reader("file,title\na.flac,A\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Reads the album and track values from a CUE sheet, such as the ones written by EAC and XLD,
+//! so they can be applied to the music files.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Used instead of a file name to look for the CUE sheet next to each music file.
+pub const CUE_AUTO: &str = "auto";
+
+/// The `REM` entries we use, and the (Vorbis comment style) tags they map to.
+const REM_TAGS: [(&str, &str); 4] = [
+    ("DATE", "DATE"),
+    ("GENRE", "GENRE"),
+    ("DISCNUMBER", "DISCNUMBER"),
+    ("TOTALDISCS", "DISCTOTAL"),
+];
+
+/// The part of the CUE sheet being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    /// Before the first track
+    Album,
+
+    /// An audio track
+    Track,
+
+    /// A data track, which is ignored
+    Other,
+}
+
+/// A track in a CUE sheet.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CueTrack {
+    /// The track number, eg. `1` for `TRACK 01 AUDIO`.
+    pub number: u32,
+
+    /// The name of the file the track is in, as given by the `FILE` entry.
+    pub file: Option<String>,
+
+    /// The track values, keyed on the Vorbis comment style tag name (eg. `TITLE`).
+    pub values: HashMap<String, String>,
+}
+
+/// The values read from a CUE sheet.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CueSheet {
+    /// The album values, keyed on the Vorbis comment style tag name (eg. `ALBUM`).
+    pub album: HashMap<String, String>,
+
+    /// The audio tracks, in the order they appear.
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Reads the CUE sheet with the given name. Files that aren't valid UTF-8 are read as Latin-1,
+    /// which is what most older rippers use.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self> {
+        let filename = filename.as_ref();
+        let bytes = fs::read(filename)
+            .with_context(|| format!("Unable to read CUE sheet {}", filename.display()))?;
+        let text = String::from_utf8(bytes)
+            .unwrap_or_else(|err| err.as_bytes().iter().map(|b| char::from(*b)).collect());
+
+        Ok(Self::parse(&text))
+    }
+
+    /// Parses the text of a CUE sheet.
+    ///
+    /// - The album `TITLE`, `PERFORMER` and `CATALOG` set `ALBUM`, `ALBUMARTIST` and `CATALOGNUMBER`.
+    /// - `REM DATE`, `REM GENRE`, `REM DISCNUMBER` and `REM TOTALDISCS` set the album values with the same names.
+    /// - The track `TITLE`, `PERFORMER`, `SONGWRITER` and `ISRC` set `TITLE`, `ARTIST`, `COMPOSER` and `ISRC`.
+    ///   Tracks without a `PERFORMER` or `SONGWRITER` use the album one.
+    ///
+    /// Anything else, such as `INDEX` and `FLAGS`, is ignored. Data tracks are left out.
+    pub fn parse(text: &str) -> Self {
+        let mut sheet = Self::default();
+        let mut album_songwriter = None;
+        let mut file: Option<String> = None;
+        let mut section = Section::Album;
+
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            let (command, rest) = split_word(line.trim());
+            let command = command.to_ascii_uppercase();
+
+            match (command.as_str(), section) {
+                ("FILE", _) => {
+                    file = Some(unquote(rest.rsplit_once(' ').map_or(rest, |(f, _)| f)));
+                }
+                ("TRACK", _) => {
+                    let (number, kind) = split_word(rest);
+                    section = if kind.eq_ignore_ascii_case("AUDIO") {
+                        sheet.tracks.push(CueTrack {
+                            number: number.parse().unwrap_or_default(),
+                            file: file.clone(),
+                            values: HashMap::new(),
+                        });
+                        Section::Track
+                    } else {
+                        Section::Other
+                    };
+                }
+                ("REM", Section::Album) => {
+                    let (key, value) = split_word(rest);
+                    if let Some((_, tag)) =
+                        REM_TAGS.iter().find(|(k, _)| k.eq_ignore_ascii_case(key))
+                    {
+                        insert(&mut sheet.album, tag, value);
+                    }
+                }
+                ("TITLE", Section::Album) => insert(&mut sheet.album, "ALBUM", rest),
+                ("PERFORMER", Section::Album) => insert(&mut sheet.album, "ALBUMARTIST", rest),
+                ("CATALOG", Section::Album) => insert(&mut sheet.album, "CATALOGNUMBER", rest),
+                ("SONGWRITER", Section::Album) => album_songwriter = Some(unquote(rest)),
+                ("TITLE" | "PERFORMER" | "SONGWRITER" | "ISRC", Section::Track) => {
+                    let tag = match command.as_str() {
+                        "PERFORMER" => "ARTIST",
+                        "SONGWRITER" => "COMPOSER",
+                        other => other,
+                    };
+                    if let Some(track) = sheet.tracks.last_mut() {
+                        insert(&mut track.values, tag, rest);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Fill in the values the tracks get from the album
+        let total = sheet.tracks.len().to_string();
+        for track in &mut sheet.tracks {
+            if track.number > 0 {
+                track
+                    .values
+                    .insert("TRACKNUMBER".to_string(), track.number.to_string());
+            }
+            track.values.insert("TRACKTOTAL".to_string(), total.clone());
+            if let Some(performer) = sheet.album.get("ALBUMARTIST") {
+                track
+                    .values
+                    .entry("ARTIST".to_string())
+                    .or_insert_with(|| performer.clone());
+            }
+            if let Some(songwriter) = album_songwriter.as_ref().filter(|s| !s.is_empty()) {
+                track
+                    .values
+                    .entry("COMPOSER".to_string())
+                    .or_insert_with(|| songwriter.clone());
+            }
+        }
+
+        log::debug!("CueSheet::parse::sheet = {sheet:?}");
+        sheet
+    }
+
+    /// Finds the CUE sheet for the music file in the same folder. If there are several, the one with a `FILE` entry
+    /// for the music file is used, then the one with the same name as the folder.
+    ///
+    /// Returns `None` if there isn't one.
+    pub fn find_for(filename: &str) -> Result<Option<PathBuf>> {
+        let dir = parent_dir(filename);
+        let mut candidates: Vec<PathBuf> = fs::read_dir(&dir)
+            .with_context(|| format!("Unable to read the folder {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && has_extension(path, "cue"))
+            .collect();
+        candidates.sort();
+
+        if candidates.len() > 1 {
+            let stem = file_stem(filename);
+            let by_file = candidates.iter().find(|path| {
+                Self::load(path).is_ok_and(|sheet| {
+                    sheet
+                        .tracks
+                        .iter()
+                        .any(|t| t.file.as_deref().map(file_stem) == Some(stem.clone()))
+                })
+            });
+            let by_folder = candidates
+                .iter()
+                .find(|path| dir.file_name() == path.file_stem());
+
+            if let Some(path) = by_file.or(by_folder) {
+                return Ok(Some(path.clone()));
+            }
+            log::warn!(
+                "{filename} :: Found {} CUE sheets and unable to tell which one to use.",
+                candidates.len()
+            );
+            return Ok(None);
+        }
+
+        Ok(candidates.pop())
+    }
+
+    /// Finds the track for the music file.
+    ///
+    /// 1. The track in a `FILE` with the same name as the music file, ignoring the extension.
+    /// 2. The track in the same position as the music file among the files of the same type in the folder,
+    ///    sorted by name. This is only used if the number of files and tracks match.
+    pub fn track_for(&self, filename: &str) -> Option<&CueTrack> {
+        let stem = file_stem(filename);
+        let in_file: Vec<&CueTrack> = self
+            .tracks
+            .iter()
+            .filter(|t| t.file.as_deref().map(file_stem) == Some(stem.clone()))
+            .collect();
+        if let [track] = in_file.as_slice() {
+            return Some(track);
+        }
+
+        let path = Path::new(filename);
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        let mut siblings: Vec<PathBuf> = fs::read_dir(parent_dir(filename))
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && has_extension(p, &extension))
+            .collect();
+        siblings.sort();
+
+        if siblings.len() != self.tracks.len() {
+            log::debug!(
+                "{filename} :: {} file(s) and {} track(s). Unable to match by track order.",
+                siblings.len(),
+                self.tracks.len()
+            );
+            return None;
+        }
+
+        let position = siblings
+            .iter()
+            .position(|p| p.file_name() == path.file_name())?;
+        self.tracks.get(position)
+    }
+
+    /// Finds the track with the given number.
+    pub fn track(&self, number: u32) -> Option<&CueTrack> {
+        self.tracks.iter().find(|t| t.number == number)
+    }
+}
+
+/// Splits the line into the first word and the rest.
+fn split_word(line: &str) -> (&str, &str) {
+    line.split_once(char::is_whitespace)
+        .map_or((line, ""), |(word, rest)| (word, rest.trim()))
+}
+
+/// Removes the quotes around a value, if there are any.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Adds the value, without quotes, if it isn't blank.
+fn insert(values: &mut HashMap<String, String>, tag: &str, value: &str) {
+    let value = unquote(value);
+    if !value.is_empty() {
+        values.insert(tag.to_string(), value);
+    }
+}
+
+/// Gets the folder the file is in, using the current folder for bare file names.
+fn parent_dir(filename: &str) -> PathBuf {
+    Path::new(filename)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+}
+
+/// Gets the name of the file without the folder and extension. `FILE` entries written on Windows use `\`.
+fn file_stem(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Checks if the file has the extension, ignoring case.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUE: &str = "\u{feff}REM GENRE Pop
+REM DATE 1989
+REM COMMENT \"ExactAudioCopy v1.6\"
+CATALOG 0075992584423
+PERFORMER \"Madonna\"
+TITLE \"Like a Prayer\"
+FILE \"01 - Like a Prayer.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Like a Prayer\"
+    ISRC USWB18900001
+    INDEX 01 00:00:00
+FILE \"Data.bin\" BINARY
+  TRACK 02 MODE1/2352
+    TITLE \"Data\"
+    INDEX 01 00:00:00
+FILE \"03 - Love Song.wav\" WAVE
+  TRACK 03 AUDIO
+    TITLE \"Love Song\"
+    PERFORMER \"Madonna & Prince\"
+    SONGWRITER \"Madonna, Prince\"
+    INDEX 01 00:00:00
+";
+
+    #[test]
+    /// Tests that the album and track values are read, and that data tracks and unknown entries are left out.
+    fn test_parse() {
+        let sheet = CueSheet::parse(CUE);
+        assert_eq!(sheet.album.len(), 5);
+        assert_eq!(sheet.album.get("ALBUM").unwrap(), "Like a Prayer");
+        assert_eq!(sheet.album.get("ALBUMARTIST").unwrap(), "Madonna");
+        assert_eq!(sheet.album.get("GENRE").unwrap(), "Pop");
+        assert_eq!(sheet.album.get("DATE").unwrap(), "1989");
+        assert_eq!(sheet.album.get("CATALOGNUMBER").unwrap(), "0075992584423");
+
+        assert_eq!(sheet.tracks.len(), 2);
+        let first = sheet.track(1).unwrap();
+        assert_eq!(first.file.as_deref(), Some("01 - Like a Prayer.wav"));
+        assert_eq!(first.values.get("TITLE").unwrap(), "Like a Prayer");
+        assert_eq!(first.values.get("ARTIST").unwrap(), "Madonna");
+        assert_eq!(first.values.get("ISRC").unwrap(), "USWB18900001");
+        assert_eq!(first.values.get("TRACKNUMBER").unwrap(), "1");
+        assert_eq!(first.values.get("TRACKTOTAL").unwrap(), "2");
+        assert!(!first.values.contains_key("COMPOSER"));
+
+        let third = sheet.track(3).unwrap();
+        assert_eq!(third.values.get("TITLE").unwrap(), "Love Song");
+        assert_eq!(third.values.get("ARTIST").unwrap(), "Madonna & Prince");
+        assert_eq!(third.values.get("COMPOSER").unwrap(), "Madonna, Prince");
+        assert!(sheet.track(2).is_none());
+    }
+
+    #[test]
+    /// Tests that tracks are matched on the `FILE` entries.
+    fn test_track_for() {
+        let sheet = CueSheet::parse(CUE);
+        assert_eq!(
+            sheet.track_for("/music/Like a Prayer/03 - Love Song.flac"),
+            sheet.track(3)
+        );
+        assert_eq!(sheet.track_for("01 - Like a Prayer.mp3"), sheet.track(1));
+        assert!(sheet
+            .track_for("/nonexistent/02 - Express Yourself.flac")
+            .is_none());
+    }
+}
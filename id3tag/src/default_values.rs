@@ -3,10 +3,23 @@
 // Read default values from config file
 use anyhow::{bail, Context, Result};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use clap::{parser::ValueSource, ArgMatches};
+use common::Filter;
+
+use crate::csv_import::CsvTags;
+use crate::cue_sheet::{CueSheet, CUE_AUTO};
+use crate::directory_config::DirectoryConfigs;
+use crate::formats::filename_tags::FilenamePattern;
+use crate::formats::id3_versions::{Id3Version, Id3v1};
+use crate::formats::transform::{Case, CaseConverter, Substitution, DEFAULT_SMALL_WORDS};
+use crate::formats::wav::RiffInfo;
+use crate::journal::{self, DiffFormat, Journal};
 
 /// Check the command line (and environment) to see if flags have been set and update the corresponding values accordingly.
 ///
@@ -157,6 +170,12 @@ pub struct TagValues {
     /// Count the number of tracks
     pub track_count: Option<bool>,
 
+    /// Number the tracks in each folder based on their sort order
+    pub auto_track_number: Option<bool>,
+
+    /// What to sort the tracks on for `auto_track_number`: `name` (the default) or a tag name, eg. `TITLE`
+    pub auto_track_sort: Option<String>,
+
     /// Default value for the track's genre.
     pub track_genre: Option<String>,
 
@@ -175,6 +194,49 @@ pub struct TagValues {
 
     /// Default value for the track's comments.
     pub track_comments: Option<String>,
+
+    /// The International Standard Recording Code (ISRC) for the track, eg. `AUAP08000041`.
+    pub isrc: Option<String>,
+
+    /// The International Standard Musical Work Code (ISWC) for the composition, eg. `T-034.524.680-1`.
+    pub iswc: Option<String>,
+
+    /// The barcode (UPC or EAN) of the release.
+    pub barcode: Option<String>,
+
+    /// The catalog number of the release.
+    pub catalog_number: Option<String>,
+
+    /// The `MusicBrainz` recording ID for the track.
+    pub musicbrainz_track_id: Option<String>,
+
+    /// The `MusicBrainz` release ID for the album.
+    pub musicbrainz_album_id: Option<String>,
+
+    /// The `MusicBrainz` artist ID(s) for the track artist(s).
+    pub musicbrainz_artist_id: Option<String>,
+
+    /// The `MusicBrainz` artist ID(s) for the album artist(s).
+    pub musicbrainz_album_artist_id: Option<String>,
+
+    /// The `MusicBrainz` release group ID for the album.
+    pub musicbrainz_release_group_id: Option<String>,
+
+    /// The `MusicBrainz` disc ID for the disc.
+    pub musicbrainz_disc_id: Option<String>,
+
+    /// Arbitrary tags to set, keyed on the Vorbis comment style name (eg. `LABEL`, `ISRC`).
+    pub set: Option<HashMap<String, String>>,
+}
+
+/// A search-and-replace to perform on the existing value of a tag.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Replacement {
+    /// The tag to change, using the Vorbis comment style name (eg. `ARTIST`, `LABEL`).
+    pub tag: String,
+
+    /// A sed-style substitution, eg. `s/ ft\. / feat. /g`
+    pub expression: String,
 }
 
 //~ spec:startcode
@@ -198,6 +260,97 @@ pub struct DefaultValues {
 
     /// New filename pattern for rename
     pub rename_file: Option<String>,
+
+    /// Tags to remove from the files, eg. `["ENCODER", "COMMENT"]`
+    pub remove: Option<Vec<String>>,
+
+    /// Tags to keep. All other tags are removed.
+    pub keep: Option<Vec<String>>,
+
+    /// Remove all existing tags before setting new ones
+    pub clear_all: Option<bool>,
+
+    /// Only set new values for fields that are empty in the file
+    pub only_if_empty: Option<bool>,
+
+    /// Tags that are only set if they are empty in the file, eg. `["GENRE", "ALBUMARTIST"]`
+    pub fill_only: Option<Vec<String>>,
+
+    /// Search-and-replace operations on existing tag values
+    pub replace: Option<Vec<Replacement>>,
+
+    /// Change the capitalisation of tag values: `title`, `sentence`, `upper` or `lower`
+    pub case: Option<String>,
+
+    /// The tags to change the capitalisation of. Defaults to `ARTIST`, `ALBUMARTIST`, `ALBUM` and `TITLE`.
+    pub case_fields: Option<Vec<String>>,
+
+    /// Words kept in lower case in title case, eg. `["of", "the", "a"]`
+    pub case_small_words: Option<Vec<String>>,
+
+    /// Words always written as given, eg. `["AC/DC", "ABBA"]`
+    pub case_exceptions: Option<Vec<String>>,
+
+    /// A pattern for getting tag values from the path of the file, eg. `%aa/%at/%dn-%tn %tt`
+    pub tags_from_filename: Option<String>,
+
+    /// A regular expression with named groups for getting tag values from the path of the file
+    pub tags_from_filename_regex: Option<String>,
+
+    /// The name of a CUE sheet to read tag values from, or `auto` to look for one next to each music file
+    pub cue: Option<String>,
+
+    /// The values read from the `cue` file, if a file name was given
+    #[serde(skip)]
+    pub cue_sheet: Option<Arc<CueSheet>>,
+
+    /// The track numbers assigned by `auto_track_number`, keyed on the file name
+    #[serde(skip)]
+    pub track_numbers: Option<Arc<HashMap<String, u16>>>,
+
+    /// The file, or folder of files, to copy the tags and cover art from
+    pub copy_tags_from: Option<String>,
+
+    /// Split the new values on this separator into fields with more than one value, eg. `";"`
+    pub multi_value_separator: Option<String>,
+
+    /// The name of a CSV file to read tag values from, eg. one written by `id3export`
+    pub from_csv: Option<String>,
+
+    /// The tag values read from the `from_csv` file
+    #[serde(skip)]
+    pub csv_tags: Option<Arc<CsvTags>>,
+
+    /// What to do with the `LIST/INFO` chunk in WAV files: `sync` (the default), `keep` or `remove`
+    pub riff_info: Option<String>,
+
+    /// The version of the `ID3v2` tags to write to MP3, DSF, DSDIFF, WAV and AIFF files: `2.3` or `2.4` (the default)
+    pub id3_version: Option<String>,
+
+    /// What to do with the `ID3v1` tag in MP3 files: `keep` (the default), `update`, `write` or `remove`
+    pub id3v1: Option<String>,
+
+    /// The file to write the undo journal to. A new file in `~/.config/id3tag/journals` is used if this isn't set.
+    pub journal: Option<String>,
+
+    /// How dry runs show the changes that would be made: `text` (the default) or `json`
+    pub diff_format: Option<String>,
+
+    /// Only process the files whose existing tags match this expression, eg. `genre == "Rock" && date < 1990`
+    #[serde(rename = "where")]
+    pub filter: Option<String>,
+
+    /// The parsed `filter` expression
+    #[serde(skip)]
+    pub file_filter: Option<Filter>,
+
+    /// The changes made in this run, which are written to the `journal` file, or shown as a preview on a dry run
+    #[serde(skip)]
+    pub journal_entries: Option<Arc<Journal>>,
+
+    /// The values from the `.id3tag.toml` files in the folders of the music files
+    #[serde(skip)]
+    pub directory_configs: Option<Arc<DirectoryConfigs>>,
 }
 //~ spec:endcode
 
@@ -215,15 +368,20 @@ impl DefaultValues {
         cfg.pictures.picture_search_folders = Some(psf_list);
 
         // Read the config file
+        let mut config_filename = None;
         if cli.contains_id("config-file") {
             let default_config = String::from("~/.config/id3tag/config.toml");
             let config_path = cli
                 .get_one::<String>("config-file")
                 .unwrap_or(&default_config);
-            let config_filename = shellexpand::tilde(config_path).to_string();
-            cfg = Self::load_config(&config_filename)?;
+            let filename = shellexpand::tilde(config_path).to_string();
+            cfg = Self::load_config(&filename)?;
+            config_filename = Some(filename);
         }
 
+        // The `.id3tag.toml` files in the folders are read on top of the config file as the files are processed
+        cfg.directory_configs = Some(Arc::new(DirectoryConfigs::new(config_filename.as_deref())?));
+
         // Collate config file flags and CLI flags and output the right config
         check_flag!(cli, "stop-on-error", cfg, stop_on_error);
         check_flag!(cli, "print-summary", cfg, print_summary);
@@ -236,6 +394,22 @@ impl DefaultValues {
         cfg.check_for_picture_max_size(cli);
         cfg.check_for_picture_front_candidates(cli);
         cfg.check_for_picture_back_candidates(cli);
+        cfg.check_for_set_tags(cli)?;
+        cfg.check_for_tag_removal(cli);
+        cfg.check_for_fill_only(cli);
+        cfg.check_for_replace(cli)?;
+        cfg.check_for_case(cli)?;
+        cfg.check_for_tags_from_filename(cli)?;
+        cfg.check_for_cue(cli)?;
+        cfg.check_for_auto_track_number(cli);
+        cfg.check_for_copy_tags_from(cli)?;
+        cfg.check_for_multi_value_separator(cli)?;
+        cfg.check_for_csv(cli)?;
+        cfg.check_for_riff_info(cli)?;
+        cfg.check_for_id3_version(cli)?;
+        cfg.check_for_id3v1(cli)?;
+        cfg.check_for_journal(cli)?;
+        cfg.check_for_filter(cli)?;
 
         Ok(cfg)
     }
@@ -262,8 +436,32 @@ impl DefaultValues {
             }
         };
 
-        // Check if the picture_search_folders contain "." and "..". Add them if not.
-        let mut psf = config
+        config.add_default_search_folders();
+
+        Ok(config)
+    }
+
+    /// Builds the config for a folder from the merged values of the config file and the `.id3tag.toml` files,
+    /// and applies the cover art and tag value options from the CLI on top, the same way as `build_config` does.
+    pub fn from_directory_table(table: toml::Table, cli: &ArgMatches) -> Result<Self> {
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .context("Unable to read the .id3tag.toml config")?;
+        config.add_default_search_folders();
+
+        config.add_picture_search_folders(cli);
+        config.check_for_picture_max_size(cli);
+        config.check_for_picture_front_candidates(cli);
+        config.check_for_picture_back_candidates(cli);
+        config.check_for_set_tags(cli)?;
+        config.check_for_auto_track_number(cli);
+
+        Ok(config)
+    }
+
+    /// Check if the `picture_search_folders` contain "." and "..". Add them if not.
+    fn add_default_search_folders(&mut self) {
+        let mut psf = self
             .pictures
             .picture_search_folders
             .clone()
@@ -274,9 +472,7 @@ impl DefaultValues {
         if !psf.contains(&"..".to_string()) {
             psf.push("..".to_string());
         }
-        config.pictures.picture_search_folders = Some(psf);
-
-        Ok(config)
+        self.pictures.picture_search_folders = Some(psf);
     }
 
     // Housekeeping functions to check which flags have been set, either on the CLI or in the config file.
@@ -367,6 +563,395 @@ impl DefaultValues {
             self.pictures.picture_back_candidates = Some(candidate_list);
         }
     }
+
+    /// Add the tags to remove or keep from the CLI to the config. CLI values replace any values from the config file.
+    fn check_for_tag_removal(&mut self, args: &clap::ArgMatches) {
+        if let Some(remove) = args.get_many::<String>("remove") {
+            self.remove = Some(remove.map(|k| k.trim().to_string()).collect());
+            log::debug!("remove = {:?}", self.remove);
+        }
+
+        if let Some(keep) = args.get_many::<String>("keep") {
+            self.keep = Some(keep.map(|k| k.trim().to_string()).collect());
+            log::debug!("keep = {:?}", self.keep);
+        }
+
+        if args.get_flag("clear-all") {
+            self.clear_all = Some(true);
+        }
+    }
+
+    /// Add the `--only-if-empty` flag from the CLI to the config. The `fill_only` list only comes from the config file.
+    fn check_for_fill_only(&mut self, args: &clap::ArgMatches) {
+        if args.get_flag("only-if-empty") {
+            self.only_if_empty = Some(true);
+        }
+        log::debug!(
+            "only_if_empty = {:?}, fill_only = {:?}",
+            self.only_if_empty,
+            self.fill_only
+        );
+    }
+
+    /// Add the `--set KEY=VALUE` tags from the CLI to the config. CLI values override any values
+    /// for the same key from the config file. Returns an error if a value isn't in the `KEY=VALUE` form.
+    fn check_for_set_tags(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(pairs) = args.get_many::<String>("set") {
+            let set = self.tags.set.get_or_insert_with(HashMap::new);
+            for pair in pairs {
+                match pair.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => {
+                        set.insert(key.trim().to_string(), value.to_string());
+                    }
+                    _ => bail!("Invalid --set value \"{pair}\". Expected KEY=VALUE."),
+                }
+            }
+            log::debug!("set = {set:?}");
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--replace TAG EXPRESSION` pairs from the CLI to any replacements from the config file,
+    /// and check that all the expressions are valid.
+    fn check_for_replace(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(pairs) = args.get_occurrences::<String>("replace") {
+            let replace = self.replace.get_or_insert_with(Vec::new);
+            for mut pair in pairs {
+                if let (Some(tag), Some(expression)) = (pair.next(), pair.next()) {
+                    replace.push(Replacement {
+                        tag: tag.trim().to_string(),
+                        expression: expression.clone(),
+                    });
+                }
+            }
+        }
+
+        for r in self.replace.iter().flatten() {
+            if r.tag.is_empty() {
+                bail!("No tag given for the replacement \"{}\".", r.expression);
+            }
+            Substitution::parse(&r.expression)
+                .with_context(|| format!("Unable to use the replacement for {}", r.tag))?;
+        }
+        log::debug!("replace = {:?}", self.replace);
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the case options from the CLI to the config. CLI values replace any values from the config file.
+    /// Returns an error if the case isn't one of `title`, `sentence`, `upper` or `lower`.
+    fn check_for_case(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(case) = args.get_one::<String>("case") {
+            self.case = Some(case.clone());
+        }
+
+        let list = |id: &str| {
+            args.get_many::<String>(id)
+                .map(|v| v.map(|s| s.trim().to_string()).collect::<Vec<String>>())
+        };
+        if let Some(fields) = list("case-fields") {
+            self.case_fields = Some(fields);
+        }
+        if let Some(small_words) = list("case-small-words") {
+            self.case_small_words = Some(small_words);
+        }
+        if let Some(exceptions) = list("case-exceptions") {
+            self.case_exceptions = Some(exceptions);
+        }
+
+        if let Some(case) = &self.case {
+            Case::from_str(case)?;
+            log::debug!("case = {case}, case_fields = {:?}", self.case_fields);
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--tags-from-filename` pattern or `--tags-from-filename-regex` expression from the CLI to the config,
+    /// replacing both values from the config file, and check that it is valid.
+    fn check_for_tags_from_filename(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(pattern) = args.get_one::<String>("tags-from-filename") {
+            self.tags_from_filename = Some(pattern.clone());
+            self.tags_from_filename_regex = None;
+        } else if let Some(expression) = args.get_one::<String>("tags-from-filename-regex") {
+            self.tags_from_filename = None;
+            self.tags_from_filename_regex = Some(expression.clone());
+        }
+
+        if self.tags_from_filename.is_some() && self.tags_from_filename_regex.is_some() {
+            bail!("Use either tags_from_filename or tags_from_filename_regex, not both.");
+        }
+
+        self.filename_pattern()?;
+        log::debug!(
+            "tags_from_filename = {:?}, tags_from_filename_regex = {:?}",
+            self.tags_from_filename,
+            self.tags_from_filename_regex
+        );
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Returns the pattern for getting tag values from the path of the file, or `None` if there isn't one.
+    pub fn filename_pattern(&self) -> Result<Option<FilenamePattern>> {
+        if let Some(pattern) = &self.tags_from_filename {
+            return Ok(Some(FilenamePattern::parse(pattern)?));
+        }
+        if let Some(expression) = &self.tags_from_filename_regex {
+            return Ok(Some(FilenamePattern::from_regex(expression)?));
+        }
+
+        Ok(None)
+    }
+
+    /// Add the `--auto-track-number` flag and `--auto-track-sort` key from the CLI to the config,
+    /// overriding any values from the config file.
+    fn check_for_auto_track_number(&mut self, args: &clap::ArgMatches) {
+        if args.value_source("auto-track-number") == Some(ValueSource::CommandLine) {
+            self.tags.auto_track_number = Some(true);
+        }
+        if let Some(sort) = args.get_one::<String>("auto-track-sort") {
+            self.tags.auto_track_sort = Some(sort.clone());
+        }
+        log::debug!(
+            "auto_track_number = {:?}, auto_track_sort = {:?}",
+            self.tags.auto_track_number,
+            self.tags.auto_track_sort
+        );
+    }
+
+    /// Add the `--copy-tags-from` file or folder from the CLI to the config, overriding any value from the config file,
+    /// and check that it exists.
+    fn check_for_copy_tags_from(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(source) = args.get_one::<String>("copy-tags-from") {
+            self.copy_tags_from = Some(source.clone());
+        }
+
+        if let Some(source) = &self.copy_tags_from {
+            let source = shellexpand::tilde(source).to_string();
+            if !std::path::Path::new(&source).exists() {
+                bail!("Unable to find {source} to copy the tags from.");
+            }
+            log::debug!("copy_tags_from = {source}");
+            self.copy_tags_from = Some(source);
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--multi-value-separator` from the CLI to the config, overriding any value from the config file.
+    fn check_for_multi_value_separator(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(separator) = args.get_one::<String>("multi-value-separator") {
+            self.multi_value_separator = Some(separator.clone());
+        }
+
+        if self.multi_value_separator.as_deref() == Some("") {
+            bail!("The multi-value separator can't be empty.");
+        }
+        log::debug!("multi_value_separator = {:?}", self.multi_value_separator);
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--cue` file from the CLI to the config, overriding any value from the config file,
+    /// and read the values from it unless the CUE sheets are to be found next to the music files.
+    fn check_for_cue(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(filename) = args.get_one::<String>("cue") {
+            self.cue = Some(filename.clone());
+        }
+
+        if let Some(filename) = self.cue.as_ref().filter(|f| *f != CUE_AUTO) {
+            let filename = shellexpand::tilde(filename).to_string();
+            let cue_sheet = CueSheet::load(&filename)?;
+            log::debug!(
+                "cue = {filename}, {} track(s) found.",
+                cue_sheet.tracks.len()
+            );
+            self.cue_sheet = Some(Arc::new(cue_sheet));
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--where` expression from the CLI to the config, overriding any value from the config file,
+    /// and parse it. Returns an error if the expression isn't valid.
+    fn check_for_filter(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(expression) = args.get_one::<String>("where") {
+            self.filter = Some(expression.clone());
+        }
+
+        if let Some(expression) = &self.filter {
+            let filter = expression
+                .parse::<Filter>()
+                .with_context(|| format!("Unable to use the filter {expression}"))?;
+            log::debug!("filter = {filter}");
+            self.file_filter = Some(filter);
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--from-csv` file from the CLI to the config, overriding any value from the config file,
+    /// and read the tag values from it.
+    fn check_for_csv(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(filename) = args.get_one::<String>("from-csv") {
+            self.from_csv = Some(filename.clone());
+        }
+
+        if let Some(filename) = &self.from_csv {
+            let filename = shellexpand::tilde(filename).to_string();
+            let csv_tags = CsvTags::load(&filename)?;
+            log::debug!(
+                "from_csv = {filename}, {} file(s) found.",
+                csv_tags.paths().len()
+            );
+            self.csv_tags = Some(Arc::new(csv_tags));
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--riff-info` setting from the CLI to the config, overriding any value from the config file,
+    /// and check that it is valid.
+    fn check_for_riff_info(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(setting) = args.get_one::<String>("riff-info") {
+            self.riff_info = Some(setting.clone());
+        }
+
+        self.riff_info_setting()?;
+        log::debug!("riff_info = {:?}", self.riff_info);
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Returns the `riff_info` setting, or `RiffInfo::Sync` if it isn't set.
+    pub fn riff_info_setting(&self) -> Result<RiffInfo> {
+        self.riff_info
+            .as_deref()
+            .map_or(Ok(RiffInfo::default()), RiffInfo::from_str)
+    }
+
+    /// Add the `--id3-version` setting from the CLI to the config, overriding any value from the config file,
+    /// and check that it is valid.
+    fn check_for_id3_version(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(setting) = args.get_one::<String>("id3-version") {
+            self.id3_version = Some(setting.clone());
+        }
+
+        self.id3_version_setting()?;
+        log::debug!("id3_version = {:?}", self.id3_version);
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Returns the `id3_version` setting, or `Id3Version::V24` if it isn't set.
+    pub fn id3_version_setting(&self) -> Result<Id3Version> {
+        self.id3_version
+            .as_deref()
+            .map_or(Ok(Id3Version::default()), Id3Version::from_str)
+    }
+
+    /// Add the `--id3v1` setting from the CLI to the config, overriding any value from the config file,
+    /// and check that it is valid.
+    fn check_for_id3v1(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(setting) = args.get_one::<String>("id3v1") {
+            self.id3v1 = Some(setting.clone());
+        }
+
+        self.id3v1_setting()?;
+        log::debug!("id3v1 = {:?}", self.id3v1);
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Add the `--journal` file and `--diff-format` setting from the CLI to the config, overriding any values from
+    /// the config file, and start the undo journal unless this is an undo. Dry runs show the journal as a preview,
+    /// so it is only started for them if the preview is shown, ie. `--detail-off` isn't set.
+    fn check_for_journal(&mut self, args: &clap::ArgMatches) -> Result<()> {
+        if let Some(filename) = args.get_one::<String>("journal") {
+            self.journal = Some(filename.clone());
+        }
+        if let Some(format) = args.get_one::<String>("diff-format") {
+            self.diff_format = Some(format.clone());
+        }
+        self.diff_format_setting()?;
+
+        if !args.contains_id("undo") {
+            if !self.execution.dry_run.unwrap_or(true) {
+                let filename = self
+                    .journal
+                    .as_deref()
+                    .map_or_else(journal::default_filename, |f| {
+                        shellexpand::tilde(f).to_string()
+                    });
+                self.journal = Some(filename);
+                self.journal_entries = Some(Arc::new(Journal::default()));
+            } else if self.shows_preview() {
+                self.journal_entries = Some(Arc::new(Journal::for_preview()));
+            }
+        }
+        log::debug!(
+            "journal = {:?}, diff_format = {:?}",
+            self.journal,
+            self.diff_format
+        );
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Checks if the changes are shown as a preview at the end of the run, ie. on a dry run without `--detail-off`.
+    pub fn shows_preview(&self) -> bool {
+        self.execution.dry_run.unwrap_or(true) && !self.execution.detail_off.unwrap_or(false)
+    }
+
+    /// Returns the `diff_format` setting, or `DiffFormat::Text` if it isn't set.
+    pub fn diff_format_setting(&self) -> Result<DiffFormat> {
+        self.diff_format
+            .as_deref()
+            .map_or(Ok(DiffFormat::default()), DiffFormat::from_str)
+    }
+
+    /// Returns the `id3v1` setting, or `Id3v1::Keep` if it isn't set.
+    pub fn id3v1_setting(&self) -> Result<Id3v1> {
+        self.id3v1
+            .as_deref()
+            .map_or(Ok(Id3v1::default()), Id3v1::from_str)
+    }
+
+    /// Returns the converter for the `case` option, or `None` if it isn't set.
+    pub fn case_converter(&self) -> Result<Option<CaseConverter>> {
+        let Some(case) = &self.case else {
+            return Ok(None);
+        };
+
+        let small_words = self.case_small_words.clone().unwrap_or_else(|| {
+            DEFAULT_SMALL_WORDS
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        });
+        let exceptions = self.case_exceptions.clone().unwrap_or_default();
+
+        Ok(Some(CaseConverter::new(
+            Case::from_str(case)?,
+            &small_words,
+            &exceptions,
+        )))
+    }
 } // impl DefaultValues
 
 // --------------------------------------------------------------------------------------------------------------------
@@ -374,12 +959,10 @@ impl DefaultValues {
 // --------------------------------------------------------------------------------------------------------------------
 
 #[cfg(test)]
-///
 mod tests {
     use super::*;
 
     #[test]
-    ///
     fn test_new_default_values() {
         // Create a blank config
         let mut dfv = DefaultValues::new();
@@ -492,13 +1075,22 @@ mod tests {
 
         assert_eq!(dfvu.rename_file.unwrap(), "%dn-%tn - %ta - %tt".to_string());
 
+        let set = dfvu.tags.set.unwrap();
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get("LABEL").unwrap(), "Deutsche Grammophon");
+        assert_eq!(set.get("CATALOGNUMBER").unwrap(), "477 6242");
+
+        let replace = dfvu.replace.unwrap();
+        assert_eq!(replace.len(), 1);
+        assert_eq!(replace[0].tag, "ARTIST");
+        assert_eq!(replace[0].expression, r"s/ ft\. / feat. /g");
+
         // Loading a non-existent config file should give an error.
-        let dfv2 = DefaultValues::load_config("missing-file.toml");
-        assert!(dfv2.is_err());
+        let missing_config = DefaultValues::load_config("missing-file.toml");
+        assert!(missing_config.is_err());
     }
 
     #[test]
-    ///
     fn test_search_folders() {
         let mut cfg = DefaultValues::new();
 
@@ -522,4 +1114,192 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    /// Tests that `--set KEY=VALUE` gets added to the config, and that invalid values are rejected.
+    fn test_check_for_set_tags() {
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--set",
+            "LABEL=Reprise",
+            "--set",
+            "COMMENT=a=b",
+        ]);
+        let mut cfg = DefaultValues::new();
+        cfg.tags.set = Some(HashMap::from([
+            ("LABEL".to_string(), "Warner".to_string()),
+            ("ISRC".to_string(), "USRC17607839".to_string()),
+        ]));
+        assert!(cfg.check_for_set_tags(&cli).is_ok());
+
+        let set = cfg.tags.set.unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get("LABEL").unwrap(), "Reprise");
+        assert_eq!(set.get("COMMENT").unwrap(), "a=b");
+        assert_eq!(set.get("ISRC").unwrap(), "USRC17607839");
+
+        let cli =
+            common::build_cli("0.0.0").get_matches_from(["id3tag", "file.flac", "--set", "LABEL"]);
+        assert!(DefaultValues::new().check_for_set_tags(&cli).is_err());
+
+        let cli =
+            common::build_cli("0.0.0").get_matches_from(["id3tag", "file.flac", "--set", "=value"]);
+        assert!(DefaultValues::new().check_for_set_tags(&cli).is_err());
+    }
+
+    #[test]
+    /// Tests that `--replace TAG EXPRESSION` gets added to the config, and that invalid expressions are rejected.
+    fn test_check_for_replace() {
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--replace",
+            "TITLE",
+            r"s/ \(Remastered \d*\)//",
+            "--replace",
+            "LABEL",
+            "s/^EMI$/Parlophone/",
+        ]);
+        let mut cfg = DefaultValues::new();
+        cfg.replace = Some(vec![Replacement {
+            tag: "ARTIST".to_string(),
+            expression: "s/ ft. / feat. /".to_string(),
+        }]);
+        assert!(cfg.check_for_replace(&cli).is_ok());
+
+        let replace = cfg.replace.unwrap();
+        assert_eq!(replace.len(), 3);
+        assert_eq!(replace[1].tag, "TITLE");
+        assert_eq!(replace[2].tag, "LABEL");
+        assert_eq!(replace[2].expression, "s/^EMI$/Parlophone/");
+
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--replace",
+            "TITLE",
+            "s/(unclosed//",
+        ]);
+        assert!(DefaultValues::new().check_for_replace(&cli).is_err());
+    }
+
+    #[test]
+    /// Tests that the filename pattern from the CLI replaces the one from the config file, and that invalid patterns are rejected.
+    fn test_check_for_tags_from_filename() {
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--tags-from-filename",
+            "%aa/%at/%tn %tt",
+        ]);
+        let mut cfg = DefaultValues::new();
+        cfg.tags_from_filename_regex = Some(r"(?P<TITLE>.+)".to_string());
+        assert!(cfg.check_for_tags_from_filename(&cli).is_ok());
+        assert_eq!(cfg.tags_from_filename.as_deref(), Some("%aa/%at/%tn %tt"));
+        assert!(cfg.tags_from_filename_regex.is_none());
+        assert!(cfg.filename_pattern().unwrap().is_some());
+
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--tags-from-filename-regex",
+            r"(\d+) (.+)",
+        ]);
+        assert!(DefaultValues::new()
+            .check_for_tags_from_filename(&cli)
+            .is_err());
+
+        let cli = common::build_cli("0.0.0").get_matches_from(["id3tag", "file.flac"]);
+        let mut cfg = DefaultValues::new();
+        cfg.tags_from_filename = Some("%tn %tt".to_string());
+        cfg.tags_from_filename_regex = Some(r"(?P<TITLE>.+)".to_string());
+        assert!(cfg.check_for_tags_from_filename(&cli).is_err());
+        assert!(DefaultValues::new().filename_pattern().unwrap().is_none());
+    }
+
+    #[test]
+    /// Tests that the case options get added to the config, and that an unknown case is rejected.
+    fn test_check_for_case() {
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--case",
+            "title",
+            "--case-fields",
+            "ARTIST,TITLE",
+            "--case-exceptions",
+            "AC/DC",
+        ]);
+        let mut cfg = DefaultValues::new();
+        cfg.case_exceptions = Some(vec!["ABBA".to_string()]);
+        assert!(cfg.check_for_case(&cli).is_ok());
+        assert_eq!(cfg.case.as_deref(), Some("title"));
+        assert_eq!(cfg.case_fields.as_ref().unwrap().len(), 2);
+        assert_eq!(
+            cfg.case_exceptions.as_ref().unwrap(),
+            &vec!["AC/DC".to_string()]
+        );
+
+        let converter = cfg.case_converter().unwrap().unwrap();
+        assert_eq!(
+            converter.apply("back in black by ac/dc"),
+            "Back in Black by AC/DC"
+        );
+
+        cfg.case = Some("camel".to_string());
+        let cli = common::build_cli("0.0.0").get_matches_from(["id3tag", "file.flac"]);
+        assert!(cfg.check_for_case(&cli).is_err());
+
+        assert!(DefaultValues::new().case_converter().unwrap().is_none());
+    }
+
+    #[test]
+    /// Tests that the journal is only started when it will be written, or shown as the dry-run preview.
+    fn test_check_for_journal() {
+        let cli = common::build_cli("0.0.0").get_matches_from(["id3tag", "file.flac"]);
+        let mut cfg = DefaultValues::new();
+        cfg.execution.dry_run = Some(true);
+        assert!(cfg.check_for_journal(&cli).is_ok());
+        assert!(cfg.journal_entries.is_some());
+        assert!(cfg.journal.is_none());
+
+        // No preview, so no journal
+        let mut cfg = DefaultValues::new();
+        cfg.execution.dry_run = Some(true);
+        cfg.execution.detail_off = Some(true);
+        assert!(cfg.check_for_journal(&cli).is_ok());
+        assert!(cfg.journal_entries.is_none());
+
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--journal",
+            "retag.json",
+        ]);
+        let mut cfg = DefaultValues::new();
+        cfg.execution.dry_run = Some(false);
+        cfg.execution.detail_off = Some(true);
+        assert!(cfg.check_for_journal(&cli).is_ok());
+        assert!(cfg.journal_entries.is_some());
+        assert_eq!(cfg.journal.as_deref(), Some("retag.json"));
+
+        let cli = common::build_cli("0.0.0").get_matches_from(["id3tag", "--undo", "retag.json"]);
+        let mut cfg = DefaultValues::new();
+        cfg.execution.dry_run = Some(false);
+        assert!(cfg.check_for_journal(&cli).is_ok());
+        assert!(cfg.journal_entries.is_none());
+    }
+
+    #[test]
+    /// Tests that the preview is only shown on dry runs without `--detail-off`.
+    fn test_shows_preview() {
+        let mut cfg = DefaultValues::new();
+        assert!(cfg.shows_preview());
+        cfg.execution.detail_off = Some(true);
+        assert!(!cfg.shows_preview());
+        cfg.execution.detail_off = Some(false);
+        cfg.execution.dry_run = Some(false);
+        assert!(!cfg.shows_preview());
+    }
 }
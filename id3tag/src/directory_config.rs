@@ -0,0 +1,285 @@
+//! Reads the `.id3tag.toml` files in the folders of the music files, so each folder can have its own default values.
+//!
+//! The files are looked for in the folder of each music file and its parent folders, up to and including a file
+//! with `root = true` in it. They are merged on top of the config file given with `-c`, with the nearest file
+//! winning, and the CLI options are then applied on top of that.
+//!
+//! Only the tag values and the cover art settings are read from these files. Any other keys are ignored, with a
+//! warning the first time each file is read.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::default_values::{DefaultValues, PictureConfig, TagValues};
+
+/// The name of the config file looked for in each folder.
+pub const CONFIG_FILENAME: &str = ".id3tag.toml";
+
+/// The key that stops the search for more files in the parent folders.
+const ROOT_KEY: &str = "root";
+
+/// The keys the auto track numbering reads. The track numbers are worked out once for the whole run, so these can't
+/// be set per folder.
+const AUTO_TRACK_KEYS: [&str; 2] = ["auto_track_number", "auto_track_sort"];
+
+/// The values that can be set in a `.id3tag.toml` file. Used to find the keys that aren't supported.
+#[derive(Debug, Default, Deserialize)]
+struct DirectoryValues {
+    /// The cover art settings.
+    #[serde(flatten)]
+    _pictures: PictureConfig,
+
+    /// The tag values, including `[set]`.
+    #[serde(flatten)]
+    _tags: TagValues,
+
+    /// Everything else.
+    #[serde(flatten)]
+    other: toml::Table,
+}
+
+/// The folder configs for the files processed, keyed on the folder.
+#[derive(Debug, Default)]
+pub struct DirectoryConfigs {
+    /// The values from the config file given with `-c`, if any.
+    base: toml::Table,
+
+    /// The merged config for each folder seen so far. `None` if there are no `.id3tag.toml` files for the folder.
+    cache: Mutex<HashMap<PathBuf, Option<Arc<DefaultValues>>>>,
+
+    /// The `.id3tag.toml` files already checked for keys that aren't supported, so each is only warned about once.
+    checked: Mutex<HashSet<PathBuf>>,
+}
+
+impl DirectoryConfigs {
+    /// Sets up the folder configs, using the values in the config file given with `-c` as the starting point.
+    /// A config file that isn't valid TOML is ignored, the same way as when it is loaded.
+    pub fn new(config_file: Option<&str>) -> Result<Self> {
+        let base = match config_file {
+            Some(filename) => fs::read_to_string(filename)
+                .with_context(|| format!("Failed to open config file {filename}"))?
+                .parse()
+                .unwrap_or_default(),
+            None => toml::Table::new(),
+        };
+
+        Ok(Self {
+            base,
+            cache: Mutex::new(HashMap::new()),
+            checked: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Gets the config for the folder the music file is in, with the options from the CLI applied.
+    /// Returns `None` if there are no `.id3tag.toml` files in the folder or its parents.
+    pub fn config_for(
+        &self,
+        filename: &str,
+        cli: &clap::ArgMatches,
+    ) -> Result<Option<Arc<DefaultValues>>> {
+        let path = std::path::absolute(filename)
+            .with_context(|| format!("Unable to find the folder of {filename}"))?;
+        let dir = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+        if let Some(config) = self.lock_cache().get(&dir) {
+            return Ok(config.clone());
+        }
+
+        let tables = config_tables(&dir)?;
+        let config = if tables.is_empty() {
+            None
+        } else {
+            // Merge the farthest file first, so the nearer ones override it
+            let mut table = self.base.clone();
+            for (path, t) in tables.into_iter().rev() {
+                let first_time = self
+                    .checked
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .insert(path.clone());
+                if first_time {
+                    for key in unsupported_keys(&t)
+                        .with_context(|| format!("Unable to read config from {}", path.display()))?
+                    {
+                        log::warn!(
+                            "{key} in {} is ignored. Only tag values and cover art settings can be set per folder.",
+                            path.display()
+                        );
+                    }
+                }
+                merge_tables(&mut table, t);
+            }
+            log::debug!("config_for::{} = {table:?}", dir.display());
+            Some(Arc::new(DefaultValues::from_directory_table(table, cli)?))
+        };
+
+        self.lock_cache().insert(dir, config.clone());
+        Ok(config)
+    }
+
+    /// Locks the cache. A poisoned lock is still used, as the cache is only ever added to.
+    fn lock_cache(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Option<Arc<DefaultValues>>>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Reads the `.id3tag.toml` files in the folder and its parents, nearest first, along with the path of each.
+/// Stops at the first file with `root = true` in it.
+fn config_tables(dir: &Path) -> Result<Vec<(PathBuf, toml::Table)>> {
+    let mut tables = Vec::new();
+
+    for folder in dir.ancestors() {
+        let path = folder.join(CONFIG_FILENAME);
+        if !path.is_file() {
+            continue;
+        }
+
+        let mut table: toml::Table = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?
+            .parse()
+            .with_context(|| format!("Unable to read config from {}", path.display()))?;
+        log::debug!("config_tables::Read {}", path.display());
+
+        let root = table.remove(ROOT_KEY).and_then(|v| v.as_bool()) == Some(true);
+        tables.push((path, table));
+        if root {
+            break;
+        }
+    }
+
+    // Return safely
+    Ok(tables)
+}
+
+/// Gets the keys in a `.id3tag.toml` file that can't be set per folder, sorted by name.
+fn unsupported_keys(table: &toml::Table) -> Result<Vec<String>> {
+    let values: DirectoryValues = toml::Value::Table(table.clone()).try_into()?;
+
+    // Return safely
+    Ok(table
+        .keys()
+        .filter(|key| values.other.contains_key(*key) || AUTO_TRACK_KEYS.contains(&key.as_str()))
+        .cloned()
+        .collect())
+}
+
+/// Merges the overlay into the base. Tables, eg. `set`, are merged key by key. Other values, including lists,
+/// replace the ones in the base.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(b)), toml::Value::Table(o)) => merge_tables(b, o),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::test_files::TestFiles;
+
+    #[test]
+    /// Tests that nearer files override farther ones, the `-c` values are used underneath, `root` stops the search,
+    /// and the CLI overrides everything.
+    fn test_config_for() {
+        let files = TestFiles::new();
+        let dir = files.dir();
+        let album = dir.join("Library/Bach/Goldberg Variations");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(dir.join(CONFIG_FILENAME), "track_genre = \"Pop\"\n").unwrap();
+        fs::write(
+            dir.join("Library").join(CONFIG_FILENAME),
+            "root = true\ntrack_genre = \"Classical\"\nalbum_artist = \"Various\"\n[set]\nLABEL = \"DG\"\n",
+        )
+        .unwrap();
+        fs::write(
+            album.join(CONFIG_FILENAME),
+            "album_artist = \"Bach, Johann Sebastian\"\ndisc_number_total = 2\npicture_front_candidates = [\"goldberg.jpg\"]\n[set]\nMEDIA = \"CD\"\n",
+        )
+        .unwrap();
+        let config_file = dir.join("config.toml");
+        fs::write(
+            &config_file,
+            "album_title = \"Goldberg Variations\"\ntrack_genre = \"Jazz\"\n",
+        )
+        .unwrap();
+
+        let configs = DirectoryConfigs::new(config_file.to_str()).unwrap();
+        let cli = common::build_cli("0.0.0").get_matches_from([
+            "id3tag",
+            "file.flac",
+            "--set",
+            "MEDIA=SACD",
+        ]);
+        let music_file = album.join("01 Aria.flac");
+        let config = configs
+            .config_for(&music_file.to_string_lossy(), &cli)
+            .unwrap()
+            .unwrap();
+
+        let tags = &config.tags;
+        assert_eq!(tags.album_artist.as_deref(), Some("Bach, Johann Sebastian"));
+        assert_eq!(tags.album_title.as_deref(), Some("Goldberg Variations"));
+        assert_eq!(tags.track_genre.as_deref(), Some("Classical"));
+        assert_eq!(tags.disc_number_total, Some(2));
+        let set = tags.set.as_ref().unwrap();
+        assert_eq!(set["LABEL"], "DG");
+        assert_eq!(set["MEDIA"], "SACD");
+        assert_eq!(
+            config.pictures.picture_front_candidates,
+            Some(vec!["goldberg.jpg".to_string()])
+        );
+        assert_eq!(config.pictures.search_folders(), vec![".", ".."]);
+
+        // Files outside the folders with `.id3tag.toml` files don't get a config
+        let other = DirectoryConfigs::new(None).unwrap();
+        let elsewhere = TestFiles::new();
+        assert!(other
+            .config_for(&elsewhere.path("nothing.flac").to_string_lossy(), &cli)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    /// Tests that the keys that can't be set per folder are found.
+    fn test_unsupported_keys() {
+        let table: toml::Table = "album_artist = \"Bach\"\nrename_file = \"%tn\"\npicture_max_size = 500\nauto_track_number = true\ndry_run = true\nremove = [\"COMMENT\"]\n[set]\nMEDIA = \"CD\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            unsupported_keys(&table).unwrap(),
+            vec!["auto_track_number", "dry_run", "remove", "rename_file"]
+        );
+
+        let bad: toml::Table = "disc_number_total = \"two\"\n".parse().unwrap();
+        assert!(unsupported_keys(&bad).is_err());
+    }
+
+    #[test]
+    /// Tests merging tables.
+    fn test_merge_tables() {
+        let mut base: toml::Table = "a = 1\nlist = [1, 2]\n[set]\nX = \"1\"\nY = \"2\"\n"
+            .parse()
+            .unwrap();
+        let overlay: toml::Table = "a = 2\nlist = [3]\n[set]\nY = \"3\"\n".parse().unwrap();
+        merge_tables(&mut base, overlay);
+
+        let expected: toml::Table = "a = 2\nlist = [3]\n[set]\nX = \"1\"\nY = \"3\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(base, expected);
+    }
+}
@@ -1,30 +1,35 @@
-//! Contains the functionality to process APE files.
+//! Contains the functionality to process files with `APEv2` tags: Monkey's Audio (APE), `WavPack`, Musepack and TAK.
 //! KEY: <https://wiki.hydrogenaud.io/index.php?title=APE_key>
 
 use crate::default_values::DefaultValues;
-use crate::formats::images::read_cover;
+use crate::formats::images::{read_cover, CoverType};
+use crate::formats::removals::{log_removal, Removals};
+use crate::formats::tags;
+use crate::rename_file;
 use anyhow::{Context, Result};
 use ape::{self, Item, ItemType};
+use common::FileTypes;
 use std::{collections::HashMap, fs::File};
 
-/// Performs the actual processing of APE files.
+/// Performs the actual processing of APE, `WavPack`, Musepack and TAK files.
 pub fn process(
     filename: &str,
+    file_type: FileTypes,
     new_tags: &HashMap<String, String>,
     config: &DefaultValues,
 ) -> Result<bool> {
     let mut processed_ok = false;
-    let mut tags = ape::read_from_path(filename)?;
+    let mut tags = read_tag(filename)?;
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(file_type, config);
+    if !removals.is_empty() {
+        remove_tags(filename, &mut tags, &removals, config);
+    }
 
     // Set new tags
     for (key, value) in new_tags {
-        if config.execution.detail_off.unwrap_or(false) {
-            log::debug!("{filename} :: New {key} = {value}");
-        } else if config.execution.dry_run.unwrap_or(false) {
-            log::info!("{filename} :: New {key} = {value}");
-        } else {
-            log::debug!("{filename} :: New {key} = {value}");
-        }
+        tags::log_new_value(filename, key, value, config);
 
         // Process the tags
         match key.as_ref() {
@@ -48,11 +53,12 @@ pub fn process(
                 }
             }
 
+            // APE items hold multi-value fields with the values separated by nulls, same as the new tags
             _ => {
                 let item = Item::new(
                     key.as_str(),
                     ItemType::Text,
-                    value.trim().as_bytes().to_vec(),
+                    tags::join_values(tags::split_values(value)).into_bytes(),
                 );
                 match item {
                     Ok(item) => {
@@ -71,7 +77,9 @@ pub fn process(
     }
 
     // Try to save
-    if !config.execution.dry_run.unwrap_or(true) {
+    if config.execution.dry_run.unwrap_or(true) {
+        processed_ok = true;
+    } else {
         let mut file = File::options().read(true).write(true).open(filename)?;
         match ape::write_to(&tags, &mut file) {
             Ok(()) => {
@@ -79,13 +87,13 @@ pub fn process(
                 log::info!("{filename}  ✓");
             }
             Err(e) => {
-                log::error!("{filename}: Failed to write APE tags: {e}");
+                log::error!("{filename}: Failed to write {file_type} tags: {e}");
             }
         }
     }
 
     if config.rename_file.is_some() {
-        rename_file(filename, config, &tags)?;
+        rename_file(filename, file_type, config, &tags)?;
         processed_ok = true;
     }
 
@@ -93,6 +101,74 @@ pub fn process(
     Ok(processed_ok)
 }
 
+/// Reads the existing text items from the file. Keys are used as they appear in the file.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let tags = read_tag(filename)?;
+
+    let values = tags
+        .iter()
+        .filter_map(|item| {
+            <&str>::try_from(item)
+                .ok()
+                .map(|v| (item.key.clone(), v.to_string()))
+        })
+        .collect();
+
+    // Return safely
+    Ok(values)
+}
+
+/// Reads the front and back covers from the file. The description in front of the image bytes is left out.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let tags = read_tag(filename)?;
+
+    let covers = [
+        (CoverType::Front, "Cover Art (Front)"),
+        (CoverType::Back, "Cover Art (Back)"),
+    ]
+    .into_iter()
+    .filter_map(|(cover_type, key)| {
+        let value = Vec::<u8>::from(tags.item(key)?);
+        let start = value.iter().position(|b| *b == 0).map_or(0, |p| p + 1);
+        Some((cover_type, value[start..].to_vec()))
+    })
+    .collect();
+
+    // Return safely
+    Ok(covers)
+}
+
+/// Reads the `APEv2` tag from the file. Files without one, eg. freshly encoded `WavPack` files, get an empty tag.
+fn read_tag(filename: &str) -> Result<ape::Tag> {
+    match ape::read_from_path(filename) {
+        Ok(tags) => Ok(tags),
+        Err(ape::Error::TagNotFound) => {
+            log::debug!("No APE tag found in {filename}. Creating a new one.");
+            Ok(ape::Tag::new())
+        }
+        Err(err) => Err(err).with_context(|| format!("Unable to read the APE tag in {filename}")),
+    }
+}
+
+/// Removes the items marked for removal. Cover art is identified as `PICTUREFRONT` and `PICTUREBACK`.
+fn remove_tags(filename: &str, tags: &mut ape::Tag, removals: &Removals, config: &DefaultValues) {
+    let keys: Vec<String> = tags.iter().map(|item| item.key.clone()).collect();
+    for key in keys {
+        let name = if key.eq_ignore_ascii_case("Cover Art (Front)") {
+            "PICTUREFRONT"
+        } else if key.eq_ignore_ascii_case("Cover Art (Back)") {
+            "PICTUREBACK"
+        } else {
+            key.as_str()
+        };
+
+        if removals.should_remove(name) {
+            log_removal(filename, &key, config);
+            tags.remove_items(&key);
+        }
+    }
+}
+
 /// Sets the front or back cover art in an APE tag.
 /// APE cover art convention: key is "Cover Art (Front)" or "Cover Art (Back)",
 /// value is a binary item with format: `description\0` + raw image bytes.
@@ -117,33 +193,149 @@ fn set_picture(tags: &mut ape::Tag, img_file: &str, ape_key: &str, max_size: u32
     Ok(())
 }
 
-/// Renames the APE file based on the tags
-// Allow the uneccesary Ok(()) for now for consistency with other functions and possible changes later.
-#[allow(clippy::unnecessary_wraps)]
-fn rename_file(_filename: &str, _config: &DefaultValues, _tags: &ape::Tag) -> Result<()> {
-    log::warn!(
-        "Rename is currently not supported for APE files because the metadata is not standardized."
-    );
+/// Other names used for the same items by other taggers, eg. "Album Artist" in foobar2000 and "Track" in Mp3tag.
+/// Item names are compared ignoring case.
+const KEY_ALIASES: [(&str, &[&str]); 8] = [
+    ("ALBUMARTIST", &["Album Artist"]),
+    ("ALBUMARTISTSORT", &["Album Artist Sort"]),
+    ("ALBUMTITLESORT", &["ALBUMSORT"]),
+    ("DISCNUMBER", &["Disc"]),
+    ("DISCTOTAL", &["TOTALDISCS"]),
+    ("TRACKNUMBER", &["Track"]),
+    ("TRACKTOTAL", &["TOTALTRACKS"]),
+    ("DATE", &["Year"]),
+];
+
+/// Renames the file based on the tags
+fn rename_file(
+    filename: &str,
+    file_type: FileTypes,
+    config: &DefaultValues,
+    tags: &ape::Tag,
+) -> Result<()> {
+    let mut pattern = String::new();
+    if let Some(p) = &config.rename_file {
+        pattern.clone_from(p);
+    }
+
+    let replace_map = rename_values(file_type, tags);
+    log::debug!("replace_map = {replace_map:?}");
+
+    // Try to rename, and process the result
+    let rename_result = rename_file::rename_file(filename, &replace_map, config);
+    match rename_result {
+        Ok(new_filename) => log::info!("{filename} --> {new_filename}"),
+        Err(err) => {
+            if config.execution.stop_on_error.unwrap_or(true) {
+                return Err(err).with_context(|| {
+                    format!("Unable to rename {filename} with tags \"{pattern}\"")
+                });
+            }
+            log::warn!("Unable to rename {filename} with tags \"{pattern}\": {err:#} Continuing.");
+        }
+    }
 
     // Return safely
     Ok(())
 }
 
+/// Gets the mappings of %aa --> ALBUMARTIST --> Madonna used to rename the file.
+fn rename_values(file_type: FileTypes, tags: &ape::Tag) -> HashMap<String, String> {
+    let mut replace_map = HashMap::new();
+
+    for (key, ape_key) in tags::option_to_tag(file_type) {
+        if let Some(value) = rename_value(tags, &ape_key) {
+            log::debug!("key = {key}, ape_key = {ape_key}, value = {value}");
+            replace_map.insert(key, value);
+        }
+    }
+
+    replace_map
+}
+
+/// Gets the value of an item for use in a file name. Track and disc numbers stored as eg. "3/12" are split,
+/// and the count is used if there isn't a separate item for it.
+fn rename_value(tags: &ape::Tag, key: &str) -> Option<String> {
+    match key {
+        "TRACKNUMBER" | "DISCNUMBER" => text_value(tags, key).map(|v| split_count(&v).0),
+        "TRACKTOTAL" | "DISCTOTAL" => text_value(tags, key).or_else(|| {
+            let number_key = if key == "TRACKTOTAL" {
+                "TRACKNUMBER"
+            } else {
+                "DISCNUMBER"
+            };
+            split_count(&text_value(tags, number_key)?).1
+        }),
+        _ => text_value(tags, key),
+    }
+}
+
+/// Gets the first value of a text item, trying the other names it is known by if it isn't found.
+fn text_value(tags: &ape::Tag, key: &str) -> Option<String> {
+    let aliases = KEY_ALIASES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map_or(&[][..], |(_, a)| *a);
+
+    std::iter::once(key)
+        .chain(aliases.iter().copied())
+        .filter_map(|k| tags.item(k))
+        .filter_map(|item| <&str>::try_from(item).ok())
+        .find_map(|v| tags::split_values(v).next().filter(|v| !v.is_empty()))
+        .map(ToString::to_string)
+}
+
+/// Splits eg. "3/12" into the number and the count.
+fn split_count(value: &str) -> (String, Option<String>) {
+    match value.split_once('/') {
+        Some((number, count)) => (
+            number.trim().to_string(),
+            Some(count.trim().to_string()).filter(|c| !c.is_empty()),
+        ),
+        None => (value.to_string(), None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_rename_file() {
-        // Skip if testdata is not available (e.g. in CI without LFS files)
-        if !std::path::Path::new("../testdata/sample.ape").exists() {
-            return;
+    /// Tests the values used to rename files, with the item names used by other taggers.
+    fn test_rename_values() {
+        let mut tags = ape::Tag::new();
+        for (key, value) in [
+            ("Album Artist", "Patricia Barber"),
+            ("Title", "Code Cool"),
+            ("Track", "3/12"),
+            ("DISCNUMBER", "1"),
+            ("Artist", "Patricia Barber\0Michael Arnopol"),
+            ("Year", ""),
+        ] {
+            tags.set_item(Item::new(key, ItemType::Text, value.as_bytes().to_vec()).unwrap());
         }
-        let blank_defaults = DefaultValues::new();
-        let blank_ape = ape::Tag::default();
 
-        // Don't need to do a lot because this function just issues a warning and exits without error.
-        assert!(rename_file("../testdata/sample.ape", &blank_defaults, &blank_ape).is_ok());
+        let values = rename_values(FileTypes::Ape, &tags);
+        assert_eq!(values["%aa"], "Patricia Barber");
+        assert_eq!(values["%tt"], "Code Cool");
+        assert_eq!(values["%tn"], "3");
+        assert_eq!(values["%to"], "12");
+        assert_eq!(values["%track-number-total"], "12");
+        assert_eq!(values["%dn"], "1");
+        assert_eq!(values["%ta"], "Patricia Barber");
+        assert!(!values.contains_key("%dt"));
+        assert!(!values.contains_key("%td"));
+    }
+
+    #[test]
+    /// Tests splitting track and disc numbers.
+    fn test_split_count() {
+        assert_eq!(
+            split_count("3/12"),
+            ("3".to_string(), Some("12".to_string()))
+        );
+        assert_eq!(split_count("3 / "), ("3".to_string(), None));
+        assert_eq!(split_count("3"), ("3".to_string(), None));
     }
 
     #[test]
@@ -156,6 +348,12 @@ mod tests {
         new_values.insert("ALBUMARTIST".to_string(), "New Album Artist".to_string());
         let blank_defaults = DefaultValues::new();
 
-        assert!(process("../testdata/sample.ape", &new_values, &blank_defaults).is_ok());
+        assert!(process(
+            "../testdata/sample.ape",
+            FileTypes::Ape,
+            &new_values,
+            &blank_defaults
+        )
+        .is_ok());
     }
 }
@@ -0,0 +1,169 @@
+//! Assigns track numbers to the files in each folder based on their sort order, for `--auto-track-number`.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{read_values, tags};
+
+/// Sort the files on their file name, rather than on a tag.
+pub const SORT_BY_NAME: &str = "name";
+
+/// Numbers the files in each folder from 1, in order. The numbering restarts in each folder,
+/// so disc subfolders (eg. `CD1`, `CD2`) get their own sequence.
+///
+/// # Arguments
+///
+/// - `filenames: &[String]` -- the files being processed
+/// - `sort_by: &str` -- `name` to use a natural sort on the file names (so `2.flac` comes before `10.flac`),
+///   or the name of a tag (eg. `TITLE`) to sort on its existing value. Files without the tag come last.
+///   Ties are sorted on the file name.
+///
+/// # Returns
+///
+/// The track number for each file, keyed on the file name as given.
+pub fn track_numbers(filenames: &[String], sort_by: &str) -> HashMap<String, u16> {
+    let mut folders: BTreeMap<PathBuf, Vec<(Option<String>, &String)>> = BTreeMap::new();
+
+    for filename in filenames {
+        let key = if sort_by.eq_ignore_ascii_case(SORT_BY_NAME) {
+            None
+        } else {
+            sort_value(filename, sort_by)
+        };
+        folders
+            .entry(folder(filename))
+            .or_default()
+            .push((key, filename));
+    }
+
+    let mut numbers = HashMap::new();
+    for (dir, mut files) in folders {
+        files.sort_by(|(a_key, a_name), (b_key, b_name)| {
+            let by_key = match (a_key, b_key) {
+                (Some(a), Some(b)) => natural_cmp(a, b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            by_key.then_with(|| natural_cmp(file_name(a_name), file_name(b_name)))
+        });
+        log::debug!("track_numbers::{} = {files:?}", dir.display());
+
+        for (number, (_, filename)) in (1..=u16::MAX).zip(files) {
+            numbers.insert(filename.clone(), number);
+        }
+    }
+
+    numbers
+}
+
+/// Gets the existing value of the tag used for sorting, or `None` if the file doesn't have it.
+fn sort_value(filename: &str, tag: &str) -> Option<String> {
+    let file_type = common::get_file_type(filename).ok()?;
+    let key = tags::map_key(file_type, tag);
+
+    match read_values(filename, file_type) {
+        Ok(values) => values
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(&key))
+            .map(|(_, v)| v),
+        Err(err) => {
+            log::warn!("{filename} :: Unable to read {tag} for sorting: {err:#}");
+            None
+        }
+    }
+}
+
+/// Gets the full path of the folder the file is in.
+fn folder(filename: &str) -> PathBuf {
+    let path = fs::canonicalize(filename).unwrap_or_else(|_| Path::new(filename).to_path_buf());
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Gets the name of the file without the folder.
+fn file_name(filename: &str) -> &str {
+    Path::new(filename)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(filename)
+}
+
+/// Compares two strings so that runs of digits are compared as numbers, eg. "Track 2" < "Track 10".
+/// Everything else is compared ignoring case.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let x_num = take_number(&mut a_chars);
+                let y_num = take_number(&mut b_chars);
+                let ord = x_num.len().cmp(&y_num.len()).then(x_num.cmp(&y_num));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that numbers in file names are sorted by value and that other text ignores case.
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("Track 2", "Track 10"), Ordering::Less);
+        assert_eq!(natural_cmp("track 10", "Track 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("02 Vogue", "2 vogue"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "B"), Ordering::Less);
+        assert_eq!(natural_cmp("Song", "Song 1"), Ordering::Less);
+    }
+
+    #[test]
+    /// Tests that the numbering restarts in each folder.
+    fn test_track_numbers() {
+        let filenames: Vec<String> = [
+            "/music/Album/CD2/10 - Ten.flac",
+            "/music/Album/CD1/10 - Ten.flac",
+            "/music/Album/CD1/2 - Two.flac",
+            "/music/Album/CD2/9 - Nine.flac",
+            "/music/Album/CD1/1 - One.flac",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+        let numbers = track_numbers(&filenames, SORT_BY_NAME);
+        assert_eq!(numbers.len(), 5);
+        assert_eq!(numbers["/music/Album/CD1/1 - One.flac"], 1);
+        assert_eq!(numbers["/music/Album/CD1/2 - Two.flac"], 2);
+        assert_eq!(numbers["/music/Album/CD1/10 - Ten.flac"], 3);
+        assert_eq!(numbers["/music/Album/CD2/9 - Nine.flac"], 1);
+        assert_eq!(numbers["/music/Album/CD2/10 - Ten.flac"], 2);
+    }
+}
@@ -0,0 +1,171 @@
+//! Copies the tags and cover art from one music file to another, for `--copy-tags-from`.
+//! The files can be of different types, eg. from a FLAC master to an MP3 or M4A transcode.
+
+use anyhow::{Context, Result};
+use common::FileTypes;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{images, read_covers, read_values, tags};
+use crate::default_values::DefaultValues;
+
+/// Cover art saved to temporary files so it can be set in a music file, eg. from the `--copy-tags-from` source file.
+/// The files are removed when this is dropped.
+#[derive(Debug, Default)]
+pub struct SavedCovers(Vec<PathBuf>);
+
+impl SavedCovers {
+    /// Saves the image to a temporary file, and returns the path to it.
+    pub fn save(&mut self, data: &[u8], name: &str) -> Result<PathBuf> {
+        let path = images::save_cover(data, name)?;
+        self.0.push(path.clone());
+        Ok(path)
+    }
+}
+
+impl Drop for SavedCovers {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            if let Err(err) = fs::remove_file(path) {
+                log::warn!("Unable to remove {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+/// Adds the values and covers from the `--copy-tags-from` source file to the new tags.
+/// Values that are already in the new tags are left as-is, so any other options take precedence.
+///
+/// # Arguments
+///
+/// - `filename: &str` -- the file being processed
+/// - `file_type: FileTypes` -- the type of the file being processed
+/// - `cfg: &DefaultValues` -- the config, holding the source file or folder
+/// - `new_tags: &mut HashMap<String, String>` -- the new tags, keyed on the tag names for the file type
+/// - `saved_covers: &mut SavedCovers` -- keeps the covers read from the source file until the file has been processed
+pub fn copy_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+    saved_covers: &mut SavedCovers,
+) -> Result<()> {
+    let Some(source) = &cfg.copy_tags_from else {
+        return Ok(());
+    };
+    let Some(source) = source_for(filename, source)? else {
+        log::warn!("{filename} :: No matching file found in {source} to copy the tags from.");
+        return Ok(());
+    };
+    if same_file(filename, &source) {
+        log::debug!("{filename} :: Not copying tags from the file itself.");
+        return Ok(());
+    }
+    log::debug!("{filename} :: Copying tags from {source}");
+
+    let source_type = common::get_file_type(&source)?;
+    let values = read_values(&source, source_type)
+        .with_context(|| format!("Unable to read the tags from {source}"))?;
+    for (key, value) in values {
+        if let Some(generic) = tags::generic_key(source_type, &key) {
+            new_tags
+                .entry(tags::map_key(file_type, &generic))
+                .or_insert(value);
+        }
+    }
+
+    let tag_names = tags::get_tag_names(file_type);
+    for (cover_type, data) in read_covers(&source, source_type)? {
+        let key = match cover_type {
+            images::CoverType::Front => &tag_names.picture_front,
+            images::CoverType::Back => &tag_names.picture_back,
+        };
+        if new_tags.contains_key(key) {
+            continue;
+        }
+        let path = saved_covers.save(&data, &cover_type.to_string())?;
+        new_tags.insert(key.clone(), path.to_string_lossy().to_string());
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Finds the file to copy the tags from. If the source is a folder, the music file with the same name
+/// (ignoring the extension) is used, then the one with the same track number.
+///
+/// Returns `None` if there's no matching file in the folder.
+pub fn source_for(filename: &str, source: &str) -> Result<Option<String>> {
+    let source_path = Path::new(source);
+    if !source_path.is_dir() {
+        return Ok(Some(source.to_string()));
+    }
+
+    let candidates = music_files(source_path)?;
+    let stem = Path::new(filename).file_stem();
+    if let Some(found) = candidates
+        .iter()
+        .find(|c| Path::new(c).file_stem().is_some_and(|s| Some(s) == stem))
+    {
+        return Ok(Some(found.clone()));
+    }
+
+    let Some(number) = track_number(filename) else {
+        return Ok(None);
+    };
+    let matches: Vec<&String> = candidates
+        .iter()
+        .filter(|c| track_number(c) == Some(number))
+        .collect();
+    if let [found] = matches.as_slice() {
+        return Ok(Some((*found).clone()));
+    }
+    if matches.len() > 1 {
+        log::warn!(
+            "{filename} :: Found {} files with track number {number} in {source}.",
+            matches.len()
+        );
+    }
+
+    Ok(None)
+}
+
+/// Gets the music files in the folder, sorted by name. Subfolders are not included.
+pub fn music_files(folder: &Path) -> Result<Vec<String>> {
+    let mut files: Vec<String> = fs::read_dir(folder)
+        .with_context(|| format!("Unable to read the folder {}", folder.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .filter(|path| {
+            common::get_file_type(path).is_ok_and(|file_type| file_type != FileTypes::Unknown)
+        })
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Gets the track number of the file, ignoring any total, eg. `3` for "03/12".
+fn track_number(filename: &str) -> Option<u32> {
+    let file_type = common::get_file_type(filename).ok()?;
+    let key = tags::map_key(file_type, "TRACKNUMBER");
+    let values = read_values(filename, file_type).ok()?;
+
+    values
+        .get(&key)?
+        .split('/')
+        .next()
+        .and_then(|n| n.trim().parse().ok())
+}
+
+/// Checks if the two names point to the same file.
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,138 @@
+//! Contains the functionality to process DSDIFF (`.dff`) files.
+//! The tags are stored in an unofficial `ID3 ` chunk and are handled the same way as for MP3 files. The artist and
+//! title are also kept in step with the `DIAR` and `DITI` chunks in the `DIIN` chunk, which is all some players show.
+
+use crate::default_values::DefaultValues;
+use crate::formats::id3_versions::{self, Id3Version};
+use crate::formats::images::CoverType;
+use crate::formats::mp3;
+use crate::formats::removals::Removals;
+use crate::formats::tags;
+use anyhow::{Context, Result};
+use common::{DffFile, FileTypes};
+use id3::{Tag, TagLike};
+use std::{collections::HashMap, io::Cursor};
+
+/// Performs the actual processing of DSDIFF files.
+///
+/// **Parameters:**
+///
+/// - `filename: &str` -- The name of the file to be processed, eg. "somefile.dff".
+/// - `nt: &HashMap<String, String>` -- A set of new tags in Key/Value form, eg. _key = TPE2_, _value = "The Tragically Hip"_
+/// - `cfg: &DefaultValues` -- A struct containing default values read from a config file and the CLI
+///
+/// **Returns:**
+///
+/// `anyhow::Result<bool>` -- `Ok(true)` if the file was saved successfully or a dry-run was performed.
+pub fn process(filename: &str, nt: &HashMap<String, String>, cfg: &DefaultValues) -> Result<bool> {
+    log::debug!("Filename: {filename}");
+    let mut processed_ok = false;
+
+    let mut dff = DffFile::read_from_path(filename)?;
+    let mut tag = read_tag(&dff, filename)?;
+    id3_versions::upgrade_frames(&mut tag);
+
+    // Don't lose the artist and title that are only in the DIIN chunk
+    copy_diin_to_tag(&dff, &mut tag);
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(FileTypes::Dff, cfg);
+    if !removals.is_empty() {
+        mp3::remove_tags(filename, &mut tag, &removals, cfg);
+    }
+
+    // Set the new tags
+    mp3::set_values(filename, &mut tag, nt, cfg)?;
+
+    // Write tags to file - unless we're on a dry run.
+    if cfg.execution.dry_run.unwrap_or(true) {
+        processed_ok = true;
+    } else {
+        match write_tags(&mut dff, &tag, cfg.id3_version_setting()?) {
+            Ok(()) => {
+                processed_ok = true;
+                log::info!("{filename}  ✓");
+            }
+            Err(err) => {
+                if cfg.execution.stop_on_error.unwrap_or(false) {
+                    return Err(err).with_context(|| format!("Unable to save {filename}"));
+                }
+                log::error!("{filename}: Failed to write DSDIFF tags: {err:#}");
+            }
+        }
+    }
+
+    // Rename file
+    if cfg.rename_file.is_some() {
+        match mp3::rename_file(filename, FileTypes::Dff, cfg, &tag) {
+            Ok(()) => {}
+            Err(err) => {
+                log::error!("Failed to rename {filename}: {err}");
+                processed_ok = false;
+            }
+        }
+    }
+
+    // return safely
+    Ok(processed_ok)
+}
+
+/// Reads the existing values from the file, using the same keys as for MP3 files.
+/// The artist and title in the `DIIN` chunk are used if they aren't in the ID3 tag.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let dff = DffFile::read_from_path(filename)?;
+    let mut tag = read_tag(&dff, filename)?;
+    copy_diin_to_tag(&dff, &mut tag);
+
+    // Return safely
+    Ok(mp3::existing_values(&tag))
+}
+
+/// Reads the front and back covers from the ID3 tag in the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let dff = DffFile::read_from_path(filename)?;
+    Ok(mp3::existing_covers(&read_tag(&dff, filename)?))
+}
+
+/// Reads the ID3 tag from the `ID3 ` chunk. Files without one get an empty tag.
+fn read_tag(dff: &DffFile, filename: &str) -> Result<Tag> {
+    if let Some(data) = dff.id3_data() {
+        Tag::read_from2(Cursor::new(data))
+            .with_context(|| format!("Unable to read the ID3 chunk in {filename}"))
+    } else {
+        log::debug!("No ID3 chunk found in {filename}. Creating a new one.");
+        Ok(Tag::new())
+    }
+}
+
+/// Writes the ID3 tag to the `ID3 ` chunk and the artist and title to the `DIIN` chunk, and saves the file.
+/// The tag is written as the given `ID3v2` version, and the `ID3 ` chunk is removed if the tag is empty.
+fn write_tags(dff: &mut DffFile, tag: &Tag, version: Id3Version) -> Result<()> {
+    copy_tag_to_diin(tag, dff);
+
+    let mut data = Vec::new();
+    if tag.frames().next().is_some() {
+        id3_versions::tag_for_version(tag, version).write_to(&mut data, version.version())?;
+    }
+    dff.set_id3_data(Some(data));
+
+    dff.save()
+}
+
+/// Copies the artist and title from the `DIIN` chunk into the tag if the tag doesn't have them.
+fn copy_diin_to_tag(dff: &DffFile, tag: &mut Tag) {
+    if let Some(artist) = dff.artist().filter(|_| tag.artist().is_none()) {
+        log::debug!("Copying TPE1 = {artist} from the DIIN chunk.");
+        tag.set_artist(artist);
+    }
+    if let Some(title) = dff.title().filter(|_| tag.title().is_none()) {
+        log::debug!("Copying TIT2 = {title} from the DIIN chunk.");
+        tag.set_title(title);
+    }
+}
+
+/// Writes the artist and title in the tag to the `DIIN` chunk. Fields with more than one value are joined with "; ".
+fn copy_tag_to_diin(tag: &Tag, dff: &mut DffFile) {
+    dff.set_artist(tag.artist().map(tags::display_value).as_deref());
+    dff.set_title(tag.title().map(tags::display_value).as_deref());
+}
@@ -1,104 +1,82 @@
-//! Contains the functionality to process DSF files. Currently only rename is supported.
+//! Contains the functionality to process DSF files.
+//! The ID3 tag is stored at the end of the file, after the audio data, and is pointed to from the `DSD ` chunk.
 
 use crate::default_values::DefaultValues;
-use crate::formats::tags::option_to_tag;
-use crate::rename_file;
+use crate::formats::id3_versions::{self, Id3Version};
+use crate::formats::images::CoverType;
+use crate::formats::mp3;
+use crate::formats::removals::Removals;
 use anyhow::{bail, Context, Result};
 use common::FileTypes;
 use dsf::{self, DsfFile};
-use id3::TagLike;
-use std::{collections::HashMap, path::Path};
+use id3::Tag;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The size of the `DSD ` chunk at the start of the file.
+const DSD_CHUNK_LEN: u64 = 28;
 
 /// Performs the actual processing of DSF files.
+/// The tags are handled the same way as for MP3 files, and the ID3 tag is written to the end of the file.
 pub fn process(
     filename: &str,
     new_tags: &HashMap<String, String>,
     config: &DefaultValues,
 ) -> Result<bool> {
     log::debug!("Filename: {filename}");
-
     let mut processed_ok = false;
 
-    if let Some(mut tag) = DsfFile::open(Path::new(&filename))?.id3_tag().clone() {
-        log::debug!("Tag: {tag:?}");
-        for frame in tag.frames() {
-            log::debug!("{} = {}", frame.id(), frame.content());
-        }
+    let mut tag = DsfFile::open(Path::new(&filename))?
+        .id3_tag()
+        .clone()
+        .unwrap_or_else(|| {
+            log::debug!("No ID3 tag found in {filename}. Creating a new one.");
+            Tag::new()
+        });
+    id3_versions::upgrade_frames(&mut tag);
+    log::debug!("Tag: {tag:?}");
+    let original = tag.clone();
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(FileTypes::Dsf, config);
+    if !removals.is_empty() {
+        mp3::remove_tags(filename, &mut tag, &removals, config);
+    }
 
-        // Print new tags
-        for (key, value) in new_tags {
-            // Output information about tags getting changed
-            if config.execution.detail_off.unwrap_or(false) {
-                log::debug!("{filename} :: New {key} = {value}");
-            } else if config.execution.dry_run.unwrap_or(false) {
-                log::info!("{filename} :: New {key} = {value}");
-            } else {
-                log::debug!("{filename} :: New {key} = {value}");
+    // Set the new tags
+    mp3::set_values(filename, &mut tag, new_tags, config)?;
+
+    // Write tags to file - unless we're on a dry run, or there is nothing to change.
+    if tag == original {
+        log::debug!("No changes to the tag in {filename}. Not writing.");
+    } else if config.execution.dry_run.unwrap_or(true) {
+        processed_ok = true;
+    } else {
+        match write_tag(filename, &tag, config.id3_version_setting()?) {
+            Ok(()) => {
+                processed_ok = true;
+                log::info!("{filename}  ✓");
             }
-
-            // Process the tags into the file. Arguaby we could skip this if it's a
-            // dry run, but it's good to do it anyway to ensure that it works.
-            match key.as_ref() {
-                // Pictures or comment
-                "APIC-P" | "APIC-B" | "COMM" => (),
-
-                // Disc number
-                "TPOS" => {
-                    let num = to_number(
-                        value,
-                        "disc number",
-                        config.execution.stop_on_error.unwrap_or(false),
-                    )?;
-                    tag.set_disc(num);
+            Err(err) => {
+                if config.execution.stop_on_error.unwrap_or(false) {
+                    return Err(err).with_context(|| format!("Unable to save {filename}"));
                 }
-
-                // Disc count
-                "TPOS-T" => {
-                    let num = to_number(
-                        value,
-                        "total discs",
-                        config.execution.stop_on_error.unwrap_or(false),
-                    )?;
-                    tag.set_total_discs(num);
-                }
-
-                // Track number
-                "TRCK" => {
-                    let num = to_number(
-                        value,
-                        "track number",
-                        config.execution.stop_on_error.unwrap_or(false),
-                    )?;
-                    tag.set_track(num);
-                }
-
-                // Track count
-                "TRCK-T" => {
-                    let num = to_number(
-                        value,
-                        "total tracks",
-                        config.execution.stop_on_error.unwrap_or(false),
-                    )?;
-                    tag.set_total_tracks(num);
-                }
-
-                // Everything else
-                _ => tag.set_text(key, value.trim()),
+                log::error!("{filename}: Failed to write DSF tags: {err:#}");
             }
-            processed_ok = true;
         }
+    }
 
-        // Write tags to file
-        log::debug!("Writing to DSF files is currently not supported. Not writing {filename}");
-
-        // Rename file
-        if config.rename_file.is_some() {
-            match rename_file(filename, config, &tag) {
-                Ok(()) => {}
-                Err(err) => {
-                    log::error!("Failed to rename {filename}: {err}");
-                    processed_ok = false;
-                }
+    // Rename file
+    if config.rename_file.is_some() {
+        match mp3::rename_file(filename, FileTypes::Dsf, config, &tag) {
+            Ok(()) => {}
+            Err(err) => {
+                log::error!("Failed to rename {filename}: {err}");
+                processed_ok = false;
             }
         }
     }
@@ -106,127 +84,159 @@ pub fn process(
     Ok(processed_ok)
 }
 
-/// Renames an MP3 file based on the pattern provided
-fn rename_file(filename: &str, config: &DefaultValues, tag: &id3::Tag) -> Result<()> {
-    let tags_names = option_to_tag(FileTypes::Dsf);
-    let mut replace_map = HashMap::new();
+/// Reads the existing values from the ID3 tag in the file, using the same keys as for MP3 files.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let values = DsfFile::open(Path::new(&filename))?
+        .id3_tag()
+        .as_ref()
+        .map(mp3::existing_values)
+        .unwrap_or_default();
 
-    let mut pattern = String::new();
-    if let Some(p) = &config.rename_file {
-        pattern.clone_from(p);
-    }
+    // Return safely
+    Ok(values)
+}
 
-    // get the mappings of %aa --> ALBUMARTIST --> Madonna
-    // key = %aa, vorbis_key = ALBUMARTIST, vval = Madonna
-    for (key, tag_name) in tags_names {
-        // Get the ID3 value based on the tag_name from the HashMap
-        if let Some(vval) = tag.get(&tag_name).and_then(|frame| frame.content().text()) {
-            if tag_name == "TPOS" || tag_name == "TRCK" {
-                let separates: Vec<&str> = vval.split('/').collect();
-                let mut count = "01".to_string();
-                let mut total = "01".to_string();
-                if !separates.is_empty() {
-                    count = format!("{:0>2}", separates[0]);
-                }
-                if separates.len() > 1 {
-                    total = format!("{:0>2}", separates[1]);
-                }
-                log::debug!("{tag_name} count = {count}, total = {total}");
-                match tag_name.as_str() {
-                    "TPOS" => {
-                        replace_map.insert("%dn".to_string(), count.clone());
-                        replace_map.insert("%disc-number".to_string(), count);
-                        replace_map.insert("%dt".to_string(), total.clone());
-                        replace_map.insert("%dnt".to_string(), total.clone());
-                        replace_map.insert("%disc-number-total".to_string(), total);
-                    }
-                    "TRCK" => {
-                        replace_map.insert("%tn".to_string(), count.clone());
-                        replace_map.insert("%track-number".to_string(), count);
-                        replace_map.insert("%to".to_string(), total.clone());
-                        replace_map.insert("%tnt".to_string(), total.clone());
-                        replace_map.insert("%track-number-total".to_string(), total);
-                    }
-                    _ => {
-                        bail!("Unknown tag {tag_name} encountered when unwrapping disc/track information.")
-                    }
-                }
-            } else {
-                let value = vval.to_string();
-                log::debug!("key = {key}, tag_name = {tag_name}, value = {value}");
-                replace_map.insert(key, value);
-            }
-        }
+/// Reads the front and back covers from the ID3 tag in the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let covers = DsfFile::open(Path::new(&filename))?
+        .id3_tag()
+        .as_ref()
+        .map(mp3::existing_covers)
+        .unwrap_or_default();
+
+    // Return safely
+    Ok(covers)
+}
+
+/// Writes the ID3 tag to the end of the DSF file, replacing any tag already there.
+/// The total file size and the metadata pointer in the `DSD ` chunk are updated to match, and the `fmt ` and `data`
+/// chunks are left as-is. If the tag is empty, the old tag is removed and the metadata pointer is set to 0.
+fn write_tag(filename: &str, tag: &Tag, version: Id3Version) -> Result<()> {
+    let mut file = File::options().read(true).write(true).open(filename)?;
+
+    let mut header = [0_u8; 12];
+    file.read_exact(&mut header)
+        .context("The file is too short.")?;
+    if &header[..4] != b"DSD " || u64::from_le_bytes(header[4..].try_into()?) != DSD_CHUNK_LEN {
+        bail!("{filename} is not a DSF file.");
     }
 
-    log::debug!("replace_map = {replace_map:?}");
+    // The tag goes straight after the data chunk, which follows the fmt chunk
+    let fmt_end = chunk_end(&mut file, DSD_CHUNK_LEN, *b"fmt ")?;
+    let data_end = chunk_end(&mut file, fmt_end, *b"data")?;
 
-    let rename_result = rename_file::rename_file(filename, &replace_map, config);
-    match rename_result {
-        Ok(new_filename) => log::info!("{filename} --> {new_filename}"),
-        Err(err) => {
-            if config.execution.stop_on_error.unwrap_or(false) {
-                return Err(err).with_context(|| {
-                    format!("Unable to rename {filename} with tags \"{pattern}\"")
-                });
-            }
-            log::warn!("Unable to rename {filename} with tags \"{pattern}\": {err:#} Continuing.");
-        }
+    let mut id3 = Vec::new();
+    if tag.frames().next().is_some() {
+        id3_versions::tag_for_version(tag, version).write_to(&mut id3, version.version())?;
     }
 
+    file.seek(SeekFrom::Start(data_end))?;
+    file.write_all(&id3)?;
+    let file_size = data_end + id3.len() as u64;
+    file.set_len(file_size)?;
+
+    let metadata_pointer = if id3.is_empty() { 0 } else { data_end };
+    file.seek(SeekFrom::Start(12))?;
+    file.write_all(&file_size.to_le_bytes())?;
+    file.write_all(&metadata_pointer.to_le_bytes())?;
+    file.flush()?;
+
     // Return safely
     Ok(())
 }
 
-/// Get get the item number based on a tag value (string).
-/// If the value is not a number, return an error.
-///
-/// # Arguments
-///
-/// * `value` - The value to convert to a number
-/// * `tag_name` - The name of the tag being processed
-/// * `stop_on_error` - Whether to stop processing on error
-///
-/// # Returns
-///     The number as an i32
-///
-/// # Errors
-///     If the value is not a number, return an error
-fn to_number(value: &str, item: &str, stop_on_error: bool) -> Result<u32> {
-    let num = match value.parse::<u32>() {
-        Ok(n) => n,
-        Err(err) => {
-            if stop_on_error {
-                return Err(err).with_context(|| format!("Unable to set {item} to {value}"));
-            }
-            log::error!("Unable to set {item} to {value}. Setting to 1 and continuing: {err:#}");
-            1
-        }
-    };
+/// Checks the ID of the chunk starting at `offset` and returns the offset of the end of the chunk.
+/// DSF chunk headers are a 4-byte ID followed by the size of the whole chunk as a 64-bit number.
+fn chunk_end(file: &mut File, offset: u64, id: [u8; 4]) -> Result<u64> {
+    let mut header = [0_u8; 12];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut header)?;
+    if header[..4] != id {
+        bail!(
+            "Expected a \"{}\" chunk at offset {offset}.",
+            String::from_utf8_lossy(&id)
+        );
+    }
 
-    // Return the value
-    Ok(num)
+    let size = u64::from_le_bytes(header[4..].try_into()?);
+    Ok(offset + size)
 }
 
 #[cfg(test)]
-///
 mod tests {
     use super::*;
+    use common::test_files::TestFiles;
+    use id3::TagLike;
 
     #[test]
-    /// Test the `to_number` function.
-    fn test_to_number() {
-        for n in 0..=100 {
-            let num1 = to_number(&format!("{n}"), "test", false).unwrap();
-            assert_eq!(num1, n);
-
-            let num2 = to_number(&format!("{n}"), "test", true).unwrap();
-            assert_eq!(num2, n);
-        }
+    /// Tests the disc and track numbers, which are set the same way as for MP3 files.
+    fn test_set_numbers() {
+        let mut cfg = DefaultValues::new();
+        let mut tag = Tag::new();
+        let values = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect()
+        };
+
+        let nt = values(&[
+            ("TPOS", "2"),
+            ("TPOS-T", "3"),
+            ("TRCK", "07"),
+            ("TRCK-T", "12"),
+        ]);
+        mp3::set_values("test.dsf", &mut tag, &nt, &cfg).unwrap();
+        assert_eq!(tag.disc(), Some(2));
+        assert_eq!(tag.total_discs(), Some(3));
+        assert_eq!(tag.track(), Some(7));
+        assert_eq!(tag.total_tracks(), Some(12));
+
+        // Values that aren't numbers are set to 1, unless we stop on errors
+        let nt = values(&[("TRCK", "error")]);
+        cfg.execution.stop_on_error = Some(false);
+        mp3::set_values("test.dsf", &mut tag, &nt, &cfg).unwrap();
+        assert_eq!(tag.track(), Some(1));
+        cfg.execution.stop_on_error = Some(true);
+        assert!(mp3::set_values("test.dsf", &mut tag, &nt, &cfg).is_err());
+        assert!(mp3::set_values("test.dsf", &mut tag, &values(&[("TPOS", "-1")]), &cfg).is_err());
+    }
 
-        assert!(to_number("error", "some value", true).is_err());
-        assert!(to_number("error", "some value", false).is_ok());
-        assert_eq!(to_number("error", "some value", false).unwrap(), 1);
-        assert_eq!(to_number("-1", "some value", false).unwrap(), 1);
+    #[test]
+    /// Tests writing, replacing and removing the ID3 tag.
+    fn test_write_tag() {
+        let files = TestFiles::new();
+        let path = files.copy("silence.dsf", "test.dsf");
+        let filename = path.to_str().unwrap();
+        let original = std::fs::read(&path).unwrap();
+
+        let header = |bytes: &[u8], offset: usize| {
+            u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+        };
+
+        let mut tag = Tag::new();
+        tag.set_title("A rather long title that will be shortened later");
+        tag.set_artist("Patricia Barber");
+        write_tag(filename, &tag, Id3Version::V24).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(header(&bytes, 12), bytes.len() as u64);
+        assert_eq!(header(&bytes, 20), original.len() as u64);
+        assert_eq!(bytes[28..original.len()], original[28..]);
+        let read = DsfFile::open(&path).unwrap().id3_tag().clone().unwrap();
+        assert_eq!(read.artist(), Some("Patricia Barber"));
+
+        // A shorter tag replaces the old one, and the file gets shorter
+        tag.set_title("Short");
+        write_tag(filename, &tag, Id3Version::V24).unwrap();
+        let shorter = std::fs::read(&path).unwrap();
+        assert!(shorter.len() < bytes.len());
+        assert_eq!(header(&shorter, 12), shorter.len() as u64);
+        let read = DsfFile::open(&path).unwrap().id3_tag().clone().unwrap();
+        assert_eq!(read.title(), Some("Short"));
+
+        // An empty tag is removed
+        write_tag(filename, &Tag::new(), Id3Version::V24).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), original);
     }
 }
@@ -0,0 +1,186 @@
+//! Gets tag values from the path of a music file. This is the reverse of `--rename-file`, so
+//! `%aa/%at/%dn-%tn %tt` turns `Madonna/Like a Prayer/01-03 Express Yourself.flac` into the album artist,
+//! album title, disc number, track number and track title.
+
+use anyhow::{bail, Context, Result};
+use common::FileTypes;
+use regex::Regex;
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use crate::formats::tags::option_to_tag;
+
+/// Tags that only match digits when used in a pattern.
+const NUMERIC_TAGS: [&str; 4] = ["DISCNUMBER", "DISCTOTAL", "TRACKNUMBER", "TRACKTOTAL"];
+
+/// A pattern used to get tag values from the path of a music file.
+#[derive(Debug, Clone)]
+pub struct FilenamePattern {
+    /// The expression the path is matched against.
+    regex: Regex,
+
+    /// The capture group names and the (Vorbis comment style) tags they set, eg. `t0` --> `ALBUMARTIST`.
+    groups: Vec<(String, String)>,
+}
+
+impl FilenamePattern {
+    /// Parses a pattern using the same placeholders as `--rename-file`, eg. `%aa/%at/%dn-%tn %tt`.
+    ///
+    /// - The pattern is matched against the end of the path, without the extension. Use `/` to separate the folders.
+    /// - Disc and track numbers only match digits. Other placeholders match anything except `/`.
+    /// - Everything that isn't a placeholder has to match exactly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern has no placeholders or uses a placeholder that isn't supported.
+    pub fn parse(pattern: &str) -> Result<Self> {
+        // Longest first, so that eg. `%aas` isn't read as `%aa` followed by "s".
+        let mut placeholders: Vec<(String, String)> = option_to_tag(FileTypes::Flac)
+            .into_iter()
+            .filter(|(_, tag)| !tag.starts_with("PICTURE"))
+            .collect();
+        placeholders.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+
+        let mut expression = String::from("(?:^|/)");
+        let mut groups = Vec::new();
+        let mut rest = pattern;
+        while let Some(pos) = rest.find('%') {
+            expression.push_str(&regex::escape(&rest[..pos]));
+            rest = &rest[pos..];
+
+            let Some((placeholder, tag)) = placeholders.iter().find(|(p, _)| rest.starts_with(p))
+            else {
+                bail!("Unknown placeholder in \"{pattern}\" at \"{rest}\".");
+            };
+
+            let name = format!("t{}", groups.len());
+            let matcher = if NUMERIC_TAGS.contains(&tag.as_str()) {
+                r"\d+"
+            } else {
+                "[^/]+?"
+            };
+            write!(expression, "(?P<{name}>{matcher})")?;
+            groups.push((name, tag.clone()));
+            rest = &rest[placeholder.len()..];
+        }
+        expression.push_str(&regex::escape(rest));
+        expression.push('$');
+
+        if groups.is_empty() {
+            bail!("No placeholders found in \"{pattern}\".");
+        }
+        log::debug!("FilenamePattern::parse::expression = {expression}");
+
+        Ok(Self {
+            regex: Regex::new(&expression)
+                .with_context(|| format!("Unable to use the pattern \"{pattern}\""))?,
+            groups,
+        })
+    }
+
+    /// Uses a regular expression with named groups, eg. `(?P<TRACKNUMBER>\d+) - (?P<TITLE>.+)$`.
+    /// The group names are the tag names, the same as for `--set`. Unnamed groups are ignored.
+    ///
+    /// The expression is matched against the path, without the extension, using `/` to separate the folders.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression isn't valid or has no named groups.
+    pub fn from_regex(expression: &str) -> Result<Self> {
+        let regex = Regex::new(expression)
+            .with_context(|| format!("Invalid regular expression \"{expression}\""))?;
+
+        let groups: Vec<(String, String)> = regex
+            .capture_names()
+            .flatten()
+            .map(|name| (name.to_string(), name.to_string()))
+            .collect();
+        if groups.is_empty() {
+            bail!(
+                "No named groups found in \"{expression}\". Use (?P<TAG>...) for the tags to set."
+            );
+        }
+
+        Ok(Self { regex, groups })
+    }
+
+    /// Gets the tag values from the path of the file, keyed on the Vorbis comment style tag names (eg. `TITLE`).
+    /// Returns `None` if the path doesn't match the pattern.
+    pub fn tags(&self, filename: &str) -> Option<HashMap<String, String>> {
+        let path = fs::canonicalize(filename).unwrap_or_else(|_| Path::new(filename).to_path_buf());
+        let path = path.with_extension("");
+        let path = path.to_string_lossy().replace('\\', "/");
+
+        let captures = self.regex.captures(&path)?;
+        let values = self
+            .groups
+            .iter()
+            .filter_map(|(name, tag)| {
+                captures
+                    .name(name)
+                    .map(|m| m.as_str().trim())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| (tag.clone(), v.to_string()))
+            })
+            .collect();
+
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that the placeholders are turned into tags, and that the pattern only matches the end of the path.
+    fn test_parse() {
+        let fp = FilenamePattern::parse("%aa/%at/%dn-%tn %tt").unwrap();
+        let tags = fp
+            .tags("/music/Madonna/Like a Prayer/01-03 Express Yourself.flac")
+            .unwrap();
+        assert_eq!(tags.len(), 5);
+        assert_eq!(tags.get("ALBUMARTIST").unwrap(), "Madonna");
+        assert_eq!(tags.get("ALBUM").unwrap(), "Like a Prayer");
+        assert_eq!(tags.get("DISCNUMBER").unwrap(), "01");
+        assert_eq!(tags.get("TRACKNUMBER").unwrap(), "03");
+        assert_eq!(tags.get("TITLE").unwrap(), "Express Yourself");
+
+        // Track numbers must be digits
+        assert!(fp
+            .tags("/music/Madonna/Like a Prayer/01-AB Express.flac")
+            .is_none());
+        // Not enough folders
+        assert!(fp.tags("01-03 Express Yourself.flac").is_none());
+
+        let fp = FilenamePattern::parse("%track-artist - %tn - %track-title").unwrap();
+        let tags = fp.tags("Some Band - 7 - The Song - Live.mp3").unwrap();
+        assert_eq!(tags.get("ARTIST").unwrap(), "Some Band");
+        assert_eq!(tags.get("TRACKNUMBER").unwrap(), "7");
+        assert_eq!(tags.get("TITLE").unwrap(), "The Song - Live");
+
+        // Longer placeholders win over shorter ones
+        let fp = FilenamePattern::parse("%aas (%tn)").unwrap();
+        let tags = fp.tags("Keys, Alicia (2).flac").unwrap();
+        assert_eq!(tags.get("ALBUMARTISTSORT").unwrap(), "Keys, Alicia");
+
+        assert!(FilenamePattern::parse("%xyz %tt").is_err());
+        assert!(FilenamePattern::parse("no placeholders").is_err());
+        assert!(FilenamePattern::parse("%pf").is_err());
+    }
+
+    #[test]
+    /// Tests that named groups are used as tag names.
+    fn test_from_regex() {
+        let fp = FilenamePattern::from_regex(
+            r"(?P<DATE>\d{4})-\d\d-\d\d/(?:d\d)?t(?P<TRACKNUMBER>\d+)$",
+        )
+        .unwrap();
+        let tags = fp.tags("shows/gd1977-05-08/d1t04.flac").unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags.get("DATE").unwrap(), "1977");
+        assert_eq!(tags.get("TRACKNUMBER").unwrap(), "04");
+
+        assert!(FilenamePattern::from_regex(r"(\d+) (.+)").is_err());
+        assert!(FilenamePattern::from_regex(r"(?P<TITLE>.+").is_err());
+    }
+}
@@ -1,12 +1,15 @@
 //! Contains the functionality to process FLAC files.
 
+use crate::cue_sheet::{CueSheet, CueTrack};
 use crate::default_values::DefaultValues;
-use crate::formats::images::read_cover;
+use crate::formats::images::{read_cover, CoverType};
+use crate::formats::removals::{log_removal, Removals};
 use crate::formats::tags;
 use crate::formats::FileTypes;
 use crate::rename_file;
 use anyhow::{Context, Result};
-use metaflac::block::PictureType::{CoverBack, CoverFront};
+use metaflac::block::PictureType::{self, CoverBack, CoverFront};
+use metaflac::block::{Block, BlockType, CueSheetTrack};
 use metaflac::Tag;
 use std::collections::HashMap;
 
@@ -85,13 +88,15 @@ pub fn process(
         } // for (key, value)
     } // if let
 
+    // Remove existing tags if asked to
+    let removals = Removals::new(FileTypes::Flac, &cfg);
+    if !removals.is_empty() {
+        remove_tags(m_file, &mut tags, &removals, &cfg);
+    }
+
     // Set new tags
     for (k, v) in nt {
-        if !(cfg.execution.detail_off.unwrap_or(false)) && !cfg.execution.dry_run.unwrap_or(false) {
-            log::debug!("process::{m_file} :: New {k} = {v}");
-        } else {
-            log::info!("{m_file} :: New {k} = {}", v.trim());
-        }
+        tags::log_new_value(m_file, k, v, &cfg);
 
         // Process the tags
         match k.as_ref() {
@@ -115,7 +120,8 @@ pub fn process(
                 } // match
             }
 
-            _ => tags.set_vorbis(k.clone(), vec![v.clone().trim()]),
+            // One comment per value for multi-value fields
+            _ => tags.set_vorbis(k.clone(), tags::split_values(v).collect()),
         } // match key.as_ref()
     }
 
@@ -147,6 +153,127 @@ pub fn process(
     Ok(processed_ok)
 }
 
+/// Reads the existing Vorbis comments from the file. Keys are upper case, eg. `ARTIST`.
+/// Fields with more than one value are joined into one multi-value field.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let tags = Tag::read_from_path(filename)?;
+
+    let values = tags
+        .vorbis_comments()
+        .map(|vc| {
+            vc.comments
+                .iter()
+                .filter(|(_, v)| !v.is_empty())
+                .map(|(k, v)| (k.to_ascii_uppercase(), tags::join_values(v)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Return safely
+    Ok(values)
+}
+
+/// Reads the front and back covers from the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let tags = Tag::read_from_path(filename)?;
+
+    let covers = tags
+        .pictures()
+        .filter_map(|pic| match pic.picture_type {
+            CoverFront => Some((CoverType::Front, pic.data.clone())),
+            CoverBack => Some((CoverType::Back, pic.data.clone())),
+            _ => None,
+        })
+        .collect();
+
+    // Return safely
+    Ok(covers)
+}
+
+/// Reads the CUE sheet embedded in the file. The text in a `CUESHEET` Vorbis comment is used if there is one,
+/// otherwise the `CUESHEET` block, which only holds the catalog number and the track numbers and ISRCs.
+///
+/// Returns `None` if there isn't one.
+pub fn read_cue_sheet(filename: &str) -> Result<Option<CueSheet>> {
+    let tags = Tag::read_from_path(filename)?;
+
+    if let Some(text) = tags.get_vorbis("CUESHEET").and_then(|mut v| v.next()) {
+        return Ok(Some(CueSheet::parse(text)));
+    }
+
+    let Some(block) = tags.get_blocks(BlockType::CueSheet).find_map(|b| match b {
+        Block::CueSheet(cs) => Some(cs),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+
+    // The lead-out track is 170 for CDs and 255 otherwise, and has no index points.
+    let audio_tracks: Vec<&CueSheetTrack> = block
+        .tracks
+        .iter()
+        .filter(|t| t.is_audio && !t.indices.is_empty())
+        .collect();
+    let total = audio_tracks.len().to_string();
+
+    let mut sheet = CueSheet::default();
+    let catalog = block.catalog_num.trim_matches(char::from(0)).trim();
+    if !catalog.is_empty() {
+        sheet
+            .album
+            .insert("CATALOGNUMBER".to_string(), catalog.to_string());
+    }
+    for track in audio_tracks {
+        let mut values = HashMap::from([
+            ("TRACKNUMBER".to_string(), track.number.to_string()),
+            ("TRACKTOTAL".to_string(), total.clone()),
+        ]);
+        let isrc = track.isrc.trim_matches(char::from(0)).trim();
+        if !isrc.is_empty() {
+            values.insert("ISRC".to_string(), isrc.to_string());
+        }
+        sheet.tracks.push(CueTrack {
+            number: u32::from(track.number),
+            file: None,
+            values,
+        });
+    }
+
+    // Return safely
+    Ok(Some(sheet))
+}
+
+/// Removes the Vorbis comments and pictures marked for removal.
+/// Front and back covers are identified as `PICTUREFRONT` and `PICTUREBACK`, and any other pictures as `PICTURE`.
+fn remove_tags(filename: &str, tags: &mut metaflac::Tag, removals: &Removals, cfg: &DefaultValues) {
+    let keys: Vec<String> = tags
+        .vorbis_comments()
+        .map(|vc| vc.comments.keys().cloned().collect())
+        .unwrap_or_default();
+    for key in keys.iter().filter(|k| removals.should_remove(k)) {
+        log_removal(filename, key, cfg);
+        tags.remove_vorbis(key);
+    }
+
+    let mut picture_types: Vec<PictureType> = Vec::new();
+    for picture in tags.pictures() {
+        if !picture_types.contains(&picture.picture_type) {
+            picture_types.push(picture.picture_type);
+        }
+    }
+    for picture_type in picture_types {
+        let key = match picture_type {
+            CoverFront => "PICTUREFRONT",
+            CoverBack => "PICTUREBACK",
+            _ => "PICTURE",
+        };
+        if removals.should_remove(key) {
+            log_removal(filename, &format!("{key} ({picture_type:?})"), cfg);
+            tags.remove_picture_type(picture_type);
+        }
+    }
+}
+
 /// Set the front or back cover (for now)
 fn set_picture(
     tags: &mut metaflac::Tag,
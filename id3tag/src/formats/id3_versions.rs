@@ -0,0 +1,480 @@
+//! Contains the functionality to write ID3v2.3 or ID3v2.4 tags, and to read, write and remove `ID3v1` tags in MP3 files.
+//!
+//! Tags are kept in the ID3v2.4 form while they are processed. Dates are converted to and from the ID3v2.3 frames
+//! (`TYER`, `TDAT`, `TIME` and `TORY`) when the tags are read and written, so `TDRC` can be used for every file.
+//! The other frames that only exist in ID3v2.4 are written to `IPLS` and `TXXX` frames, and read back the same way.
+
+use crate::formats::tags;
+use anyhow::{bail, Context, Result};
+use id3::frame::{Content, ExtendedText, InvolvedPeopleList};
+use id3::{v1, Encoding, ErrorKind, Frame, Tag, TagLike, Timestamp, Version};
+use std::{fs::OpenOptions, io::Write, str::FromStr};
+
+/// The length of an `ID3v1` tag.
+const V1_TAG_LEN: usize = 128;
+
+/// The text frames that only exist in ID3v2.4, and the descriptions of the `TXXX` frames they are written to in ID3v2.3.
+const V24_TEXT_FRAMES: [(&str, &str); 9] = [
+    ("TDRL", "RELEASEDATE"),
+    ("TDTG", "TAGGINGDATE"),
+    ("TMOO", "MOOD"),
+    ("TSO2", "ALBUMARTISTSORT"),
+    ("TSOA", "ALBUMTITLESORT"),
+    ("TSOC", "COMPOSERSORT"),
+    ("TSOP", "ARTISTSORT"),
+    ("TSOT", "TITLESORT"),
+    ("TSST", "DISCSUBTITLE"),
+];
+
+/// The version of the `ID3v2` tags to write.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Id3Version {
+    /// ID3v2.3, which is read by most older players and car stereos.
+    V23,
+    /// ID3v2.4
+    #[default]
+    V24,
+}
+
+impl FromStr for Id3Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s
+            .to_ascii_lowercase()
+            .trim_start_matches("id3")
+            .trim_start_matches('v')
+        {
+            "2.3" => Ok(Self::V23),
+            "2.4" => Ok(Self::V24),
+            _ => bail!("Unknown ID3 version \"{s}\". Must be 2.3 or 2.4."),
+        }
+    }
+}
+
+impl Id3Version {
+    /// Returns the `id3` crate version to write.
+    pub const fn version(self) -> Version {
+        match self {
+            Self::V23 => Version::Id3v23,
+            Self::V24 => Version::Id3v24,
+        }
+    }
+}
+
+/// What to do with the `ID3v1` tag at the end of MP3 files when the tags are written.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Id3v1 {
+    /// Leave the `ID3v1` tag as-is.
+    #[default]
+    Keep,
+    /// Write the new values to the `ID3v1` tag if the file has one.
+    Update,
+    /// Write the new values to the `ID3v1` tag, adding one if the file doesn't have one.
+    Write,
+    /// Remove the `ID3v1` tag.
+    Remove,
+}
+
+impl FromStr for Id3v1 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(Self::Keep),
+            "update" => Ok(Self::Update),
+            "write" => Ok(Self::Write),
+            "remove" => Ok(Self::Remove),
+            _ => bail!(
+                "Unknown ID3v1 setting \"{s}\". Must be one of keep, update, write or remove."
+            ),
+        }
+    }
+}
+
+/// Reads the `ID3v2` tag from an MP3 file. Files that only have an `ID3v1` tag get an `ID3v2` tag with the same values,
+/// and files without any tag get an empty one.
+pub fn read_mp3_tag(filename: &str) -> Result<Tag> {
+    let mut tag = match Tag::read_from_path(filename) {
+        Ok(tag) => tag,
+        Err(err) if matches!(err.kind, ErrorKind::NoTag) => {
+            if let Ok(v1_tag) = v1::Tag::read_from_path(filename) {
+                log::info!("{filename}: Upgrading the ID3v1 tag to ID3v2.");
+                Tag::from(v1_tag)
+            } else {
+                log::debug!("No ID3 tag found in {filename}. Creating a new one.");
+                Tag::new()
+            }
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("Unable to read the ID3 tag in {filename}"))
+        }
+    };
+
+    upgrade_frames(&mut tag);
+    Ok(tag)
+}
+
+/// Replaces the ID3v2.3 date frames with the ID3v2.4 ones: `TYER`, `TDAT` and `TIME` with `TDRC`, and `TORY` with `TDOR`.
+/// The ID3v2.3 frames are removed if the ID3v2.4 frames are already there.
+///
+/// `IPLS` is replaced with `TIPL`, and in ID3v2.3 tags the `TXXX` frames written by `tag_for_version` for the frames
+/// that only exist in ID3v2.4, eg. `TXXX:ARTISTSORT`, are replaced with those frames, eg. `TSOP`.
+pub fn upgrade_frames(tag: &mut Tag) {
+    if tag.get("TDRC").is_none() {
+        if let Some(date) = recording_date_v23(tag) {
+            tag.set_date_recorded(date);
+        }
+    }
+    if tag.get("TDOR").is_none() {
+        if let Some(date) = original_date_v23(tag) {
+            tag.set_original_date_released(date);
+        }
+    }
+
+    for id in ["TYER", "TDAT", "TIME", "TORY"] {
+        tag.remove(id);
+    }
+
+    if let Some(ipls) = tag.remove("IPLS").into_iter().next() {
+        if tag.get("TIPL").is_none() {
+            tag.add_frame(Frame::with_content("TIPL", ipls.content().clone()));
+        }
+    }
+
+    if tag.version() == Version::Id3v23 {
+        for (id, description) in V24_TEXT_FRAMES {
+            let value = tag
+                .extended_texts()
+                .find(|t| t.description.eq_ignore_ascii_case(description))
+                .map(|t| t.value.clone());
+            if let Some(value) = value {
+                tag.remove_extended_text(Some(description), None);
+                if tag.get(id).is_none() {
+                    tag.set_text(id, value);
+                }
+            }
+        }
+    }
+}
+
+/// Gets the recording date from the `TYER`, `TDAT` (DDMM) and `TIME` (HHMM) frames used by ID3v2.3.
+pub fn recording_date_v23(tag: &Tag) -> Option<Timestamp> {
+    let year = tag.year()?;
+    let pair = |id: &str| {
+        let text = tag.get(id)?.content().text()?.trim();
+        let first = text.get(..2)?.parse::<u8>().ok()?;
+        let second = text.get(2..4)?.parse::<u8>().ok()?;
+        Some((first, second))
+    };
+    let date = pair("TDAT");
+    let time = pair("TIME").filter(|_| date.is_some());
+
+    Some(Timestamp {
+        year,
+        month: date.map(|(_, month)| month),
+        day: date.map(|(day, _)| day),
+        hour: time.map(|(hour, _)| hour),
+        minute: time.map(|(_, minute)| minute),
+        second: None,
+    })
+}
+
+/// Gets the original release year from the `TORY` frame used by ID3v2.3.
+pub fn original_date_v23(tag: &Tag) -> Option<Timestamp> {
+    let year = tag.get("TORY")?.content().text()?.trim().parse().ok()?;
+
+    Some(Timestamp {
+        year,
+        month: None,
+        day: None,
+        hour: None,
+        minute: None,
+        second: None,
+    })
+}
+
+/// Returns a copy of the tag that is ready to be written as the given version.
+///
+/// For ID3v2.3 the dates are moved to the `TYER`, `TDAT`, `TIME` and `TORY` frames, the UTF-8 text is written as UTF-16,
+/// and the values in `TXXX` frames with more than one value are separated by "/", as the `id3` crate does for the
+/// other text frames. `TIPL` and `TMCL` are combined into one `IPLS` frame, and the other frames that only exist in
+/// ID3v2.4, eg. `TSOP` and `TMOO`, are written as `TXXX` frames, eg. `TXXX:ARTISTSORT` and `TXXX:MOOD`.
+pub fn tag_for_version(tag: &Tag, version: Id3Version) -> Tag {
+    let mut out = tag.clone();
+    upgrade_frames(&mut out);
+    if version == Id3Version::V24 {
+        return out;
+    }
+
+    if let Some(date) = out.date_recorded() {
+        out.remove("TDRC");
+        out.set_text("TYER", format!("{:04}", date.year));
+        if let (Some(month), Some(day)) = (date.month, date.day) {
+            out.set_text("TDAT", format!("{day:02}{month:02}"));
+            if let (Some(hour), Some(minute)) = (date.hour, date.minute) {
+                out.set_text("TIME", format!("{hour:02}{minute:02}"));
+            }
+        }
+    }
+    if let Some(date) = out.original_date_released() {
+        out.remove("TDOR");
+        out.set_text("TORY", format!("{:04}", date.year));
+    }
+
+    let items: Vec<_> = ["TIPL", "TMCL"]
+        .into_iter()
+        .flat_map(|id| out.remove(id))
+        .filter_map(|frame| frame.content().involved_people_list().cloned())
+        .flat_map(|list| list.items)
+        .collect();
+    if !items.is_empty() {
+        out.add_frame(Frame::with_content(
+            "IPLS",
+            Content::InvolvedPeopleList(InvolvedPeopleList { items }),
+        ));
+    }
+
+    let mut converted = Tag::with_version(Version::Id3v23);
+    for frame in out.frames() {
+        converted.add_frame(frame_v23(frame));
+    }
+    converted
+}
+
+/// Converts a frame so that it can be written to an ID3v2.3 tag.
+fn frame_v23(frame: &Frame) -> Frame {
+    let v24_description = V24_TEXT_FRAMES
+        .iter()
+        .find(|(id, _)| *id == frame.id())
+        .map(|(_, description)| *description);
+
+    let frame = match (frame.content(), v24_description) {
+        (Content::Text(text), Some(description)) => {
+            log::debug!("Writing {} as TXXX:{description} for ID3v2.3.", frame.id());
+            Frame::from(ExtendedText {
+                description: description.to_string(),
+                value: text.replace('\0', "/"),
+            })
+        }
+        (Content::ExtendedText(text), _) if text.value.contains('\0') => {
+            Frame::from(ExtendedText {
+                description: text.description.clone(),
+                value: text.value.replace('\0', "/"),
+            })
+        }
+        _ => frame.clone(),
+    };
+
+    // UTF-8 and UTF-16BE are only allowed in ID3v2.4. The id3 crate writes UTF-16 if no encoding is set.
+    if matches!(frame.encoding(), Some(Encoding::UTF8 | Encoding::UTF16BE)) {
+        frame.set_encoding(None)
+    } else {
+        frame
+    }
+}
+
+/// Updates, writes or removes the `ID3v1` tag at the end of an MP3 file, depending on the setting.
+pub fn write_v1(filename: &str, tag: &Tag, setting: Id3v1) -> Result<()> {
+    match setting {
+        Id3v1::Keep => return Ok(()),
+        Id3v1::Update if !v1::Tag::is_candidate(std::fs::File::open(filename)?)? => return Ok(()),
+        Id3v1::Remove => {
+            if v1::Tag::remove_from_path(filename)? {
+                log::debug!("{filename}: Removed the ID3v1 tag.");
+            }
+            return Ok(());
+        }
+        Id3v1::Update | Id3v1::Write => {}
+    }
+
+    // Any existing tag, including the extended TAG+ block, is replaced
+    v1::Tag::remove_from_path(filename)?;
+    let mut file = OpenOptions::new().append(true).open(filename)?;
+    file.write_all(&encode_v1(tag))?;
+    log::debug!("{filename}: Wrote the ID3v1 tag.");
+
+    // Return safely
+    Ok(())
+}
+
+/// Encodes the values in the tag as an ID3v1.1 tag. Text that doesn't fit is cut short, fields with more than one
+/// value are joined with "; ", and characters that aren't in ISO-8859-1 are written as "?".
+fn encode_v1(tag: &Tag) -> [u8; V1_TAG_LEN] {
+    let mut data = [0_u8; V1_TAG_LEN];
+    data[..3].copy_from_slice(b"TAG");
+
+    let mut put = |offset: usize, len: usize, value: Option<&str>| {
+        let text = tags::display_value(value.unwrap_or_default());
+        let bytes = text
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'));
+        for (pos, byte) in (offset..offset + len).zip(bytes) {
+            data[pos] = byte;
+        }
+    };
+
+    put(3, 30, tag.title());
+    put(33, 30, tag.artist());
+    put(63, 30, tag.album());
+    let year = tag.date_recorded().map(|d| d.year).or_else(|| tag.year());
+    put(93, 4, year.map(|y| format!("{y:04}")).as_deref());
+    put(97, 28, tag.comments().next().map(|c| c.text.as_str()));
+
+    if let Some(track) = tag.track().and_then(|t| u8::try_from(t).ok()) {
+        data[126] = track;
+    }
+    data[127] = tag
+        .genre_parsed()
+        .and_then(|g| common::Genre::from_str(&g).ok())
+        .and_then(|g| u8::try_from(g as u32).ok())
+        .unwrap_or(u8::MAX);
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::frame::InvolvedPeopleListItem;
+    use std::io::Cursor;
+
+    #[test]
+    /// Tests the `id3_version` and `id3v1` settings.
+    fn test_settings_from_str() {
+        assert_eq!(Id3Version::from_str("2.3").unwrap(), Id3Version::V23);
+        assert_eq!(Id3Version::from_str("ID3v2.4").unwrap(), Id3Version::V24);
+        assert!(Id3Version::from_str("2.2").is_err());
+        assert_eq!(Id3Version::default(), Id3Version::V24);
+
+        assert_eq!(Id3v1::from_str("Update").unwrap(), Id3v1::Update);
+        assert_eq!(Id3v1::from_str("REMOVE").unwrap(), Id3v1::Remove);
+        assert!(Id3v1::from_str("strip").is_err());
+        assert_eq!(Id3v1::default(), Id3v1::Keep);
+    }
+
+    #[test]
+    /// Tests converting the dates and multi-value fields to and from ID3v2.3.
+    fn test_tag_for_version() {
+        let mut tag = Tag::new();
+        tag.set_text("TDRC", "2014-06-10T21:45");
+        tag.set_text("TDOR", "1999");
+        tag.set_text("TPE1", "Cher\0Sonny");
+        tag.add_frame(ExtendedText {
+            description: "CATALOGNUMBER".to_string(),
+            value: "A1\0B2".to_string(),
+        });
+        tag.add_frame(Frame::text("TALB", "Album").set_encoding(Some(Encoding::UTF8)));
+
+        let v23 = tag_for_version(&tag, Id3Version::V23);
+        assert!(v23.get("TDRC").is_none());
+        assert_eq!(v23.get("TYER").unwrap().content().text(), Some("2014"));
+        assert_eq!(v23.get("TDAT").unwrap().content().text(), Some("1006"));
+        assert_eq!(v23.get("TIME").unwrap().content().text(), Some("2145"));
+        assert_eq!(v23.get("TORY").unwrap().content().text(), Some("1999"));
+        assert_eq!(v23.extended_texts().next().unwrap().value, "A1/B2");
+        assert_eq!(v23.get("TALB").unwrap().encoding(), None);
+
+        // Written as ID3v2.3 and read back, the dates end up where they started
+        let mut data = Vec::new();
+        v23.write_to(&mut data, Version::Id3v23).unwrap();
+        let mut read = Tag::read_from2(Cursor::new(data)).unwrap();
+        assert_eq!(read.version(), Version::Id3v23);
+        // The id3 crate writes multiple values separated by "/" and splits them again when reading ID3v2.3
+        assert_eq!(read.artist(), Some("Cher\0Sonny"));
+        upgrade_frames(&mut read);
+        assert_eq!(read.date_recorded(), tag.date_recorded());
+        assert_eq!(read.get("TDOR").unwrap().content().text(), Some("1999"));
+        assert!(read.get("TYER").is_none());
+
+        // ID3v2.4 tags are left as they are
+        assert_eq!(tag_for_version(&tag, Id3Version::V24), tag);
+    }
+
+    #[test]
+    /// Tests writing the frames that only exist in ID3v2.4 as ID3v2.3, and reading them back.
+    fn test_tag_for_version_v24_frames() {
+        let people = |items: &[(&str, &str)]| {
+            Content::InvolvedPeopleList(InvolvedPeopleList {
+                items: items
+                    .iter()
+                    .map(|(involvement, involvee)| InvolvedPeopleListItem {
+                        involvement: (*involvement).to_string(),
+                        involvee: (*involvee).to_string(),
+                    })
+                    .collect(),
+            })
+        };
+
+        let mut tag = Tag::new();
+        for (id, _) in V24_TEXT_FRAMES {
+            tag.set_text(id, format!("{id} value"));
+        }
+        tag.add_frame(Frame::with_content(
+            "TIPL",
+            people(&[("producer", "Nellee Hooper")]),
+        ));
+        tag.add_frame(Frame::with_content("TMCL", people(&[("drums", "Gota")])));
+
+        let v23 = tag_for_version(&tag, Id3Version::V23);
+        for (id, description) in V24_TEXT_FRAMES {
+            assert!(v23.get(id).is_none());
+            assert!(v23
+                .extended_texts()
+                .any(|t| t.description == description && t.value == format!("{id} value")));
+        }
+        assert!(v23.get("TIPL").is_none());
+        assert!(v23.get("TMCL").is_none());
+        assert_eq!(
+            v23.get("IPLS").unwrap().content(),
+            &people(&[("producer", "Nellee Hooper"), ("drums", "Gota")])
+        );
+
+        // Written as ID3v2.3 and read back, the frames end up as ID3v2.4 frames again
+        let mut data = Vec::new();
+        v23.write_to(&mut data, Version::Id3v23).unwrap();
+        let mut read = Tag::read_from2(Cursor::new(data)).unwrap();
+        assert_eq!(read.version(), Version::Id3v23);
+        upgrade_frames(&mut read);
+        for (id, _) in V24_TEXT_FRAMES {
+            assert_eq!(
+                read.get(id).unwrap().content().text(),
+                Some(format!("{id} value").as_str())
+            );
+        }
+        assert_eq!(read.extended_texts().count(), 0);
+        assert!(read.get("IPLS").is_none());
+        assert_eq!(
+            read.get("TIPL").unwrap().content(),
+            &people(&[("producer", "Nellee Hooper"), ("drums", "Gota")])
+        );
+    }
+
+    #[test]
+    /// Tests encoding an ID3v1.1 tag and reading it back.
+    fn test_encode_v1() {
+        let mut tag = Tag::new();
+        tag.set_title("A title that is far too long to fit in an ID3v1 tag");
+        tag.set_artist("Björk\0Thom Yorke");
+        tag.set_text("TDRC", "1997-09-22");
+        tag.set_track(7);
+        tag.set_genre("Electronic");
+        tag.add_frame(id3::frame::Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: "Ωmega".to_string(),
+        });
+
+        let data = encode_v1(&tag);
+        let read = v1::Tag::read_from(Cursor::new(data.to_vec())).unwrap();
+        assert_eq!(read.title, "A title that is far too long t");
+        assert_eq!(read.artist, "Björk; Thom Yorke");
+        assert_eq!(read.year, "1997");
+        assert_eq!(read.comment, "?mega");
+        assert_eq!(read.track, Some(7));
+        assert_eq!(read.genre(), Some("Electronic"));
+
+        // No genre is written as 255
+        assert_eq!(encode_v1(&Tag::new())[127], u8::MAX);
+    }
+}
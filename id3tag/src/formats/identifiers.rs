@@ -0,0 +1,151 @@
+//! Checks the format of the ISRC and ISWC identifiers before they are written to the files.
+
+use anyhow::{bail, Result};
+use common::FileTypes;
+use std::collections::HashMap;
+
+use super::tags;
+
+/// Checks the ISRC and ISWC values in the new tags and writes them in their standard form,
+/// eg. `AUAP08000041` and `T-034.524.680-1`. Each value of a multi-value field is checked.
+///
+/// # Errors
+///
+/// Returns an error naming the value if it isn't a valid ISRC or ISWC.
+pub fn check_values(file_type: FileTypes, new_tags: &mut HashMap<String, String>) -> Result<()> {
+    let tag_names = tags::get_tag_names(file_type);
+
+    if let Some(value) = new_tags.get_mut(&tag_names.isrc) {
+        *value = normalize(value, normalize_isrc)?;
+    }
+    if let Some(value) = new_tags.get_mut(&tag_names.iswc) {
+        *value = normalize(value, normalize_iswc)?;
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Normalizes each value of a (possibly) multi-value field. Empty values are left as-is.
+fn normalize(value: &str, normalizer: fn(&str) -> Result<String>) -> Result<String> {
+    let values = tags::split_values(value)
+        .map(|v| {
+            if v.is_empty() {
+                Ok(String::new())
+            } else {
+                normalizer(v)
+            }
+        })
+        .collect::<Result<Vec<String>>>()?;
+    Ok(tags::join_values(values))
+}
+
+/// Checks that the value is an ISRC, eg. `AU-AP0-80-00041`, and returns it without separators, eg. `AUAP08000041`.
+///
+/// An ISRC is a 2-letter country code, a 3-character registrant code, a 2-digit year and a 5-digit designation code.
+pub fn normalize_isrc(value: &str) -> Result<String> {
+    let isrc: String = value
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+
+    let chars: Vec<char> = isrc.chars().collect();
+    let valid = chars.len() == 12
+        && chars[..2].iter().all(char::is_ascii_uppercase)
+        && chars[2..5].iter().all(char::is_ascii_alphanumeric)
+        && chars[5..].iter().all(char::is_ascii_digit);
+    if !valid {
+        bail!("'{value}' is not a valid ISRC. Expected 12 characters, eg. AUAP08000041 or AU-AP0-80-00041.");
+    }
+
+    Ok(isrc)
+}
+
+/// Checks that the value is an ISWC with a correct check digit, eg. `T0345246801`,
+/// and returns it in the standard form, eg. `T-034.524.680-1`.
+pub fn normalize_iswc(value: &str) -> Result<String> {
+    let iswc: String = value
+        .chars()
+        .filter(|c| *c != '-' && *c != '.' && !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+
+    let Some(digits) = iswc
+        .strip_prefix('T')
+        .filter(|d| d.len() == 10 && d.chars().all(|c| c.is_ascii_digit()))
+    else {
+        bail!("'{value}' is not a valid ISWC. Expected the letter T and 10 digits, eg. T-034.524.680-1.");
+    };
+
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum = 1 + (1..).zip(&digits[..9]).map(|(i, d)| i * d).sum::<u32>();
+    let check = (10 - sum % 10) % 10;
+    if check != digits[9] {
+        bail!("'{value}' is not a valid ISWC. The check digit should be {check}.");
+    }
+
+    let digits: String = digits.iter().map(ToString::to_string).collect();
+    Ok(format!(
+        "T-{}.{}.{}-{}",
+        &digits[..3],
+        &digits[3..6],
+        &digits[6..9],
+        &digits[9..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that ISRCs are accepted with or without hyphens and that invalid ones are rejected.
+    fn test_normalize_isrc() {
+        assert_eq!(normalize_isrc("AUAP08000041").unwrap(), "AUAP08000041");
+        assert_eq!(normalize_isrc("au-ap0-80-00041").unwrap(), "AUAP08000041");
+        assert_eq!(normalize_isrc("GB AYE 69 00531").unwrap(), "GBAYE6900531");
+
+        assert!(normalize_isrc("AUAP0800004").is_err());
+        assert!(normalize_isrc("1UAP08000041").is_err());
+        assert!(normalize_isrc("AUAP0800004X").is_err());
+    }
+
+    #[test]
+    /// Tests that ISWCs are written in the standard form and that the check digit is verified.
+    fn test_normalize_iswc() {
+        assert_eq!(
+            normalize_iswc("T-034.524.680-1").unwrap(),
+            "T-034.524.680-1"
+        );
+        assert_eq!(normalize_iswc("T0345246801").unwrap(), "T-034.524.680-1");
+        assert_eq!(
+            normalize_iswc("t-070.000.111-1").unwrap(),
+            "T-070.000.111-1"
+        );
+
+        assert!(normalize_iswc("T-034.524.680-2").is_err());
+        assert!(normalize_iswc("034.524.680-1").is_err());
+        assert!(normalize_iswc("T-034.524.680").is_err());
+    }
+
+    #[test]
+    /// Tests that each value of a field is checked, and that other tags are left as-is.
+    fn test_check_values() {
+        let mut new_tags = HashMap::from([
+            (
+                "TSRC".to_string(),
+                "au-ap0-80-00041\0GBAYE6900531".to_string(),
+            ),
+            ("TXXX:ISWC".to_string(), "T0345246801".to_string()),
+            ("TPE1".to_string(), "ac-dc".to_string()),
+        ]);
+        assert!(check_values(FileTypes::MP3, &mut new_tags).is_ok());
+        assert_eq!(new_tags["TSRC"], "AUAP08000041\0GBAYE6900531");
+        assert_eq!(new_tags["TXXX:ISWC"], "T-034.524.680-1");
+        assert_eq!(new_tags["TPE1"], "ac-dc");
+
+        let mut new_tags = HashMap::from([("ISRC".to_string(), "nope".to_string())]);
+        assert!(check_values(FileTypes::Flac, &mut new_tags).is_err());
+    }
+}
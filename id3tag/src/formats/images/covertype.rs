@@ -9,6 +9,16 @@ pub enum CoverType {
     Back,
 }
 
+impl CoverType {
+    /// The generic tag name for the cover, ie. `PICTUREFRONT` or `PICTUREBACK`.
+    pub const fn tag_name(self) -> &'static str {
+        match self {
+            Self::Front => "PICTUREFRONT",
+            Self::Back => "PICTUREBACK",
+        }
+    }
+}
+
 /// Implements the `Display` trait for the `CoverType` enum
 impl Display for CoverType {
     /// Display function for the `CoverType`.
@@ -3,7 +3,7 @@
 use anyhow::{bail, Result};
 use image::{self, imageops::FilterType, ImageFormat, ImageReader};
 use std::io::Cursor;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 // Homegrown stuff
 mod covertype;
@@ -14,9 +14,9 @@ mod tests;
 
 use crate::default_values::DefaultValues;
 use common::path_to_string;
-use covertype::CoverType;
 use paths::{find_first_image, gather_cover_candidates};
 
+pub use covertype::CoverType;
 pub use ops::aspect_ratio_ok;
 
 /// Catch the image-related CLI parameters and process the image(s).
@@ -120,7 +120,7 @@ pub fn read_cover(cover_file: &str, max_size: u32) -> Result<(Vec<u8>, String)>
     Ok((img_buffer.into_inner(), mime_type))
 }
 
-/// Converts image bytes to JPEG format. Used by formats that only support JPEG (e.g. MP4).
+/// Converts image bytes to JPEG format. Used for images in a format the music file doesn't support, eg. WebP in MP4 files.
 pub fn to_jpeg(data: &[u8]) -> Result<Vec<u8>> {
     let img = image::load_from_memory(data)?;
     let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
@@ -128,6 +128,31 @@ pub fn to_jpeg(data: &[u8]) -> Result<Vec<u8>> {
     Ok(buf.into_inner())
 }
 
+/// Saves cover art read from a music file to a file in the temporary folder, so it can be set in another music file.
+/// The extension is based on the image format, eg. `.png`, so `read_cover` keeps the format.
+///
+/// # Arguments
+///
+/// `data: &[u8]` - the image bytes.
+/// `name: &str` - used in the file name to tell the files apart, eg. `front`.
+///
+/// # Returns
+///
+/// `anyhow::Result<PathBuf>` - the path to the saved file. The caller is responsible for removing it.
+pub fn save_cover(data: &[u8], name: &str) -> Result<PathBuf> {
+    let format = image::guess_format(data).unwrap_or(ImageFormat::Jpeg);
+    let extension = format.extensions_str().first().unwrap_or(&"jpg");
+    let path = std::env::temp_dir().join(format!(
+        "id3tag-{}-{}-{name}.{extension}",
+        std::process::id(),
+        common::get_unique_value()
+    ));
+    std::fs::write(&path, data)?;
+    log::debug!("save_cover::path = {}", path.display());
+
+    Ok(path)
+}
+
 /// Detects the image format from the file extension, falling back to JPEG.
 fn detect_format(cover_file: &str) -> ImageFormat {
     ImageFormat::from_path(Path::new(cover_file)).unwrap_or(ImageFormat::Jpeg)
@@ -10,12 +10,10 @@ pub fn aspect_ratio_ok(x: u32, y: u32) -> bool {
 }
 
 #[cfg(test)]
-///
 mod tests {
     use super::*;
 
     #[test]
-    ///
     fn test_aspect_ratio_is_ok() {
         assert!(aspect_ratio_ok(100, 100));
         assert!(aspect_ratio_ok(500, 500));
@@ -9,17 +9,30 @@ use std::{
     path::{Component, Path},
 };
 
+use crate::csv_import;
+use crate::cue_sheet::{CueSheet, CUE_AUTO};
 use crate::default_values::DefaultValues;
+use crate::journal::{CoverImage, Journal, JournalEntry};
 use common::FileTypes;
 
 mod ape;
+pub mod auto_number;
+pub mod copy_tags;
+mod dff;
 mod dsf;
+pub mod filename_tags;
 mod flac;
+pub mod id3_versions;
+mod identifiers;
 pub mod images;
 mod mp3;
 mod mp4;
+mod ogg;
+mod removals;
 mod tag_macros;
-mod tags;
+pub mod tags;
+pub mod transform;
+pub mod wav;
 
 // Import the macros
 use crate::{disc_number_count, pic, tag, track_album_artist, track_genre_num, track_number_count};
@@ -32,6 +45,7 @@ use crate::{disc_number_count, pic, tag, track_album_artist, track_genre_num, tr
 /// - `filename: &str` -- the name of the file
 /// - `config: &DefaultValues` -- The default config values to use (stop on error, etc)
 /// - `cli_args: &clap::ArgMatches` -- The config values and options supplied from the CLI
+/// - `existing: Option<ExistingTags>` -- The tags already read from the file, eg. for the `--where` filter
 /// - `counts: &mut shared::Counts` -- A struct for various file counters (skipped, processed, total)
 ///
 /// Returns:
@@ -43,10 +57,27 @@ pub fn process_file(
     filename: &str,
     cfg: &DefaultValues,
     cli_args: &clap::ArgMatches,
+    existing: Option<ExistingTags>,
 ) -> Result<bool> {
     // Check if we need to create one or more cover images.
     log::debug!("process_file::filename = {filename}");
     let mut config = cfg.clone();
+
+    // Use the tag values and cover art settings from any `.id3tag.toml` files in the folders
+    if let Some(dirs) = &cfg.directory_configs {
+        match dirs.config_for(filename, cli_args) {
+            Ok(Some(dir_config)) => {
+                config.tags.clone_from(&dir_config.tags);
+                config.pictures.clone_from(&dir_config.pictures);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log::error!("{filename} :: {err:#}");
+                return Err(err);
+            }
+        }
+    }
+
     let (front_cover_path, back_cover_path) = images::get_cover_filenames(filename, &config)?;
     log::debug!("process_file::front_cover_path = {front_cover_path:?}, back_cover_path = {back_cover_path:?}, ");
 
@@ -58,7 +89,19 @@ pub fn process_file(
         config.pictures.picture_back = back_cover_path;
     }
 
-    let new_tags_result = parse_options(filename, file_type, &config, cli_args);
+    // Keeps the covers copied from another file until the file has been processed
+    let mut saved_covers = copy_tags::SavedCovers::default();
+
+    let new_tags_result =
+        parse_options(filename, file_type, &config, cli_args).and_then(|mut nt| {
+            copy_tags::copy_values(filename, file_type, &config, &mut nt, &mut saved_covers)?;
+            csv_values(filename, file_type, &mut config, &mut nt);
+            fill_only_values(filename, file_type, &config, &mut nt)?;
+            split_multi_values(file_type, &config, &mut nt);
+            transform_values(filename, file_type, &config, &mut nt)?;
+            identifiers::check_values(file_type, &mut nt)?;
+            Ok(nt)
+        });
 
     let mut new_tags;
     let mut processed = false;
@@ -67,19 +110,36 @@ pub fn process_file(
     match new_tags_result {
         Ok(res) => {
             new_tags = res;
-            let proc_res = match file_type {
-                FileTypes::Ape => ape::process(filename, &new_tags, &config),
-                FileTypes::Dsf => dsf::process(filename, &new_tags, &config),
-                FileTypes::Flac => flac::process(filename, &mut new_tags, &config),
-                FileTypes::MP3 => mp3::process(filename, &new_tags, &config),
-                FileTypes::M4A => mp4::process(filename, &new_tags, &config),
-                FileTypes::Unknown => {
-                    bail!("{filename} is unknown file type.")
-                }
+            // Keep the old values and covers for the undo journal, or for the preview on a dry run
+            let journal = config.journal_entries.clone();
+            let existing = match (&journal, existing) {
+                (None, _) => None,
+                (Some(_), Some(existing)) => Some(existing),
+                (Some(_), None) => ExistingTags::read(filename, file_type)
+                    .map_err(|err| log::warn!("{filename} :: Not added to the journal: {err:#}"))
+                    .ok(),
             };
 
+            let proc_res = process_tags(file_type, filename, &mut new_tags, &config);
+
             match proc_res {
-                Ok(_) => processed = true,
+                Ok(_) => {
+                    processed = true;
+                    if let (Some(journal), Some(existing)) = (journal, existing) {
+                        // The new tags are updated while the file is processed, eg. FLAC track numbers like "3/12"
+                        if config.execution.dry_run.unwrap_or(true) {
+                            let preview =
+                                preview_values(file_type, &config, &existing.values, &new_tags);
+                            let old_values = to_generic(file_type, existing.values);
+                            journal.record_changes(filename, &old_values, &preview);
+                            let covers =
+                                preview_covers(file_type, &config, &existing.covers, &new_tags);
+                            journal.record_covers(filename, &existing.covers, &covers);
+                        } else {
+                            journal_changes(filename, file_type, &journal, existing);
+                        }
+                    }
+                }
                 Err(err) => {
                     if config.execution.stop_on_error.unwrap_or(true) {
                         return Err(err).with_context(|| format!("Unable to process {filename}"));
@@ -100,6 +160,253 @@ pub fn process_file(
     Ok(processed)
 }
 
+/// Writes the new tags to the file using the functions for the file type.
+fn process_tags(
+    file_type: FileTypes,
+    filename: &str,
+    new_tags: &mut HashMap<String, String>,
+    config: &DefaultValues,
+) -> Result<bool> {
+    match file_type {
+        FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+            ape::process(filename, file_type, new_tags, config)
+        }
+        FileTypes::Dff => dff::process(filename, new_tags, config),
+        FileTypes::Dsf => dsf::process(filename, new_tags, config),
+        FileTypes::Flac => flac::process(filename, new_tags, config),
+        FileTypes::MP3 => mp3::process(filename, new_tags, config),
+        FileTypes::M4A => mp4::process(filename, new_tags, config),
+        FileTypes::Ogg | FileTypes::Opus => ogg::process(filename, file_type, new_tags, config),
+        FileTypes::Wav | FileTypes::Aiff => wav::process(filename, file_type, new_tags, config),
+        FileTypes::Unknown => {
+            bail!("{filename} is unknown file type.")
+        }
+    }
+}
+
+/// The tag values and covers in a file before it is processed.
+pub struct ExistingTags {
+    /// The values, keyed on the tag names for the file type (eg. `TPE1` for MP3).
+    values: HashMap<String, String>,
+
+    /// The front and back covers.
+    covers: Vec<(images::CoverType, Vec<u8>)>,
+}
+
+impl ExistingTags {
+    /// Reads the values and covers from the file.
+    pub fn read(filename: &str, file_type: FileTypes) -> Result<Self> {
+        Ok(Self {
+            values: read_values(filename, file_type)?,
+            covers: read_covers(filename, file_type)?,
+        })
+    }
+
+    /// Checks if the tags match the `--where` filter. Cover art is given as the size of the image.
+    pub fn matches(&self, file_type: FileTypes, filter: &common::Filter) -> bool {
+        let mut values = common::FilterValues::new();
+        for (key, value) in &self.values {
+            if let Some(generic) = tags::generic_key(file_type, key) {
+                values.insert(&generic, tags::split_values(value));
+            }
+        }
+        for (cover_type, image) in &self.covers {
+            values.insert(cover_type.tag_name(), [image.len().to_string()]);
+        }
+
+        filter.matches(&values)
+    }
+}
+
+/// Records the tags and covers changed in the file in the undo journal. The file may have been renamed by now.
+fn journal_changes(filename: &str, file_type: FileTypes, journal: &Journal, old: ExistingTags) {
+    let current = journal.current_path(filename);
+    match ExistingTags::read(&current, file_type) {
+        Ok(new) => {
+            journal.record_changes(
+                filename,
+                &to_generic(file_type, old.values),
+                &to_generic(file_type, new.values),
+            );
+            journal.record_covers(filename, &old.covers, &new.covers);
+        }
+        Err(err) => log::warn!("{current} :: Not added to the journal: {err:#}"),
+    }
+}
+
+/// Works out the values the file will have once the new tags are written, keyed on the generic tag names.
+/// Used for the dry-run preview. Covers are handled by `preview_covers`.
+///
+/// The values are written the same way as the file types do, so disc and track numbers in files that store them as
+/// numbers (eg. MP3 and MP4) lose any leading zeros.
+fn preview_values(
+    file_type: FileTypes,
+    config: &DefaultValues,
+    existing: &HashMap<String, String>,
+    new_tags: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let removals = removals::Removals::new(file_type, config);
+    let tag_names = tags::get_tag_names(file_type);
+
+    let mut values: HashMap<String, String> = existing
+        .iter()
+        .filter(|(key, _)| !removals.should_remove(key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let numbers = [
+        &tag_names.disc_number,
+        &tag_names.disc_number_total,
+        &tag_names.track_number,
+        &tag_names.track_number_total,
+    ];
+    let stored_as_number = !matches!(
+        file_type,
+        FileTypes::Flac
+            | FileTypes::Ogg
+            | FileTypes::Opus
+            | FileTypes::Ape
+            | FileTypes::WavPack
+            | FileTypes::Musepack
+            | FileTypes::Tak
+    );
+
+    for (key, value) in new_tags {
+        if *key == tag_names.picture_front || *key == tag_names.picture_back {
+            continue;
+        }
+
+        // Numbers that can't be read are set to 1
+        let value = if stored_as_number && numbers.contains(&key) {
+            value.trim().parse::<u32>().unwrap_or(1).to_string()
+        } else {
+            value.trim().to_string()
+        };
+        values.insert(key.clone(), value);
+    }
+
+    to_generic(file_type, values)
+}
+
+/// Works out the front and back covers the file will have once the new tags are written. Used for the dry-run
+/// preview. New covers are read the same way as the file types do, so images that are resized get their new size.
+fn preview_covers(
+    file_type: FileTypes,
+    config: &DefaultValues,
+    existing: &[(images::CoverType, Vec<u8>)],
+    new_tags: &HashMap<String, String>,
+) -> Vec<(images::CoverType, Vec<u8>)> {
+    let removals = removals::Removals::new(file_type, config);
+    let tag_names = tags::get_tag_names(file_type);
+    let max_size = match file_type {
+        FileTypes::M4A => 0,
+        FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+            config.pictures.picture_max_size.unwrap_or(0)
+        }
+        _ => config.pictures.picture_max_size.unwrap_or(500),
+    };
+
+    let mut covers = Vec::new();
+    for cover_type in [images::CoverType::Front, images::CoverType::Back] {
+        let key = match cover_type {
+            images::CoverType::Front => &tag_names.picture_front,
+            images::CoverType::Back => &tag_names.picture_back,
+        };
+        if let Some(cover_file) = new_tags.get(key) {
+            match images::read_cover(cover_file.trim(), max_size) {
+                Ok((data, _)) => covers.push((cover_type, data)),
+                Err(err) => log::debug!("preview_covers::Unable to read {cover_file}: {err:#}"),
+            }
+            continue;
+        }
+
+        // Removing the front cover from an MP4 file removes the back cover too
+        let removed = removals.should_remove(key)
+            || (file_type == FileTypes::M4A && removals.should_remove(&tag_names.picture_front));
+        if !removed {
+            covers.extend(existing.iter().filter(|(t, _)| *t == cover_type).cloned());
+        }
+    }
+
+    covers
+}
+
+/// Sets the tags and covers in the file back to the old values in the undo journal. Tags and covers the file didn't
+/// have are removed. The old covers are read from the `covers` folder written with the journal.
+/// Nothing else is changed, and the file isn't renamed.
+pub fn restore_values(
+    filename: &str,
+    file_type: FileTypes,
+    entry: &JournalEntry,
+    covers: &Path,
+    cfg: &DefaultValues,
+) -> Result<bool> {
+    let mut config = cfg.clone();
+    config.rename_file = None;
+    config.keep = None;
+    config.clear_all = None;
+
+    let current = generic_values(filename, file_type)?;
+    let mut new_tags = HashMap::new();
+    let mut remove = Vec::new();
+    for change in &entry.changes {
+        if current.get(&change.tag) != change.new.as_ref() {
+            log::warn!(
+                "{filename} :: {} has been changed since the journal was written. Restoring it anyway.",
+                change.tag
+            );
+        }
+        match &change.old {
+            Some(value) => {
+                log::debug!("{filename} :: Restore {} = {value}", change.tag);
+                new_tags.insert(tags::map_key(file_type, &change.tag), value.clone());
+            }
+            None => remove.push(change.tag.clone()),
+        }
+    }
+
+    // The old covers are set as they were, without resizing them
+    let mut saved_covers = copy_tags::SavedCovers::default();
+    if !entry.covers.is_empty() {
+        let current_covers = read_covers(filename, file_type)?;
+        for cover_type in [images::CoverType::Front, images::CoverType::Back] {
+            let tag = cover_type.tag_name();
+            let Some(change) = entry.covers.iter().find(|c| c.tag == tag) else {
+                continue;
+            };
+            let current = current_covers
+                .iter()
+                .find(|(t, _)| *t == cover_type)
+                .map(|(_, data)| CoverImage::new(data));
+            if current != change.new {
+                log::warn!(
+                    "{filename} :: {tag} has been changed since the journal was written. Restoring it anyway."
+                );
+            }
+            match &change.old {
+                Some(image) => {
+                    log::debug!("{filename} :: Restore {tag} ({} bytes)", image.size);
+                    let data = fs::read(covers.join(&image.sha256)).with_context(|| {
+                        format!(
+                            "Unable to read the old {cover_type} cover from {}",
+                            covers.display()
+                        )
+                    })?;
+                    let path = saved_covers.save(&data, &cover_type.to_string())?;
+                    new_tags.insert(
+                        tags::map_key(file_type, tag),
+                        path.to_string_lossy().to_string(),
+                    );
+                }
+                None => remove.push(tag.to_string()),
+            }
+        }
+        config.pictures.picture_max_size = Some(0);
+    }
+    config.remove = (!remove.is_empty()).then_some(remove);
+
+    process_tags(file_type, filename, &mut new_tags, &config)
+}
+
 /// Collect the various options/tags submitted into a `HashMap` for later use.
 /// Also checks the default values loaded from a config file.
 #[allow(clippy::cognitive_complexity)]
@@ -113,6 +420,10 @@ fn parse_options(
     let mut nt = HashMap::new();
     let ot = tags::get_tag_names(file_type);
 
+    // Values from the path of the file come first, so any other values take precedence.
+    filename_values(filename, file_type, dv, &mut nt)?;
+    cue_values(filename, file_type, dv, &mut nt)?;
+
     // Track and album artist at the same time.
     track_album_artist!(cli, dv.tags, nt, ot);
 
@@ -201,16 +512,462 @@ fn parse_options(
         false
     );
 
+    identifier_values(file_type, dv, cli, &mut nt);
+
     disc_number_count!(cli, dv.tags, nt, ot, filename);
     track_number_count!(cli, dv.tags, nt, ot, filename);
+    auto_track_number(filename, file_type, dv, &mut nt);
     track_genre_num!(cli, dv.tags, nt, ot);
 
+    // Any other tags, eg. `--set LABEL=Reprise`
+    if let Some(set) = &dv.tags.set {
+        for (key, value) in set {
+            nt.insert(tags::map_key(file_type, key), value.clone());
+        }
+    }
+
     pic!(cli, dv.pictures, nt, ot, front);
     pic!(cli, dv.pictures, nt, ot, back);
 
     Ok(nt)
 }
 
+/// Adds the identifiers, eg. `--isrc` and `--musicbrainz-track-id`, to the new tags.
+fn identifier_values(
+    file_type: FileTypes,
+    dv: &DefaultValues,
+    cli: &clap::ArgMatches,
+    nt: &mut HashMap<String, String>,
+) {
+    let ot = tags::get_tag_names(file_type);
+
+    tag!(cli, dv.tags, nt, ot, "isrc", isrc, false);
+    tag!(cli, dv.tags, nt, ot, "iswc", iswc, false);
+    tag!(cli, dv.tags, nt, ot, "barcode", barcode, false);
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "catalog-number",
+        catalog_number,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-track-id",
+        musicbrainz_track_id,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-album-id",
+        musicbrainz_album_id,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-artist-id",
+        musicbrainz_artist_id,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-album-artist-id",
+        musicbrainz_album_artist_id,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-release-group-id",
+        musicbrainz_release_group_id,
+        false
+    );
+    tag!(
+        cli,
+        dv.tags,
+        nt,
+        ot,
+        "musicbrainz-disc-id",
+        musicbrainz_disc_id,
+        false
+    );
+}
+
+/// Adds the track number assigned by `--auto-track-number` to the new tags.
+fn auto_track_number(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) {
+    if let Some(number) = cfg.track_numbers.as_ref().and_then(|tn| tn.get(filename)) {
+        new_tags.insert(
+            tags::map_key(file_type, "TRACKNUMBER"),
+            format!("{number:0>2}"),
+        );
+    }
+}
+
+/// Adds the values from the path of the file, using the `--tags-from-filename` pattern, to the new tags.
+fn filename_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) -> Result<()> {
+    let Some(pattern) = cfg.filename_pattern()? else {
+        return Ok(());
+    };
+
+    match pattern.tags(filename) {
+        Some(values) => {
+            for (tag, value) in values {
+                new_tags.insert(tags::map_key(file_type, &tag), value);
+            }
+        }
+        None => {
+            log::warn!("{filename} doesn't match the pattern for getting tags from the filename.");
+        }
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Adds the album and track values from the `--cue` sheet to the new tags.
+///
+/// With `auto`, the CUE sheet next to the music file is used, or for FLAC files without one, the one embedded in the file.
+/// The track in an embedded CUE sheet is found using the track number of the file.
+fn cue_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) -> Result<()> {
+    if cfg.cue.as_deref() != Some(CUE_AUTO) && cfg.cue_sheet.is_none() {
+        return Ok(());
+    }
+
+    let (sheet, embedded) = if let Some(sheet) = &cfg.cue_sheet {
+        (sheet.as_ref().clone(), false)
+    } else if let Some(path) = CueSheet::find_for(filename)? {
+        log::debug!("{filename} :: Using CUE sheet {}", path.display());
+        (CueSheet::load(path)?, false)
+    } else if let Some(sheet) = (file_type == FileTypes::Flac)
+        .then(|| flac::read_cue_sheet(filename))
+        .transpose()?
+        .flatten()
+    {
+        log::debug!("{filename} :: Using the embedded CUE sheet.");
+        (sheet, true)
+    } else {
+        log::warn!("{filename} :: No CUE sheet found.");
+        return Ok(());
+    };
+
+    let track = if embedded {
+        let existing = read_values(filename, file_type)?;
+        // The track number may be written as "3/12"
+        let number = current_value("TRACKNUMBER", new_tags, &existing)
+            .and_then(|n| n.split('/').next())
+            .and_then(|n| n.trim().parse::<u32>().ok());
+        match number {
+            Some(number) if number > 0 => sheet.track(number),
+            _ if sheet.tracks.len() == 1 => sheet.tracks.first(),
+            _ => None,
+        }
+    } else {
+        sheet.track_for(filename)
+    };
+    if track.is_none() {
+        log::warn!(
+            "{filename} :: No matching track in the CUE sheet. Only using the album values."
+        );
+    }
+
+    for (tag, value) in sheet
+        .album
+        .iter()
+        .chain(track.iter().flat_map(|t| &t.values))
+    {
+        new_tags.insert(tags::map_key(file_type, tag), value.clone());
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Adds the values from the `--from-csv` file for this file to the new tags. Values from the CSV take
+/// precedence over the other new values. Tags marked with `csv_import::DELETE_MARKER` are added to the tags to remove.
+fn csv_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &mut DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) {
+    let Some(csv_tags) = cfg.csv_tags.clone() else {
+        return;
+    };
+    let Some(values) = csv_tags.values(filename) else {
+        log::debug!("{filename} :: Not found in the CSV file.");
+        return;
+    };
+
+    for (tag, value) in values {
+        let key = tags::map_key(file_type, tag);
+        if value == csv_import::DELETE_MARKER {
+            new_tags.remove(&key);
+            cfg.remove.get_or_insert_with(Vec::new).push(tag.clone());
+        } else {
+            new_tags.insert(key, value.clone());
+        }
+    }
+}
+
+/// Leaves out the new values for fields that already have a value in the file, for `--only-if-empty` and the
+/// fields in the `fill_only` list. Fields that are blank, or are removed before the new values are set, are filled.
+fn fill_only_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) -> Result<()> {
+    if !cfg.only_if_empty.unwrap_or(false) && cfg.fill_only.is_none() {
+        return Ok(());
+    }
+
+    let existing = read_values(filename, file_type)?;
+    let covers: Vec<images::CoverType> = read_covers(filename, file_type)?
+        .into_iter()
+        .map(|(cover_type, _)| cover_type)
+        .collect();
+    drop_filled_values(filename, file_type, cfg, &existing, &covers, new_tags);
+
+    // Return safely
+    Ok(())
+}
+
+/// Removes the new values for the fill-only fields that have a value in `existing`, or a cover in `covers`.
+fn drop_filled_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    existing: &HashMap<String, String>,
+    covers: &[images::CoverType],
+    new_tags: &mut HashMap<String, String>,
+) {
+    let fill_only: Vec<String> = cfg
+        .fill_only
+        .iter()
+        .flatten()
+        .map(|k| tags::map_key(file_type, k))
+        .collect();
+    let all_fields = cfg.only_if_empty.unwrap_or(false);
+    let removals = removals::Removals::new(file_type, cfg);
+    let tag_names = tags::get_tag_names(file_type);
+
+    new_tags.retain(|key, _| {
+        if !all_fields && !fill_only.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+            return true;
+        }
+
+        let has_value = if *key == tag_names.picture_front {
+            covers.contains(&images::CoverType::Front) && !removals.should_remove(key)
+        } else if *key == tag_names.picture_back {
+            covers.contains(&images::CoverType::Back) && !removals.should_remove(key)
+        } else {
+            existing.iter().any(|(k, v)| {
+                k.eq_ignore_ascii_case(key)
+                    && !removals.should_remove(k)
+                    && tags::split_values(v).any(|v| !v.is_empty())
+            })
+        };
+
+        if has_value {
+            log::debug!("{filename} :: {key} already has a value. Not changed.");
+        }
+        !has_value
+    });
+}
+
+/// Splits the new values on the `--multi-value-separator` into fields with more than one value.
+/// Cover art file names are left as-is.
+fn split_multi_values(
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) {
+    let Some(separator) = &cfg.multi_value_separator else {
+        return;
+    };
+
+    let tag_names = tags::get_tag_names(file_type);
+    for (key, value) in new_tags.iter_mut() {
+        if *key == tag_names.picture_front || *key == tag_names.picture_back {
+            continue;
+        }
+        if value.contains(separator.as_str()) {
+            *value = tags::join_values(
+                value
+                    .split(separator.as_str())
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty()),
+            );
+        }
+    }
+}
+
+/// Reads the existing tag values from the file, keyed on the tag names for the file type (eg. `TPE1` for MP3).
+fn read_values(filename: &str, file_type: FileTypes) -> Result<HashMap<String, String>> {
+    match file_type {
+        FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+            ape::read_values(filename)
+        }
+        FileTypes::Dff => dff::read_values(filename),
+        FileTypes::Dsf => dsf::read_values(filename),
+        FileTypes::Flac => flac::read_values(filename),
+        FileTypes::MP3 => mp3::read_values(filename),
+        FileTypes::M4A => mp4::read_values(filename),
+        FileTypes::Ogg | FileTypes::Opus => ogg::read_values(filename),
+        FileTypes::Wav | FileTypes::Aiff => wav::read_values(filename, file_type),
+        FileTypes::Unknown => bail!("{filename} is unknown file type."),
+    }
+}
+
+/// Reads the existing tag values from the file, keyed on the generic (Vorbis comment style) tag names,
+/// eg. `ALBUMARTIST`. Pictures and tags without a generic name are left out.
+fn generic_values(filename: &str, file_type: FileTypes) -> Result<HashMap<String, String>> {
+    Ok(to_generic(file_type, read_values(filename, file_type)?))
+}
+
+/// Changes the keys of the values from the tag names for the file type to the generic tag names.
+/// Pictures and tags without a generic name are left out.
+///
+/// Keys that would get the same generic name, eg. `Label` and `LABEL` in an APE tag, keep their own names,
+/// so neither value is lost.
+fn to_generic(file_type: FileTypes, values: HashMap<String, String>) -> HashMap<String, String> {
+    let generic_keys: HashMap<String, String> = values
+        .keys()
+        .filter_map(|key| tags::generic_key(file_type, key).map(|g| (key.clone(), g)))
+        .collect();
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    for generic in generic_keys.values() {
+        *counts.entry(generic).or_default() += 1;
+    }
+
+    values
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let generic = generic_keys.get(&key)?;
+            if counts[generic] > 1 {
+                Some((key, value))
+            } else {
+                Some((generic.clone(), value))
+            }
+        })
+        .collect()
+}
+
+/// Reads the front and back covers from the file.
+fn read_covers(filename: &str, file_type: FileTypes) -> Result<Vec<(images::CoverType, Vec<u8>)>> {
+    match file_type {
+        FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+            ape::read_covers(filename)
+        }
+        FileTypes::Dff => dff::read_covers(filename),
+        FileTypes::Dsf => dsf::read_covers(filename),
+        FileTypes::Flac => flac::read_covers(filename),
+        FileTypes::MP3 => mp3::read_covers(filename),
+        FileTypes::M4A => mp4::read_covers(filename),
+        FileTypes::Ogg | FileTypes::Opus => ogg::read_covers(filename),
+        FileTypes::Wav | FileTypes::Aiff => wav::read_covers(filename),
+        FileTypes::Unknown => bail!("{filename} is unknown file type."),
+    }
+}
+
+/// Looks up a value by key, ignoring case. New values take precedence over the existing ones.
+fn current_value<'a>(
+    key: &str,
+    new_tags: &'a HashMap<String, String>,
+    existing: &'a HashMap<String, String>,
+) -> Option<&'a String> {
+    let lookup = |map: &'a HashMap<String, String>| {
+        map.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    };
+
+    lookup(new_tags).or_else(|| lookup(existing))
+}
+
+/// Applies the `--replace` search-and-replace operations and the `--case` changes to the current values of the tags,
+/// in that order. Only values that actually change are added to the new tags.
+fn transform_values(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    new_tags: &mut HashMap<String, String>,
+) -> Result<()> {
+    let case_converter = cfg.case_converter()?;
+    if cfg.replace.is_none() && case_converter.is_none() {
+        return Ok(());
+    }
+
+    let existing = read_values(filename, file_type)?;
+
+    // Sets the new value if it is different from the current one
+    let mut update = |key: String, transform: &dyn Fn(&str) -> String| {
+        let Some(value) = current_value(&key, new_tags, &existing) else {
+            log::debug!("{filename} :: No value for {key} to change.");
+            return;
+        };
+
+        // Multi-value fields are changed one value at a time
+        let new_value = tags::join_values(tags::split_values(value).map(transform));
+        if &new_value != value {
+            log::debug!(
+                "{filename} :: {key} \"{}\" -> \"{}\"",
+                tags::display_value(value),
+                tags::display_value(&new_value)
+            );
+            new_tags.insert(key, new_value);
+        }
+    };
+
+    for r in cfg.replace.iter().flatten() {
+        let substitution = transform::Substitution::parse(&r.expression)?;
+        update(tags::map_key(file_type, &r.tag), &|v| {
+            substitution.apply(v).into_owned()
+        });
+    }
+
+    if let Some(converter) = case_converter {
+        let default_fields = transform::DEFAULT_CASE_FIELDS.map(String::from).to_vec();
+        for field in cfg.case_fields.as_ref().unwrap_or(&default_fields) {
+            update(tags::map_key(file_type, field), &|v| converter.apply(v));
+        }
+    }
+
+    // Return safely
+    Ok(())
+}
+
 /// Convert a numerical ID3 genre to a string
 /// Ref: <https://en.wikipedia.org/wiki/ID3#Genre_list_in_ID3v1%5B12%5D>
 #[allow(clippy::too_many_lines)] // Not much we can do about this one.
@@ -619,6 +1376,233 @@ mod tests {
         assert_eq!(disc_count("../testdata/sample.mp3").unwrap(), 1);
     }
 
+    #[test]
+    /// Tests that the CSV values for a file are added to the new tags, and that deletions are added to the removals.
+    fn test_csv_values() {
+        let csv = "path,artist,title,comments\nmusic/01.mp3,Madonna,<delete>,\n";
+        let mut cfg = DefaultValues::new();
+        cfg.csv_tags = Some(std::sync::Arc::new(
+            csv_import::CsvTags::from_reader(csv.as_bytes()).unwrap(),
+        ));
+
+        let mut nt = HashMap::from([
+            ("TIT2".to_string(), "Vogue".to_string()),
+            ("COMM".to_string(), "Keep me".to_string()),
+        ]);
+        csv_values("music/01.mp3", FileTypes::MP3, &mut cfg, &mut nt);
+        assert_eq!(nt.get("TPE1").unwrap(), "Madonna");
+        assert_eq!(nt.get("COMM").unwrap(), "Keep me");
+        assert!(!nt.contains_key("TIT2"));
+        assert_eq!(cfg.remove, Some(vec!["TITLE".to_string()]));
+
+        // Files not in the CSV are left alone
+        let mut nt = HashMap::new();
+        csv_values("music/02.mp3", FileTypes::MP3, &mut cfg, &mut nt);
+        assert!(nt.is_empty());
+    }
+
+    #[test]
+    /// Tests that fill-only fields are only set when the file has no value for them, or a blank value.
+    fn test_drop_filled_values() {
+        let existing = HashMap::from([
+            ("TPE2".to_string(), "Madonna".to_string()),
+            ("TCON".to_string(), " ".to_string()),
+            ("TALB".to_string(), "Erotica".to_string()),
+        ]);
+        let covers = [images::CoverType::Front];
+        let new_tags = HashMap::from([
+            ("TPE2".to_string(), "Various Artists".to_string()),
+            ("TCON".to_string(), "Pop".to_string()),
+            ("TALB".to_string(), "Bedtime Stories".to_string()),
+            ("TIT2".to_string(), "Vogue".to_string()),
+            ("APIC-F".to_string(), "front.jpg".to_string()),
+            ("APIC-B".to_string(), "back.jpg".to_string()),
+        ]);
+
+        // Only the listed fields
+        let mut cfg = DefaultValues::new();
+        cfg.fill_only = Some(vec!["albumartist".to_string(), "GENRE".to_string()]);
+        let mut nt = new_tags.clone();
+        drop_filled_values("01.mp3", FileTypes::MP3, &cfg, &existing, &covers, &mut nt);
+        assert!(!nt.contains_key("TPE2"));
+        assert_eq!(nt.get("TCON").unwrap(), "Pop");
+        assert_eq!(nt.get("TALB").unwrap(), "Bedtime Stories");
+        assert_eq!(nt.len(), 5);
+
+        // All the fields, except the ones being removed
+        cfg.only_if_empty = Some(true);
+        cfg.remove = Some(vec!["ALBUM".to_string()]);
+        let mut nt = new_tags;
+        drop_filled_values("01.mp3", FileTypes::MP3, &cfg, &existing, &covers, &mut nt);
+        let mut keys: Vec<&String> = nt.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["APIC-B", "TALB", "TCON", "TIT2"]);
+    }
+
+    #[test]
+    /// Tests that the preview has the values the file types write, eg. track numbers without leading zeros in MP3 files.
+    fn test_preview_values() {
+        let cfg = DefaultValues::new();
+        let existing = HashMap::from([
+            ("TRCK".to_string(), "1".to_string()),
+            ("TIT2".to_string(), "Vogue".to_string()),
+        ]);
+        let new_tags = HashMap::from([
+            ("TRCK".to_string(), "01".to_string()),
+            ("TRCK-T".to_string(), "12".to_string()),
+            ("TPOS".to_string(), "one".to_string()),
+            ("APIC-F".to_string(), "front.jpg".to_string()),
+        ]);
+        let preview = preview_values(FileTypes::MP3, &cfg, &existing, &new_tags);
+        assert_eq!(preview.get("TRACKNUMBER").unwrap(), "1");
+        assert_eq!(preview.get("TRACKTOTAL").unwrap(), "12");
+        assert_eq!(preview.get("DISCNUMBER").unwrap(), "1");
+        assert_eq!(preview.get("TITLE").unwrap(), "Vogue");
+        assert!(!preview.contains_key("PICTUREFRONT"));
+
+        // FLAC files keep the values as they are given
+        let new_tags = HashMap::from([("TRACKNUMBER".to_string(), "01".to_string())]);
+        let preview = preview_values(FileTypes::Flac, &cfg, &HashMap::new(), &new_tags);
+        assert_eq!(preview.get("TRACKNUMBER").unwrap(), "01");
+
+        // APE keys that only differ in case keep their own names
+        let existing = HashMap::from([
+            ("Label".to_string(), "Sire".to_string()),
+            ("LABEL".to_string(), "Warner".to_string()),
+            ("Artist".to_string(), "Madonna".to_string()),
+        ]);
+        let preview = preview_values(FileTypes::Ape, &cfg, &existing, &HashMap::new());
+        assert_eq!(preview.len(), 3);
+        assert_eq!(preview.get("Label").unwrap(), "Sire");
+        assert_eq!(preview.get("LABEL").unwrap(), "Warner");
+        assert_eq!(preview.get("ARTIST").unwrap(), "Madonna");
+    }
+
+    #[test]
+    /// Tests the covers shown in the dry-run preview.
+    fn test_preview_covers() {
+        let cover = common::test_files::sample_path("cover.png");
+        let cover = cover.to_str().unwrap();
+        let existing = vec![
+            (images::CoverType::Front, vec![1, 2, 3]),
+            (images::CoverType::Back, vec![4, 5, 6]),
+        ];
+        let mut cfg = DefaultValues::new();
+
+        // A new front cover, and the back cover is kept
+        let new_tags = HashMap::from([("PICTUREFRONT".to_string(), cover.to_string())]);
+        let covers = preview_covers(FileTypes::Flac, &cfg, &existing, &new_tags);
+        assert_eq!(covers.len(), 2);
+        assert!(covers[0].0 == images::CoverType::Front);
+        assert_eq!(covers[0].1, images::read_cover(cover, 500).unwrap().0);
+        assert_eq!(covers[1].1, vec![4, 5, 6]);
+
+        cfg.remove = Some(vec!["PICTUREBACK".to_string()]);
+        let covers = preview_covers(FileTypes::Flac, &cfg, &existing, &HashMap::new());
+        assert_eq!(covers.len(), 1);
+        assert!(covers[0].0 == images::CoverType::Front);
+
+        // Removing the front cover from an MP4 file removes both
+        cfg.remove = Some(vec!["PICTUREFRONT".to_string()]);
+        assert!(preview_covers(FileTypes::M4A, &cfg, &existing, &HashMap::new()).is_empty());
+        assert_eq!(
+            preview_covers(FileTypes::MP3, &cfg, &existing, &HashMap::new()).len(),
+            1
+        );
+    }
+
+    #[test]
+    /// Tests that runs that only remove fields write the files and count them as processed.
+    fn test_removal_only() {
+        let files = common::test_files::TestFiles::new();
+        let samples = [
+            ("silence.ape", FileTypes::Ape),
+            ("silence.flac", FileTypes::Flac),
+            ("silence.m4a", FileTypes::M4A),
+            ("silence.mp3", FileTypes::MP3),
+        ];
+        let mut cfg = DefaultValues::new();
+        cfg.execution.dry_run = Some(false);
+
+        for (sample, file_type) in samples {
+            let path = files.copy(sample, sample);
+            let filename = path.to_str().unwrap();
+
+            cfg.remove = Some(vec!["GENRE".to_string()]);
+            let mut dry_run = cfg.clone();
+            dry_run.execution.dry_run = Some(true);
+            assert!(process_tags(file_type, filename, &mut HashMap::new(), &dry_run).unwrap());
+            assert!(generic_values(filename, file_type)
+                .unwrap()
+                .contains_key("GENRE"));
+
+            assert!(process_tags(file_type, filename, &mut HashMap::new(), &cfg).unwrap());
+            let values = generic_values(filename, file_type).unwrap();
+            assert!(!values.contains_key("GENRE"), "{sample}: {values:?}");
+            assert_eq!(values.get("TITLE").unwrap(), "Silence", "{sample}");
+
+            cfg.remove = None;
+            cfg.clear_all = Some(true);
+            assert!(process_tags(file_type, filename, &mut HashMap::new(), &cfg).unwrap());
+            let values = generic_values(filename, file_type).unwrap();
+            assert!(values.is_empty(), "{sample}: {values:?}");
+            cfg.clear_all = None;
+        }
+    }
+
+    #[test]
+    /// Tests that `--clear-all` records the removed cover in the journal, and that undoing it puts the cover back.
+    fn test_undo_covers() {
+        let files = common::test_files::TestFiles::new();
+        let cover = common::test_files::sample_path("cover.png");
+        let pixels = |data: &[u8]| image::load_from_memory(data).unwrap().to_rgb8();
+        let front = |filename: &str, file_type: FileTypes| {
+            read_covers(filename, file_type)
+                .unwrap()
+                .into_iter()
+                .find(|(t, _)| *t == images::CoverType::Front)
+                .map(|(_, data)| data)
+        };
+
+        for (sample, file_type) in [
+            ("silence.flac", FileTypes::Flac),
+            ("silence.mp3", FileTypes::MP3),
+        ] {
+            let path = files.copy(sample, sample);
+            let filename = path.to_str().unwrap();
+            let mut cfg = DefaultValues::new();
+            cfg.execution.dry_run = Some(false);
+
+            let tag_names = tags::get_tag_names(file_type);
+            let mut new_tags = HashMap::from([(
+                tag_names.picture_front.clone(),
+                cover.to_string_lossy().to_string(),
+            )]);
+            process_tags(file_type, filename, &mut new_tags, &cfg).unwrap();
+            let original = front(filename, file_type).unwrap();
+
+            let journal = std::sync::Arc::new(Journal::default());
+            cfg.clear_all = Some(true);
+            cfg.journal_entries = Some(std::sync::Arc::clone(&journal));
+            let cli = common::build_cli("0.0.0").get_matches_from(["id3tag", filename]);
+            assert!(process_file(file_type, filename, &cfg, &cli, None).unwrap());
+            assert!(front(filename, file_type).is_none(), "{sample}");
+
+            let journal_file = files.path(&format!("{sample}.json"));
+            let journal_file = journal_file.to_str().unwrap();
+            assert_eq!(journal.save(journal_file).unwrap(), 1);
+            let covers = crate::journal::covers_folder(journal_file);
+            let old = CoverImage::new(&original);
+            assert_eq!(fs::read(covers.join(&old.sha256)).unwrap(), original);
+
+            let mut undo_cfg = DefaultValues::new();
+            undo_cfg.execution.dry_run = Some(false);
+            crate::journal::undo(journal_file, &undo_cfg).unwrap();
+            let restored = front(filename, file_type).expect(sample);
+            assert_eq!(pixels(&restored), pixels(&original), "{sample}");
+        }
+    }
+
     #[test]
     /// Tests the `disc_candidates`() function.
     fn test_disc_candidates() {
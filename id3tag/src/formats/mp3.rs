@@ -1,53 +1,111 @@
 //! Contains the functionality to process MP3 files.
-use crate::formats::images::read_cover;
-use crate::formats::tags::option_to_tag;
+use crate::formats::id3_versions;
+use crate::formats::images::{read_cover, CoverType};
+use crate::formats::removals::{log_removal, Removals};
+use crate::formats::tags::{self, option_to_tag};
 use crate::{default_values::DefaultValues, rename_file};
 use common::FileTypes;
-use id3::frame::{self, ExtendedText};
+use id3::frame::{self, Content, ExtendedLink, ExtendedText, UniqueFileIdentifier};
 use id3::TagLike;
-use id3::{frame::PictureType, Tag, Version};
+use id3::{frame::PictureType, Frame, Tag};
 
 use anyhow::{bail, Context, Result};
 use std::collections::HashMap;
 
 /// Performs the actual processing of MP4 files.
-#[allow(clippy::too_many_lines)]
 pub fn process(filename: &str, nt: &HashMap<String, String>, cfg: &DefaultValues) -> Result<bool> {
     log::debug!("Filename: {filename}");
     let mut processed_ok = false;
-    let max_size = cfg.pictures.picture_max_size.unwrap_or(500);
+    let version = cfg.id3_version_setting()?;
+    let id3v1 = cfg.id3v1_setting()?;
 
     // Reat the tag - bomb out if it doesn't work.
-    let mut tag = Tag::read_from_path(filename)?;
+    let mut tag = id3_versions::read_mp3_tag(filename)?;
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(FileTypes::MP3, cfg);
+    if !removals.is_empty() {
+        remove_tags(filename, &mut tag, &removals, cfg);
+    }
+
+    // Set the new tags
+    set_values(filename, &mut tag, nt, cfg)?;
+
+    // Write tags to file - unless we're on a dry run.
+    if cfg.execution.dry_run.unwrap_or(true) {
+        processed_ok = true;
+    } else {
+        match write_tags(filename, &tag, version, id3v1) {
+            Ok(()) => {
+                processed_ok = true;
+                log::info!("{filename}  ✓");
+            }
+            Err(e) => {
+                log::error!("{filename}: Failed to write MP3 tags: {e:#}");
+            }
+        }
+    }
+
+    // Rename file
+    if cfg.rename_file.is_some() {
+        match rename_file(filename, FileTypes::MP3, cfg, &tag) {
+            Ok(()) => {}
+            Err(err) => {
+                log::error!("Failed to rename {filename}: {err}");
+                processed_ok = false;
+            }
+        }
+    }
+
+    // return safely
+    Ok(processed_ok)
+}
+
+/// Writes the `ID3v2` tag as the given version, and then updates, writes or removes the `ID3v1` tag.
+fn write_tags(
+    filename: &str,
+    tag: &Tag,
+    version: id3_versions::Id3Version,
+    id3v1: id3_versions::Id3v1,
+) -> Result<()> {
+    id3_versions::tag_for_version(tag, version).write_to_path(filename, version.version())?;
+    id3_versions::write_v1(filename, tag, id3v1)
+}
+
+/// Sets the new values in the tag. Used for MP3 files and the ID3 tags in WAV and AIFF files.
+///
+/// Covers are read from the image files given as the values of `APIC-F` and `APIC-B`, disc and track numbers are
+/// set using `TPOS`, `TPOS-T`, `TRCK` and `TRCK-T`, and the values of multi-value fields are separated by nulls.
+#[allow(clippy::too_many_lines)]
+pub fn set_values(
+    filename: &str,
+    tag: &mut Tag,
+    nt: &HashMap<String, String>,
+    cfg: &DefaultValues,
+) -> Result<()> {
+    let max_size = cfg.pictures.picture_max_size.unwrap_or(500);
 
-    // Print new tags
     for (key, value) in nt {
         // Output information about tags getting changed
-        if cfg.execution.dry_run.unwrap_or(false) {
-            log::info!("{filename} :: New {key} = {value}");
-        }
+        tags::log_new_value(filename, key, value, cfg);
 
         // Process the tags into the file. Arguaby we could skip this if it's a
         // dry run, but it's good to do it anyway to ensure that it works.
         match key.as_ref() {
             // Front picture
-            "APIC-F" => {
-                match set_picture(&mut tag, value.trim(), PictureType::CoverFront, max_size) {
-                    Ok(()) => (),
-                    Err(err) => {
-                        if cfg.execution.stop_on_error.unwrap_or(false) {
-                            return Err(err).with_context(|| {
-                                format!("Unable to set front cover for {filename}")
-                            });
-                        }
-                        log::error!("Unable to set front cover for {filename}: {err:#}");
+            "APIC-F" => match set_picture(tag, value.trim(), PictureType::CoverFront, max_size) {
+                Ok(()) => (),
+                Err(err) => {
+                    if cfg.execution.stop_on_error.unwrap_or(false) {
+                        return Err(err)
+                            .with_context(|| format!("Unable to set front cover for {filename}"));
                     }
+                    log::error!("Unable to set front cover for {filename}: {err:#}");
                 }
-            }
+            },
 
             // Back picture
-            "APIC-B" => match set_picture(&mut tag, value.trim(), PictureType::CoverBack, max_size)
-            {
+            "APIC-B" => match set_picture(tag, value.trim(), PictureType::CoverBack, max_size) {
                 Ok(()) => (),
                 Err(err) => {
                     if cfg.execution.stop_on_error.unwrap_or(false) {
@@ -59,7 +117,7 @@ pub fn process(filename: &str, nt: &HashMap<String, String>, cfg: &DefaultValues
             },
 
             // Comment
-            "COMM" => set_comment(&mut tag, value.trim()),
+            "COMM" => set_comment(tag, value.trim()),
 
             // Disc number
             "TPOS" => {
@@ -133,39 +191,18 @@ pub fn process(filename: &str, nt: &HashMap<String, String>, cfg: &DefaultValues
                 tag.set_total_tracks(num);
             }
 
-            // Everything else
-            _ => tag.set_text(key, value.trim()),
-        }
-    }
-
-    // Write tags to file - unless we're on a dry run.
-    if cfg.execution.dry_run.unwrap_or(true) {
-        processed_ok = true;
-    } else {
-        match tag.write_to_path(filename, Version::Id3v24) {
-            Ok(()) => {
-                processed_ok = true;
-                log::info!("{filename}  ✓");
-            }
-            Err(e) => {
-                log::error!("{filename}: Failed to write MP3 tags: {e}");
+            // User-defined text, links and unique file identifiers, eg. from `--set`
+            k if k == "WXXX" || k.starts_with("TXXX:") || k.starts_with("UFID:") => {
+                set_custom_frame(tag, k, value.trim());
             }
-        }
-    }
 
-    // Rename file
-    if cfg.rename_file.is_some() {
-        match rename_file(filename, cfg, &tag) {
-            Ok(()) => {}
-            Err(err) => {
-                log::error!("Failed to rename {filename}: {err}");
-                processed_ok = false;
-            }
+            // Everything else. Multi-value fields are written as one frame with the values separated by nulls.
+            _ => tag.set_text_values(key, tags::split_values(value)),
         }
     }
 
-    // return safely
-    Ok(processed_ok)
+    // Return safely
+    Ok(())
 }
 
 /// Adds front or back covers
@@ -202,7 +239,7 @@ fn set_picture(
 }
 
 /// Sets the comments field
-fn set_comment(tags: &mut id3::Tag, value: &str) {
+pub fn set_comment(tags: &mut id3::Tag, value: &str) {
     log::debug!("Removing {} existing comment(s):", tags.comments().count());
     for comment in tags.comments() {
         log::debug!(
@@ -220,9 +257,145 @@ fn set_comment(tags: &mut id3::Tag, value: &str) {
     });
 }
 
-/// Renames an MP3 file based on the pattern provided
-fn rename_file(filename: &str, cfg: &DefaultValues, tag: &id3::Tag) -> Result<()> {
-    let tags_names = option_to_tag(FileTypes::MP3);
+/// Sets a user-defined text frame (`TXXX:<description>`), a user-defined link (`WXXX`) or a unique
+/// file identifier frame (`UFID:<owner>`). Any existing frame with the same description or owner is replaced.
+pub fn set_custom_frame(tags: &mut Tag, key: &str, value: &str) {
+    if key == "WXXX" {
+        log::debug!("Setting WXXX to: {value}");
+        tags.add_frame(ExtendedLink {
+            description: String::new(),
+            link: value.to_string(),
+        });
+    } else if let Some(owner_identifier) = key.strip_prefix("UFID:") {
+        log::debug!("Setting UFID {owner_identifier} to: {value}");
+        tags.add_frame(UniqueFileIdentifier {
+            owner_identifier: owner_identifier.to_string(),
+            identifier: value.as_bytes().to_vec(),
+        });
+    } else {
+        let description = key.strip_prefix("TXXX:").unwrap_or(key);
+        log::debug!("Setting TXXX {description} to: {value}");
+        tags.add_frame(ExtendedText {
+            description: description.to_string(),
+            value: value.to_string(),
+        });
+    }
+}
+
+/// Reads the existing values from the file, using the same keys as `process`,
+/// eg. `TPE1`, `TRCK`, `TRCK-T` or `TXXX:CATALOGNUMBER`.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let tag = id3_versions::read_mp3_tag(filename)?;
+    Ok(existing_values(&tag))
+}
+
+/// Gets the existing values from the tag, using the same keys as `process`. Pictures are not included.
+pub fn existing_values(tag: &Tag) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+
+    for frame in tag.frames() {
+        let value = match frame.content() {
+            Content::Text(text) | Content::Link(text) => text.clone(),
+            Content::ExtendedText(text) => text.value.clone(),
+            Content::ExtendedLink(link) => link.link.clone(),
+            Content::Comment(comment) => comment.text.clone(),
+            Content::UniqueFileIdentifier(ufid) => {
+                String::from_utf8_lossy(&ufid.identifier).to_string()
+            }
+            _ => continue,
+        };
+        values.entry(frame_key(frame)).or_insert(value);
+    }
+
+    // ID3v2.3 tags have the dates in other frames
+    if let Some(date) = id3_versions::recording_date_v23(tag) {
+        values.entry("TDRC".to_string()).or_insert(date.to_string());
+    }
+    if let Some(date) = id3_versions::original_date_v23(tag) {
+        values.entry("TDOR".to_string()).or_insert(date.to_string());
+    }
+
+    // Disc and track numbers are stored as "number/total"
+    if let Some(disc) = tag.disc() {
+        values.insert("TPOS".to_string(), disc.to_string());
+    }
+    if let Some(total) = tag.total_discs() {
+        values.insert("TPOS-T".to_string(), total.to_string());
+    }
+    if let Some(track) = tag.track() {
+        values.insert("TRCK".to_string(), track.to_string());
+    }
+    if let Some(total) = tag.total_tracks() {
+        values.insert("TRCK-T".to_string(), total.to_string());
+    }
+
+    values
+}
+
+/// Reads the front and back covers from the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let tag = id3_versions::read_mp3_tag(filename)?;
+    Ok(existing_covers(&tag))
+}
+
+/// Gets the front and back covers from the tag.
+pub fn existing_covers(tag: &Tag) -> Vec<(CoverType, Vec<u8>)> {
+    tag.pictures()
+        .filter_map(|pic| match pic.picture_type {
+            PictureType::CoverFront => Some((CoverType::Front, pic.data.clone())),
+            PictureType::CoverBack => Some((CoverType::Back, pic.data.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the key used to identify a frame, eg. `TPE1`, `TXXX:CATALOGNUMBER` or `APIC-F`.
+pub fn frame_key(frame: &Frame) -> String {
+    match frame.content() {
+        // This is how `set_comment` stores comments
+        Content::ExtendedText(text) if text.description == "Comment" => "COMM".to_string(),
+        Content::ExtendedText(text) => format!("TXXX:{}", text.description),
+        Content::UniqueFileIdentifier(ufid) => format!("UFID:{}", ufid.owner_identifier),
+        Content::Picture(pic) if pic.picture_type == PictureType::CoverFront => {
+            "APIC-F".to_string()
+        }
+        Content::Picture(pic) if pic.picture_type == PictureType::CoverBack => "APIC-B".to_string(),
+        _ => frame.id().to_string(),
+    }
+}
+
+/// Removes the frames marked for removal. The disc and track totals (`TPOS-T`, `TRCK-T`) are
+/// removed from the `TPOS` and `TRCK` frames if the frames themselves are kept.
+pub fn remove_tags(filename: &str, tag: &mut Tag, removals: &Removals, cfg: &DefaultValues) {
+    let mut kept = Tag::with_version(tag.version());
+    for frame in tag.frames() {
+        let key = frame_key(frame);
+        if removals.should_remove(&key) {
+            log_removal(filename, &key, cfg);
+        } else {
+            kept.add_frame(frame.clone());
+        }
+    }
+    *tag = kept;
+
+    if tag.total_discs().is_some() && removals.should_remove("TPOS-T") {
+        log_removal(filename, "TPOS-T", cfg);
+        tag.remove_total_discs();
+    }
+    if tag.total_tracks().is_some() && removals.should_remove("TRCK-T") {
+        log_removal(filename, "TRCK-T", cfg);
+        tag.remove_total_tracks();
+    }
+}
+
+/// Renames an MP3, WAV or AIFF file based on the pattern provided
+pub fn rename_file(
+    filename: &str,
+    file_type: FileTypes,
+    cfg: &DefaultValues,
+    tag: &id3::Tag,
+) -> Result<()> {
+    let tags_names = option_to_tag(file_type);
     let mut replace_map = HashMap::new();
 
     let mut pattern = String::new();
@@ -266,7 +439,11 @@ fn rename_file(filename: &str, cfg: &DefaultValues, tag: &id3::Tag) -> Result<()
                     }
                 }
             } else {
-                let value = vval.to_string();
+                // Only the first value of multi-value fields is used, same as for FLAC files
+                let value = tags::split_values(vval)
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
                 log::debug!("key = {key}, tag_name = {tag_name}, value = {value}");
                 replace_map.insert(key, value);
             }
@@ -291,3 +468,61 @@ fn rename_file(filename: &str, cfg: &DefaultValues, tag: &id3::Tag) -> Result<()
     // Return safely
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that custom frames get set, and that frames marked for removal are removed.
+    fn test_remove_tags() {
+        let mut tag = Tag::new();
+        tag.set_artist("Artist");
+        tag.set_album("Album");
+        tag.set_track(2);
+        tag.set_total_tracks(12);
+        set_custom_frame(&mut tag, "TXXX:CATALOGNUMBER", "477 6242");
+        set_custom_frame(&mut tag, "WXXX", "http://example.com");
+        set_comment(&mut tag, "Ripped by someone");
+
+        let keys: Vec<String> = tag.frames().map(frame_key).collect();
+        assert!(keys.contains(&"TXXX:CATALOGNUMBER".to_string()));
+        assert!(keys.contains(&"WXXX".to_string()));
+        assert!(keys.contains(&"COMM".to_string()));
+
+        let mut cfg = DefaultValues::new();
+        cfg.remove = Some(vec!["URL".to_string(), "COMMENT".to_string()]);
+        remove_tags(
+            "test.mp3",
+            &mut tag,
+            &Removals::new(FileTypes::MP3, &cfg),
+            &cfg,
+        );
+        assert_eq!(tag.frames().count(), 4);
+        assert_eq!(tag.artist(), Some("Artist"));
+
+        cfg.remove = None;
+        cfg.keep = Some(vec!["ARTIST".to_string(), "TRACKNUMBER".to_string()]);
+        remove_tags(
+            "test.mp3",
+            &mut tag,
+            &Removals::new(FileTypes::MP3, &cfg),
+            &cfg,
+        );
+        assert_eq!(tag.artist(), Some("Artist"));
+        assert_eq!(tag.track(), Some(2));
+        assert!(tag.total_tracks().is_none());
+        assert!(tag.album().is_none());
+        assert_eq!(tag.extended_texts().count(), 0);
+
+        cfg.keep = None;
+        cfg.clear_all = Some(true);
+        remove_tags(
+            "test.mp3",
+            &mut tag,
+            &Removals::new(FileTypes::MP3, &cfg),
+            &cfg,
+        );
+        assert_eq!(tag.frames().count(), 0);
+    }
+}
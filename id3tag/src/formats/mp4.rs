@@ -1,10 +1,13 @@
 //! Contains the functionality to process MP4 files.
 //!
 use crate::default_values::DefaultValues;
-use crate::formats::images;
+use crate::formats::images::{self, CoverType};
+use crate::formats::removals::{log_removal, Removals};
+use crate::formats::tags;
 use crate::rename_file;
 use anyhow::{bail, Context, Result};
-use mp4ameta::{Data, Fourcc, ImgFmt, Tag};
+use common::FileTypes;
+use mp4ameta::{ident, Data, DataIdent, Fourcc, FreeformIdent, Img, ImgBuf, MediaType, Tag};
 use std::collections::HashMap;
 
 /// Performs the actual processing of MP4 files.
@@ -28,38 +31,54 @@ pub fn process(
         }
     }
 
+    // Remove existing tags if asked to
+    let removals = Removals::new(FileTypes::M4A, config);
+    if !removals.is_empty() {
+        remove_tags(filename, &mut tag, &removals, config);
+    }
+
+    // The covers are set after the other tags, so the front cover is always the first image
+    let mut front_cover = None;
+    let mut back_cover = None;
+
     // Process tags
     for (key, value) in new_tags {
         // Let the user know what we're processing
-        if !(config.execution.detail_off.unwrap_or(false)) {
-            log::debug!("{filename} :: New {key} = {value}");
-        } else if config.execution.dry_run.unwrap_or(false) {
-            log::info!("{filename} :: New {key} = {value}");
-        } else {
-            log::debug!("{filename} :: New {key} = {value}");
-        }
+        tags::log_new_value(filename, key, value, config);
 
         // Process the tags
         match key.as_ref() {
-            "aART" => tag.set_album_artist(value.trim()),
-            "soaa" => tag.set_data(Fourcc(*b"soaa"), Data::Utf8(value.trim().into())), // Album artist stort
-            "©alb" => tag.set_album(value.trim()),
-            "soal" => tag.set_data(Fourcc(*b"soal"), Data::Utf8(value.trim().into())), // Album title sort
-            "©ART" => tag.set_artist(value.trim()),
-            "soar" => tag.set_data(Fourcc(*b"soar"), Data::Utf8(value.trim().into())), // Track artist stort
-            "©nam" => tag.set_title(value.trim()),
-            "sonm" => tag.set_data(Fourcc(*b"sonm"), Data::Utf8(value.trim().into())), // Track title sort
-            "©gen" => tag.set_genre(value.trim()),
-            "©wrt" => tag.set_composer(value.trim()),
-            "soco" => tag.set_data(Fourcc(*b"soco"), Data::Utf8(value.trim().into())), // Composer sort
-            "©day" => tag.set_year(value.trim()),
-            "©cmt" => tag.set_comment(value.trim()),
-            "covr-f" => set_picture(&mut tag, value.trim())?,
-            "covr-b" => log::warn!("Setting back cover on MP4 files is currently not implemented."),
+            "aART" => set_text(&mut tag, ident::ALBUM_ARTIST, value),
+            "soaa" => set_text(&mut tag, ident::ALBUM_ARTIST_SORT_ORDER, value),
+            "©alb" => set_text(&mut tag, ident::ALBUM, value),
+            "soal" => set_text(&mut tag, ident::ALBUM_SORT_ORDER, value),
+            "©ART" => set_text(&mut tag, ident::ARTIST, value),
+            "soar" => set_text(&mut tag, ident::ARTIST_SORT_ORDER, value),
+            "©nam" => set_text(&mut tag, ident::TITLE, value),
+            "sonm" => set_text(&mut tag, ident::TITLE_SORT_ORDER, value),
+            "©gen" => tag.set_genres(tags::split_values(value).map(String::from)),
+            "©wrt" => set_text(&mut tag, ident::COMPOSER, value),
+            "soco" => set_text(&mut tag, ident::COMPOSER_SORT_ORDER, value),
+            "©day" => set_text(&mut tag, ident::YEAR, value),
+            "©cmt" => set_text(&mut tag, ident::COMMENT, value),
+            "cprt" => set_text(&mut tag, ident::COPYRIGHT, value),
+            "©grp" => set_text(&mut tag, ident::GROUPING, value),
+            "©too" => set_text(&mut tag, ident::ENCODER, value),
+            "©lyr" => set_text(&mut tag, ident::LYRICS, value),
+            "©wrk" => set_text(&mut tag, ident::WORK, value),
+            "©mvn" => set_text(&mut tag, ident::MOVEMENT, value),
+            "©mvi" => tag.set_movement_index(parse_number(key, value)?),
+            "©mvc" => tag.set_movement_count(parse_number(key, value)?),
+            "tmpo" => tag.set_bpm(parse_number(key, value)?),
+            "cpil" => set_compilation(&mut tag, value)?,
+            "stik" => tag.set_media_type(parse_media_type(value)?),
+            "covr-f" => front_cover = Some(read_picture(value.trim())?),
+            "covr-b" => back_cover = Some(read_picture(value.trim())?),
             "disk" => tag.set_disc_number(value.parse::<u16>().unwrap_or(1)),
             "disk-t" => tag.set_total_discs(value.parse::<u16>().unwrap_or(1)),
             "trkn" => tag.set_track_number(value.parse::<u16>().unwrap_or(1)),
             "trkn-t" => tag.set_total_tracks(value.parse::<u16>().unwrap_or(1)),
+            k if k.starts_with("----:") => set_freeform(&mut tag, k, value)?,
             _ => {
                 // tag.set_data(Fourcc(key.as_bytes().try_into()?), Data::Utf8(value.into()));
                 bail!("Unknown key: {key}");
@@ -67,6 +86,10 @@ pub fn process(
         }
     }
 
+    if front_cover.is_some() || back_cover.is_some() {
+        set_artworks(filename, &mut tag, front_cover, back_cover);
+    }
+
     // Write to file
     if config.execution.dry_run.unwrap_or(true) {
         processed_ok = true;
@@ -100,23 +123,233 @@ pub fn process(
     Ok(processed_ok)
 }
 
-/// Sets the front or back cover
-fn set_picture(tags: &mut Tag, filename: &str) -> Result<()> {
-    let fmt = ImgFmt::Jpeg;
+/// Reads a cover image. JPEG and PNG images are kept as-is, other formats are converted to JPEG.
+fn read_picture(filename: &str) -> Result<ImgBuf> {
     let (raw_data, mime_type) = images::read_cover(filename, 0)?;
-    // MP4 only supports JPEG — convert if needed
-    let data = if mime_type == "image/jpeg" {
-        raw_data
-    } else {
-        log::debug!("Converting {mime_type} to JPEG for MP4 embedding.");
-        images::to_jpeg(&raw_data)?
+
+    let img = match mime_type.as_str() {
+        "image/jpeg" => Img::jpeg(raw_data),
+        "image/png" => Img::png(raw_data),
+        _ => {
+            log::debug!("Converting {mime_type} to JPEG for MP4 embedding.");
+            Img::jpeg(images::to_jpeg(&raw_data)?)
+        }
     };
-    tags.set_artwork(mp4ameta::Img { fmt, data });
+
+    // Return safely
+    Ok(img)
+}
+
+/// Sets the images in the `covr` atom. The front cover is the first image and the back cover the second,
+/// followed by any other images already in the file. Covers that aren't given are kept as-is.
+fn set_artworks(filename: &str, tag: &mut Tag, front: Option<ImgBuf>, back: Option<ImgBuf>) {
+    let mut artworks: Vec<ImgBuf> = tag.take_artworks().collect();
+
+    for (index, img) in [(0, front), (1, back)] {
+        let Some(img) = img else {
+            continue;
+        };
+
+        if index < artworks.len() {
+            artworks[index] = img;
+        } else {
+            if index > artworks.len() {
+                log::warn!(
+                    "{filename} has no front cover, so the back cover is the first image and will be shown as the front cover."
+                );
+            }
+            artworks.push(img);
+        }
+    }
+
+    tag.set_artworks(artworks);
+}
+
+/// Reads the existing values from the file, using the same keys as `process`,
+/// eg. `©ART`, `trkn`, `trkn-t` or `----:com.apple.iTunes:LABEL`. Artwork is not included.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let tag = Tag::read_from_path(filename)?;
+    let mut values = HashMap::new();
+
+    // Atoms with more than one value are joined into one multi-value field
+    for (ident, data) in tag.data() {
+        if let Some(value) = data.string() {
+            values
+                .entry(ident_key(ident))
+                .and_modify(|v: &mut String| *v = tags::join_values([v.as_str(), value]))
+                .or_insert_with(|| value.to_string());
+        }
+    }
+
+    // Genre may be stored as a number, and disc and track numbers as "number/total"
+    if tag.genre().is_some() {
+        values.insert("©gen".to_string(), tags::join_values(tag.genres()));
+    }
+    if let Some(disc) = tag.disc_number() {
+        values.insert("disk".to_string(), disc.to_string());
+    }
+    if let Some(total) = tag.total_discs() {
+        values.insert("disk-t".to_string(), total.to_string());
+    }
+    if let Some(track) = tag.track_number() {
+        values.insert("trkn".to_string(), track.to_string());
+    }
+    if let Some(total) = tag.total_tracks() {
+        values.insert("trkn-t".to_string(), total.to_string());
+    }
+
+    // The integer atoms
+    if tag.compilation() {
+        values.insert("cpil".to_string(), "1".to_string());
+    }
+    if let Some(bpm) = tag.bpm() {
+        values.insert("tmpo".to_string(), bpm.to_string());
+    }
+    if let Some(index) = tag.movement_index() {
+        values.insert("©mvi".to_string(), index.to_string());
+    }
+    if let Some(count) = tag.movement_count() {
+        values.insert("©mvc".to_string(), count.to_string());
+    }
+    if let Some(media_type) = tag.media_type() {
+        values.insert("stik".to_string(), media_type.code().to_string());
+    }
+
+    // Return safely
+    Ok(values)
+}
+
+/// Reads the covers from the file. MP4 files don't label the images, so the first image is used as the
+/// front cover and the second as the back cover.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let tag = Tag::read_from_path(filename)?;
+
+    let covers = [CoverType::Front, CoverType::Back]
+        .into_iter()
+        .zip(tag.artworks())
+        .map(|(cover_type, img)| (cover_type, img.data.to_vec()))
+        .collect();
+
+    // Return safely
+    Ok(covers)
+}
+
+/// Returns the key used to identify an atom, eg. `©ART` or `----:com.apple.iTunes:LABEL`.
+/// All the images are identified as `covr-f` here. `remove_tags` tells the back cover apart.
+fn ident_key(ident: &DataIdent) -> String {
+    match ident {
+        DataIdent::Fourcc(fourcc) if *fourcc == Fourcc(*b"covr") => "covr-f".to_string(),
+        DataIdent::Fourcc(fourcc) if *fourcc == Fourcc(*b"gnre") => "©gen".to_string(),
+        DataIdent::Fourcc(fourcc) => fourcc.to_string(),
+        DataIdent::Freeform { mean, name } => format!("----:{mean}:{name}"),
+    }
+}
+
+/// Removes the atoms marked for removal. The disc and track totals (`disk-t`, `trkn-t`) are
+/// removed from the `disk` and `trkn` atoms if the atoms themselves are kept.
+/// Removing the front cover (`covr-f`) removes all the images, since the next image would otherwise become the
+/// front cover. Removing the back cover (`covr-b`) only removes the second image.
+fn remove_tags(filename: &str, tag: &mut Tag, removals: &Removals, config: &DefaultValues) {
+    let mut idents: Vec<DataIdent> = Vec::new();
+    for (ident, _) in tag.data() {
+        if !idents.contains(ident) {
+            idents.push(ident.clone());
+        }
+    }
+
+    if tag.artworks().nth(1).is_some()
+        && removals.should_remove("covr-b")
+        && !removals.should_remove("covr-f")
+    {
+        log_removal(filename, "covr-b", config);
+        let mut artworks: Vec<ImgBuf> = tag.take_artworks().collect();
+        artworks.remove(1);
+        tag.set_artworks(artworks);
+    }
+
+    for ident in idents {
+        let key = ident_key(&ident);
+        if removals.should_remove(&key) {
+            log_removal(filename, &key, config);
+            tag.remove_data_of(&ident);
+        }
+    }
+
+    if tag.total_discs().is_some() && removals.should_remove("disk-t") {
+        log_removal(filename, "disk-t", config);
+        tag.remove_total_discs();
+    }
+    if tag.total_tracks().is_some() && removals.should_remove("trkn-t") {
+        log_removal(filename, "trkn-t", config);
+        tag.remove_total_tracks();
+    }
+}
+
+/// Sets a text atom. Multi-value fields get one data item per value, eg. one per artist.
+fn set_text(tag: &mut Tag, fourcc: Fourcc, value: &str) {
+    tag.set_all_data(
+        fourcc,
+        tags::split_values(value).map(|v| Data::Utf8(v.to_string())),
+    );
+}
+
+/// Sets a freeform atom. The key takes the form `----:mean:name`, eg. `----:com.apple.iTunes:LABEL`.
+/// Multi-value fields get one data item per value.
+fn set_freeform(tags: &mut Tag, key: &str, value: &str) -> Result<()> {
+    let Some((mean, name)) = key.strip_prefix("----:").and_then(|k| k.split_once(':')) else {
+        bail!("Invalid freeform key: {key}. Expected ----:mean:name");
+    };
+    tags.set_all_data(
+        FreeformIdent::new_borrowed(mean, name),
+        tags::split_values(value).map(|v| Data::Utf8(v.to_string())),
+    );
+
+    // Return safely
+    Ok(())
+}
+
+/// Parses the value of an integer atom, eg. `tmpo` or `©mvi`.
+fn parse_number(key: &str, value: &str) -> Result<u16> {
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("Unable to set {key}: \"{value}\" is not a number."))
+}
+
+/// Sets or removes the compilation flag (`cpil`). "1", "true" and "yes" set it, "0", "false", "no" and blank remove it.
+fn set_compilation(tag: &mut Tag, value: &str) -> Result<()> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => tag.set_compilation(),
+        "0" | "false" | "no" | "" => tag.remove_compilation(),
+        _ => bail!("Unable to set cpil: \"{value}\" is not 1 or 0."),
+    }
 
     // Return safely
     Ok(())
 }
 
+/// Parses the media kind (`stik`), either as the number stored in the atom, eg. `1`, or the name, eg. `Normal` or `Audiobook`.
+fn parse_media_type(value: &str) -> Result<MediaType> {
+    let value = value.trim();
+    if let Ok(code) = value.parse::<u8>() {
+        return MediaType::try_from(code).with_context(|| format!("Unknown media type: {value}"));
+    }
+
+    [
+        MediaType::Movie,
+        MediaType::Normal,
+        MediaType::AudioBook,
+        MediaType::WhackedBookmark,
+        MediaType::MusicVideo,
+        MediaType::ShortFilm,
+        MediaType::TvShow,
+        MediaType::Booklet,
+    ]
+    .into_iter()
+    .find(|media_type| media_type.to_string().eq_ignore_ascii_case(value))
+    .with_context(|| format!("Unknown media type: {value}"))
+}
+
 /// Renames the MP4 file based on the pattern provided
 fn rename_file(filename: &str, config: &DefaultValues, tag: &mp4ameta::Tag) -> Result<()> {
     let tags_map = get_mp4_tags(tag);
@@ -247,3 +480,44 @@ fn get_mp4_tags(tags: &mp4ameta::Tag) -> HashMap<String, String> {
     // Return safely
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests the values accepted for the integer atoms.
+    fn test_integer_atoms() {
+        assert_eq!(parse_number("tmpo", " 120").unwrap(), 120);
+        assert!(parse_number("tmpo", "fast").is_err());
+
+        assert_eq!(parse_media_type("1").unwrap(), MediaType::Normal);
+        assert_eq!(parse_media_type("audiobook").unwrap(), MediaType::AudioBook);
+        assert!(parse_media_type("3").is_err());
+        assert!(parse_media_type("Podcast").is_err());
+
+        let mut tag = Tag::default();
+        set_compilation(&mut tag, "Yes").unwrap();
+        assert!(tag.compilation());
+        set_compilation(&mut tag, "0").unwrap();
+        assert!(!tag.compilation());
+        assert!(set_compilation(&mut tag, "maybe").is_err());
+    }
+
+    #[test]
+    /// Tests that the front cover is the first image and the back cover the second.
+    fn test_set_artworks() {
+        let mut tag = Tag::default();
+        set_artworks("test.m4a", &mut tag, None, Some(Img::png(vec![2])));
+        assert_eq!(tag.artworks().collect::<Vec<_>>(), [Img::png(&[2][..])]);
+
+        set_artworks("test.m4a", &mut tag, Some(Img::jpeg(vec![1])), None);
+        tag.add_artwork(Img::jpeg(vec![3]));
+        tag.add_artwork(Img::jpeg(vec![5]));
+        set_artworks("test.m4a", &mut tag, None, Some(Img::png(vec![4])));
+        assert_eq!(
+            tag.artworks().collect::<Vec<_>>(),
+            [Img::jpeg(&[1][..]), Img::png(&[4][..]), Img::jpeg(&[5][..])]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Contains the functionality to process Ogg Vorbis and Ogg Opus files.
+//! These use Vorbis comments, the same as FLAC, and store the cover art in `METADATA_BLOCK_PICTURE` comments.
+
+use crate::default_values::DefaultValues;
+use crate::formats::images::{read_cover, CoverType};
+use crate::formats::removals::{log_removal, Removals};
+use crate::formats::tags;
+use crate::rename_file;
+use anyhow::{Context, Result};
+use common::{FileTypes, OggFile, OggPicture};
+use std::collections::HashMap;
+
+/// Performs the actual processing of Ogg Vorbis and Ogg Opus files.
+///
+/// **Parameters:**
+///
+/// - `filename: &str` -- The name of the file to be processed, eg. "somefile.opus".
+/// - `file_type: FileTypes` -- `FileTypes::Ogg` or `FileTypes::Opus`
+/// - `new_tags: &HashMap<String, String>` -- A set of new tags in Key/Value form, eg. _key = ALBUMARTIST_, _value = "The Tragically Hip"_
+/// - `config: &DefaultValues` -- A struct containing default values read from a config file and the CLI
+///
+/// **Returns:**
+///
+/// `anyhow::Result<bool>` -- `Ok(true)` if the file was saved successfully or a dry-run was performed.
+pub fn process(
+    filename: &str,
+    file_type: FileTypes,
+    new_tags: &HashMap<String, String>,
+    config: &DefaultValues,
+) -> Result<bool> {
+    let mut tags = OggFile::read_from_path(filename)?;
+    let mut processed_ok = false;
+    let max_size = config.pictures.picture_max_size.unwrap_or(500);
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(file_type, config);
+    if !removals.is_empty() {
+        remove_tags(filename, &mut tags, &removals, config);
+    }
+
+    // Set new tags
+    for (k, v) in new_tags {
+        tags::log_new_value(filename, k, v, config);
+
+        match k.as_ref() {
+            // Pictures need special treatment
+            "PICTUREFRONT" | "PICTUREBACK" => {
+                let picture_type = if k == "PICTUREFRONT" {
+                    OggPicture::FRONT_COVER
+                } else {
+                    OggPicture::BACK_COVER
+                };
+
+                match set_picture(&mut tags, v.trim(), picture_type, max_size) {
+                    Ok(()) => log::debug!("process::{k} set."),
+                    Err(err) => {
+                        if config.execution.stop_on_error.unwrap_or(true) {
+                            return Err(err).with_context(|| format!("Unable to set {k} to {v}"));
+                        }
+                        log::error!("Unable to set {k} to {v}. Continuing: {err:#}");
+                    }
+                } // match
+            }
+
+            // One comment per value for multi-value fields
+            _ => tags.set(k, tags::split_values(v)),
+        } // match k.as_ref()
+    }
+
+    // Try to save
+    if config.execution.dry_run.unwrap_or(true) {
+        log::debug!("Dry-run. Not saving.");
+        processed_ok = true;
+    } else {
+        match tags.save() {
+            Ok(()) => {
+                processed_ok = true;
+                log::info!("{filename}   ✓");
+            }
+            Err(err) => {
+                if config.execution.stop_on_error.unwrap_or(true) {
+                    return Err(err).with_context(|| format!("Unable to save {filename}"));
+                }
+                log::warn!("Unable to save {filename}: {err:#}");
+            }
+        }
+    }
+
+    // Rename file
+    if config.rename_file.is_some() {
+        rename_file(filename, file_type, config, &tags)?;
+    }
+
+    // Return safely
+    Ok(processed_ok)
+}
+
+/// Reads the existing Vorbis comments from the file. Keys are upper case, eg. `ARTIST`.
+/// Fields with more than one value are joined into one multi-value field.
+pub fn read_values(filename: &str) -> Result<HashMap<String, String>> {
+    let tags = OggFile::read_from_path(filename)?;
+
+    let mut values: HashMap<String, Vec<&str>> = HashMap::new();
+    for (key, value) in tags.comments() {
+        values
+            .entry(key.to_ascii_uppercase())
+            .or_default()
+            .push(value);
+    }
+
+    // Return safely
+    Ok(values
+        .into_iter()
+        .map(|(k, v)| (k, tags::join_values(v)))
+        .collect())
+}
+
+/// Reads the front and back covers from the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    let tags = OggFile::read_from_path(filename)?;
+
+    let covers = tags
+        .pictures()
+        .iter()
+        .filter_map(|pic| match pic.picture_type {
+            OggPicture::FRONT_COVER => Some((CoverType::Front, pic.data.clone())),
+            OggPicture::BACK_COVER => Some((CoverType::Back, pic.data.clone())),
+            _ => None,
+        })
+        .collect();
+
+    // Return safely
+    Ok(covers)
+}
+
+/// Removes the Vorbis comments and pictures marked for removal.
+/// Front and back covers are identified as `PICTUREFRONT` and `PICTUREBACK`, and any other pictures as `PICTURE`.
+fn remove_tags(filename: &str, tags: &mut OggFile, removals: &Removals, cfg: &DefaultValues) {
+    let mut keys: Vec<String> = Vec::new();
+    for (key, _) in tags.comments() {
+        if !keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+            keys.push(key.clone());
+        }
+    }
+    for key in keys.iter().filter(|k| removals.should_remove(k)) {
+        log_removal(filename, key, cfg);
+        tags.remove(key);
+    }
+
+    let mut picture_types: Vec<u32> = Vec::new();
+    for picture in tags.pictures() {
+        if !picture_types.contains(&picture.picture_type) {
+            picture_types.push(picture.picture_type);
+        }
+    }
+    for picture_type in picture_types {
+        let key = match picture_type {
+            OggPicture::FRONT_COVER => "PICTUREFRONT",
+            OggPicture::BACK_COVER => "PICTUREBACK",
+            _ => "PICTURE",
+        };
+        if removals.should_remove(key) {
+            log_removal(filename, &format!("{key} (type {picture_type})"), cfg);
+            tags.remove_picture_type(picture_type);
+        }
+    }
+}
+
+/// Set the front or back cover
+fn set_picture(tags: &mut OggFile, img_file: &str, picture_type: u32, max_size: u32) -> Result<()> {
+    let (img, mime_type) = read_cover(img_file, max_size)?;
+    log::debug!(
+        "set_picture::Image {img_file} read. Length = {}, mime = {mime_type}",
+        img.len()
+    );
+
+    tags.add_picture(OggPicture {
+        picture_type,
+        mime_type,
+        data: img,
+        ..OggPicture::default()
+    });
+
+    // Return safely
+    Ok(())
+}
+
+/// Renames an Ogg file based on the pattern provided
+fn rename_file(
+    filename: &str,
+    file_type: FileTypes,
+    config: &DefaultValues,
+    tags: &OggFile,
+) -> Result<()> {
+    let tags_names = tags::option_to_tag(file_type);
+    let mut replace_map = HashMap::new();
+    let mut pattern = String::new();
+    if let Some(p) = &config.rename_file {
+        pattern.clone_from(p);
+    }
+
+    // get the mappings of %aa --> ALBUMARTIST --> Madonna
+    for (key, vorbis_key) in tags_names {
+        if let Some(value) = tags.get(&vorbis_key).first() {
+            log::debug!("key = {key}, value = {value}");
+            replace_map.insert(key, (*value).to_string());
+        }
+    }
+    log::debug!("replace_map = {replace_map:?}");
+
+    // Try to rename, and process the result
+    let rename_result = rename_file::rename_file(filename, &replace_map, config);
+    match rename_result {
+        Ok(new_filename) => log::info!("{filename} --> {new_filename}"),
+        Err(err) => {
+            if config.execution.stop_on_error.unwrap_or(true) {
+                return Err(err).with_context(|| {
+                    format!("Unable to rename {filename} with tags \"{pattern}\"")
+                });
+            }
+            log::warn!("Unable to rename {filename} with tags \"{pattern}\": {err:#} Continuing.");
+        }
+    }
+
+    // Return safely
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Works out which of the existing tags to remove from a file before new values are set.
+//! Used by `--remove`, `--keep` and `--clear-all`.
+
+use crate::default_values::DefaultValues;
+use crate::formats::tags::map_key;
+use common::FileTypes;
+
+/// The tags to remove from a file, using the keys for the file type (eg. `TPE1` for MP3).
+#[derive(Debug, Default, Clone)]
+pub struct Removals {
+    /// Tags to remove.
+    remove: Vec<String>,
+
+    /// If set, every tag not in this list is removed. An empty list removes everything.
+    keep: Option<Vec<String>>,
+}
+
+impl Removals {
+    /// Builds the list of removals for the file type from the config.
+    /// If both `keep` and `clear_all` are set, `keep` is used.
+    pub fn new(file_type: FileTypes, cfg: &DefaultValues) -> Self {
+        let remove = cfg
+            .remove
+            .iter()
+            .flatten()
+            .map(|k| map_key(file_type, k))
+            .collect();
+
+        let keep = match &cfg.keep {
+            Some(keep) => Some(keep.iter().map(|k| map_key(file_type, k)).collect()),
+            None if cfg.clear_all.unwrap_or(false) => Some(Vec::new()),
+            None => None,
+        };
+
+        Self { remove, keep }
+    }
+
+    /// Returns `true` if there is nothing to remove.
+    pub fn is_empty(&self) -> bool {
+        self.remove.is_empty() && self.keep.is_none()
+    }
+
+    /// Checks if the tag with the given key should be removed. Keys are compared case-insensitively.
+    pub fn should_remove(&self, key: &str) -> bool {
+        let matches = |k: &String| k.eq_ignore_ascii_case(key);
+
+        self.remove.iter().any(matches)
+            || self
+                .keep
+                .as_ref()
+                .is_some_and(|keep| !keep.iter().any(matches))
+    }
+}
+
+/// Lets the user know that a tag is being removed.
+pub fn log_removal(filename: &str, key: &str, cfg: &DefaultValues) {
+    if cfg.execution.dry_run.unwrap_or(false) {
+        log::info!("{filename} :: Remove {key}");
+    } else {
+        log::debug!("{filename} :: Remove {key}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests that the right tags are marked for removal.
+    fn test_should_remove() {
+        let mut cfg = DefaultValues::new();
+        assert!(Removals::new(FileTypes::Flac, &cfg).is_empty());
+
+        cfg.remove = Some(vec!["encoder".to_string(), "COMMENT".to_string()]);
+        let flac = Removals::new(FileTypes::Flac, &cfg);
+        assert!(!flac.is_empty());
+        assert!(flac.should_remove("ENCODER"));
+        assert!(flac.should_remove("comment"));
+        assert!(!flac.should_remove("ARTIST"));
+
+        let mp3 = Removals::new(FileTypes::MP3, &cfg);
+        assert!(mp3.should_remove("TSSE"));
+        assert!(mp3.should_remove("COMM"));
+        assert!(!mp3.should_remove("TPE1"));
+
+        cfg.remove = None;
+        cfg.keep = Some(vec!["ARTIST".to_string(), "TITLE".to_string()]);
+        let mp3 = Removals::new(FileTypes::MP3, &cfg);
+        assert!(!mp3.should_remove("TPE1"));
+        assert!(!mp3.should_remove("TIT2"));
+        assert!(mp3.should_remove("TALB"));
+        assert!(mp3.should_remove("TXXX:CATALOGNUMBER"));
+
+        // Keep takes precedence over clear-all
+        cfg.clear_all = Some(true);
+        let m4a = Removals::new(FileTypes::M4A, &cfg);
+        assert!(!m4a.should_remove("©ART"));
+        assert!(m4a.should_remove("©alb"));
+
+        cfg.keep = None;
+        let ape = Removals::new(FileTypes::Ape, &cfg);
+        assert!(ape.should_remove("ARTIST"));
+        assert!(ape.should_remove("Cover Art (Front)"));
+    }
+}
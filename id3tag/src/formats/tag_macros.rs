@@ -4,8 +4,9 @@
 ///
 /// This macro checks if the command line argument exists in `$cli`.
 /// If it does, it inserts the value into `$nt` using the field specified by `$name` in `$t`.
-/// If the command line argument does not exist, it checks if the "config-file" argument exists in `$cli`.
-/// If it does, it inserts the value from `$cfg` using the field specified by `$name` in `$t`.
+/// Arguments given more than once, eg. `--track-artist Simon --track-artist Garfunkel`, give a multi-value field.
+/// If the command line argument does not exist, it inserts the value from `$cfg` using the field specified by `$name` in `$t`,
+/// if there is one. These come from the config file and any `.id3tag.toml` files in the music file's folders.
 ///
 /// # Arguments
 ///
@@ -31,28 +32,24 @@ macro_rules! tag {
         if $cli.contains_id($arg) {
             $nt.insert(
                 $t.$name,
-                $cli.get_one::<String>($arg)
-                    .unwrap_or(&String::new())
-                    .to_string(),
+                $crate::formats::tags::join_values(
+                    $cli.get_many::<String>($arg).unwrap_or_default(),
+                ),
             );
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.$name {
-                $nt.insert($t.$name, val.to_string());
-            }
+        } else if let Some(val) = &$cfg.$name {
+            $nt.insert($t.$name, val.to_string());
         }
     };
     ($cli:ident, $cfg:expr, $nt:ident, $t:ident, $arg:expr, $name:ident, true) => {
         if $cli.contains_id($arg) {
             $nt.insert(
                 $t.$name.clone(),
-                $cli.get_one::<String>($arg)
-                    .unwrap_or(&String::new())
-                    .to_string(),
+                $crate::formats::tags::join_values(
+                    $cli.get_many::<String>($arg).unwrap_or_default(),
+                ),
             );
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.$name {
-                $nt.insert($t.$name.clone(), val.to_string());
-            }
+        } else if let Some(val) = &$cfg.$name {
+            $nt.insert($t.$name.clone(), val.to_string());
         }
     };
 }
@@ -82,10 +79,8 @@ macro_rules! pic {
                     .unwrap_or(&String::new())
                     .to_string(),
             );
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.picture_front {
-                $nt.insert($t.picture_front, val.to_string());
-            }
+        } else if let Some(val) = &$cfg.picture_front {
+            $nt.insert($t.picture_front, val.to_string());
         }
     };
     ($cli:ident, $cfg:expr, $nt:ident, $t:ident, back) => {
@@ -97,10 +92,8 @@ macro_rules! pic {
                     .unwrap_or(&String::new())
                     .to_string(),
             );
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.picture_back {
-                $nt.insert($t.picture_back, val.to_string());
-            }
+        } else if let Some(val) = &$cfg.picture_back {
+            $nt.insert($t.picture_back, val.to_string());
         }
     };
 }
@@ -110,7 +103,7 @@ macro_rules! pic {
 /// This macro is used to handle the logic for setting the track artist and album artist values in the ID3 tag.
 /// It takes in the command line interface (`$cli`), configuration (`$cfg`), ID3 tag (`$nt`), and track (`$t`) as input.
 /// If the command line interface contains the "track-album-artist" option, it retrieves the value and sets it as the track artist and album artist in the ID3 tag.
-/// If the "config-file" option is present in the command line interface, it checks if the `track_album_artist` value is specified in the configuration.
+/// Otherwise, it checks if the `track_album_artist` value is specified in the configuration.
 /// If it is, it sets the track artist and album artist in the ID3 tag to the specified value.
 ///
 /// # Arguments
@@ -123,18 +116,16 @@ macro_rules! pic {
 macro_rules! track_album_artist {
     ($cli:ident, $cfg:expr, $nt:ident, $t:ident) => {
         if $cli.contains_id("track-album-artist") {
-            let taa = $cli
-                .get_one::<String>("track-album-artist")
-                .unwrap_or(&String::new())
-                .to_string();
+            let taa = $crate::formats::tags::join_values(
+                $cli.get_many::<String>("track-album-artist")
+                    .unwrap_or_default(),
+            );
+            $nt.insert($t.track_artist.clone(), taa.clone());
+            $nt.insert($t.album_artist.clone(), taa);
+        } else if let Some(val) = &$cfg.track_album_artist {
+            let taa = val.to_string();
             $nt.insert($t.track_artist.clone(), taa.clone());
             $nt.insert($t.album_artist.clone(), taa);
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.track_album_artist {
-                let taa = val.to_string();
-                $nt.insert($t.track_artist.clone(), taa.clone());
-                $nt.insert($t.album_artist.clone(), taa);
-            }
         }
     };
 }
@@ -142,7 +133,7 @@ macro_rules! track_album_artist {
 /// Count the number of discs
 ///
 /// This macro is used to handle the logic for inserting disc number and disc count tags into the ID3 tag.
-/// It checks if the "disc-number-count" value is provided through the command line or if the disc count is enabled in the config file.
+/// It checks if the "disc-number-count" value is provided through the command line or if the disc count is enabled in the configuration.
 /// If either condition is true, it retrieves the disc number and disc count from the given file name and inserts them into the ID3 tag.
 ///
 /// # Arguments
@@ -156,7 +147,7 @@ macro_rules! track_album_artist {
 macro_rules! disc_number_count {
     ($cli:ident, $cfg:expr, $nt:ident, $t:ident, $fname:ident) => {
         if $cli.value_source("disc-number-count") == Some(clap::parser::ValueSource::CommandLine)
-            || ($cli.contains_id("config-file") && $cfg.disc_count.unwrap_or(false))
+            || $cfg.disc_count.unwrap_or(false)
         {
             let disc_num = disc_number($fname)?;
             let disc_count = disc_count($fname)?;
@@ -170,7 +161,7 @@ macro_rules! disc_number_count {
 ///
 /// Macro to insert the total track number count into the given tag.
 ///
-/// This macro checks if the command line argument `track-count` is present or if the `config-file` option is enabled and the `track_count` configuration is set to `true`.
+/// This macro checks if the command line argument `track-count` is present or if the `track_count` configuration is set to `true`.
 /// If either condition is true, it counts the number of files using the `common::count_files` function and inserts the count into the specified tag.
 ///
 /// # Arguments
@@ -186,9 +177,7 @@ macro_rules! disc_number_count {
 /// ```
 /// macro_rules! track_number_count {
 ///     ($cli:ident, $cfg:ident, $nt:ident, $t:ident, $fname:ident) => {
-///         if $cli.contains_id("track-count")
-///             || ($cli.contains_id("config-file") && $cfg.track_count.unwrap_or(false))
-///         {
+///         if $cli.contains_id("track-count") || $cfg.track_count.unwrap_or(false) {
 ///             let file_count = common::count_files($fname)?;
 ///             $nt.insert($t.track_number_total, file_count);
 ///         }
@@ -198,9 +187,7 @@ macro_rules! disc_number_count {
 #[macro_export]
 macro_rules! track_number_count {
     ($cli:ident, $cfg:expr, $nt:ident, $t:ident, $fname:ident) => {
-        if $cli.contains_id("track-count")
-            || ($cli.contains_id("config-file") && $cfg.track_count.unwrap_or(false))
-        {
+        if $cli.contains_id("track-count") || $cfg.track_count.unwrap_or(false) {
             let file_count = common::count_files($fname)?;
             $nt.insert($t.track_number_total, file_count);
         }
@@ -220,7 +207,7 @@ macro_rules! track_number_count {
 /// - `$t:ident` - The name of the variable that contains the existing tags `HashSet`
 ///
 /// If the command line interface contains the "track-genre-number" identifier, it inserts the track genre number and name into the hashmap.
-/// If the command line interface does not contain the "track-genre-number" identifier but the configuration file contains the "config-file" identifier, it inserts the track genre number and name from the configuration file into the hashmap.
+/// If the command line interface does not contain the "track-genre-number" identifier, it inserts the track genre number and name from the configuration into the hashmap, if there is one.
 ///
 /// # Example
 ///
@@ -241,10 +228,8 @@ macro_rules! track_genre_num {
                 $t.track_genre.clone(),
                 genre_name(*$cli.get_one::<u16>("track-genre-number").unwrap_or(&0))?,
             );
-        } else if $cli.contains_id("config-file") {
-            if let Some(val) = &$cfg.track_genre_number {
-                $nt.insert($t.track_genre.clone(), genre_name(*val)?);
-            }
+        } else if let Some(val) = &$cfg.track_genre_number {
+            $nt.insert($t.track_genre.clone(), genre_name(*val)?);
         }
     };
 }
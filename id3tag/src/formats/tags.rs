@@ -1,5 +1,6 @@
 //! Contains the scaffoldig for processing tags in a generic way
 
+use crate::default_values::DefaultValues;
 use common::FileTypes;
 use std::collections::HashMap;
 
@@ -26,9 +27,20 @@ pub struct TagNames {
     pub track_comments: String,
     pub picture_front: String,
     pub picture_back: String,
+    pub isrc: String,
+    pub iswc: String,
+    pub barcode: String,
+    pub catalog_number: String,
+    pub musicbrainz_track_id: String,
+    pub musicbrainz_album_id: String,
+    pub musicbrainz_artist_id: String,
+    pub musicbrainz_album_artist_id: String,
+    pub musicbrainz_release_group_id: String,
+    pub musicbrainz_disc_id: String,
 }
 
 /// Gets the tag names based on the file type
+#[allow(clippy::too_many_lines)]
 pub fn get_tag_names(file_type: FileTypes) -> TagNames {
     match file_type {
         // May need to revisit this, so keeping it for now.
@@ -53,7 +65,13 @@ pub fn get_tag_names(file_type: FileTypes) -> TagNames {
         //     picture_front: "PICTUREFRONT".to_string(),
         //     picture_back: "PICTUREBACK".to_string(),
         // },
-        FileTypes::Ape | FileTypes::Flac => TagNames {
+        FileTypes::Ape
+        | FileTypes::WavPack
+        | FileTypes::Musepack
+        | FileTypes::Tak
+        | FileTypes::Flac
+        | FileTypes::Ogg
+        | FileTypes::Opus => TagNames {
             album_artist: "ALBUMARTIST".to_string(),
             album_artist_sort: "ALBUMARTISTSORT".to_string(),
             album_title: "ALBUM".to_string(),
@@ -73,28 +91,50 @@ pub fn get_tag_names(file_type: FileTypes) -> TagNames {
             track_comments: "DESCRIPTION".to_string(),
             picture_front: "PICTUREFRONT".to_string(),
             picture_back: "PICTUREBACK".to_string(),
+            isrc: "ISRC".to_string(),
+            iswc: "ISWC".to_string(),
+            barcode: "BARCODE".to_string(),
+            catalog_number: "CATALOGNUMBER".to_string(),
+            musicbrainz_track_id: "MUSICBRAINZ_TRACKID".to_string(),
+            musicbrainz_album_id: "MUSICBRAINZ_ALBUMID".to_string(),
+            musicbrainz_artist_id: "MUSICBRAINZ_ARTISTID".to_string(),
+            musicbrainz_album_artist_id: "MUSICBRAINZ_ALBUMARTISTID".to_string(),
+            musicbrainz_release_group_id: "MUSICBRAINZ_RELEASEGROUPID".to_string(),
+            musicbrainz_disc_id: "MUSICBRAINZ_DISCID".to_string(),
         },
-        FileTypes::MP3 | FileTypes::Dsf => TagNames {
-            album_artist: "TPE2".to_string(),
-            album_artist_sort: "TSO2".to_string(),
-            album_title: "TALB".to_string(),
-            album_title_sort: "TSOA".to_string(),
-            disc_number: "TPOS".to_string(),
-            disc_number_total: "TPOS-T".to_string(),
-            track_artist: "TPE1".to_string(),
-            track_artist_sort: "TSOP".to_string(),
-            track_title: "TIT2".to_string(),
-            track_title_sort: "TSOT".to_string(),
-            track_number: "TRCK".to_string(),
-            track_number_total: "TRCK-T".to_string(),
-            track_genre: "TCON".to_string(),
-            track_composer: "TCOM".to_string(),
-            track_composer_sort: "TSOC".to_string(),
-            track_date: "TDRC".to_string(),
-            track_comments: "COMM".to_string(),
-            picture_front: "APIC-F".to_string(),
-            picture_back: "APIC-B".to_string(),
-        },
+        FileTypes::MP3 | FileTypes::Dsf | FileTypes::Dff | FileTypes::Wav | FileTypes::Aiff => {
+            TagNames {
+                album_artist: "TPE2".to_string(),
+                album_artist_sort: "TSO2".to_string(),
+                album_title: "TALB".to_string(),
+                album_title_sort: "TSOA".to_string(),
+                disc_number: "TPOS".to_string(),
+                disc_number_total: "TPOS-T".to_string(),
+                track_artist: "TPE1".to_string(),
+                track_artist_sort: "TSOP".to_string(),
+                track_title: "TIT2".to_string(),
+                track_title_sort: "TSOT".to_string(),
+                track_number: "TRCK".to_string(),
+                track_number_total: "TRCK-T".to_string(),
+                track_genre: "TCON".to_string(),
+                track_composer: "TCOM".to_string(),
+                track_composer_sort: "TSOC".to_string(),
+                track_date: "TDRC".to_string(),
+                track_comments: "COMM".to_string(),
+                picture_front: "APIC-F".to_string(),
+                picture_back: "APIC-B".to_string(),
+                isrc: "TSRC".to_string(),
+                iswc: "TXXX:ISWC".to_string(),
+                barcode: "TXXX:BARCODE".to_string(),
+                catalog_number: "TXXX:CATALOGNUMBER".to_string(),
+                musicbrainz_track_id: "UFID:http://musicbrainz.org".to_string(),
+                musicbrainz_album_id: "TXXX:MusicBrainz Album Id".to_string(),
+                musicbrainz_artist_id: "TXXX:MusicBrainz Artist Id".to_string(),
+                musicbrainz_album_artist_id: "TXXX:MusicBrainz Album Artist Id".to_string(),
+                musicbrainz_release_group_id: "TXXX:MusicBrainz Release Group Id".to_string(),
+                musicbrainz_disc_id: "TXXX:MusicBrainz Disc Id".to_string(),
+            }
+        }
         FileTypes::M4A => TagNames {
             album_artist: "aART".to_string(),
             album_artist_sort: "soaa".to_string(),
@@ -115,6 +155,18 @@ pub fn get_tag_names(file_type: FileTypes) -> TagNames {
             track_comments: "©cmt".to_string(),
             picture_front: "covr-f".to_string(),
             picture_back: "covr-b".to_string(),
+            isrc: "----:com.apple.iTunes:ISRC".to_string(),
+            iswc: "----:com.apple.iTunes:ISWC".to_string(),
+            barcode: "----:com.apple.iTunes:BARCODE".to_string(),
+            catalog_number: "----:com.apple.iTunes:CATALOGNUMBER".to_string(),
+            musicbrainz_track_id: "----:com.apple.iTunes:MusicBrainz Track Id".to_string(),
+            musicbrainz_album_id: "----:com.apple.iTunes:MusicBrainz Album Id".to_string(),
+            musicbrainz_artist_id: "----:com.apple.iTunes:MusicBrainz Artist Id".to_string(),
+            musicbrainz_album_artist_id: "----:com.apple.iTunes:MusicBrainz Album Artist Id"
+                .to_string(),
+            musicbrainz_release_group_id: "----:com.apple.iTunes:MusicBrainz Release Group Id"
+                .to_string(),
+            musicbrainz_disc_id: "----:com.apple.iTunes:MusicBrainz Disc Id".to_string(),
         },
         FileTypes::Unknown => TagNames {
             album_artist: String::new(),
@@ -136,6 +188,16 @@ pub fn get_tag_names(file_type: FileTypes) -> TagNames {
             track_comments: String::new(),
             picture_front: String::new(),
             picture_back: String::new(),
+            isrc: String::new(),
+            iswc: String::new(),
+            barcode: String::new(),
+            catalog_number: String::new(),
+            musicbrainz_track_id: String::new(),
+            musicbrainz_album_id: String::new(),
+            musicbrainz_artist_id: String::new(),
+            musicbrainz_album_artist_id: String::new(),
+            musicbrainz_release_group_id: String::new(),
+            musicbrainz_disc_id: String::new(),
         },
     }
 }
@@ -235,11 +297,355 @@ pub fn option_to_tag(file_type: FileTypes) -> HashMap<String, String> {
     tm
 }
 
-#[cfg(test)]
+impl TagNames {
+    /// Returns the tag names as a list, in the order the fields are declared.
+    /// Used to look up the same field across file types.
+    fn as_list(&self) -> [&str; 29] {
+        [
+            &self.album_artist,
+            &self.album_artist_sort,
+            &self.album_title,
+            &self.album_title_sort,
+            &self.disc_number,
+            &self.disc_number_total,
+            &self.track_artist,
+            &self.track_artist_sort,
+            &self.track_title,
+            &self.track_title_sort,
+            &self.track_number,
+            &self.track_number_total,
+            &self.track_genre,
+            &self.track_composer,
+            &self.track_composer_sort,
+            &self.track_date,
+            &self.track_comments,
+            &self.picture_front,
+            &self.picture_back,
+            &self.isrc,
+            &self.iswc,
+            &self.barcode,
+            &self.catalog_number,
+            &self.musicbrainz_track_id,
+            &self.musicbrainz_album_id,
+            &self.musicbrainz_artist_id,
+            &self.musicbrainz_album_artist_id,
+            &self.musicbrainz_release_group_id,
+            &self.musicbrainz_disc_id,
+        ]
+    }
+}
+
+/// ID3v2.4 text frames that can be set directly, eg. `--set TKEY=Am`.
+const ID3_TEXT_FRAMES: [&str; 38] = [
+    "TALB", "TBPM", "TCMP", "TCOM", "TCON", "TCOP", "TDEN", "TDLY", "TDOR", "TDRC", "TDRL", "TDTG",
+    "TENC", "TEXT", "TFLT", "TIT1", "TIT2", "TIT3", "TKEY", "TLAN", "TLEN", "TMED", "TMOO", "TOAL",
+    "TOFN", "TOLY", "TOPE", "TOWN", "TPE1", "TPE2", "TPE3", "TPE4", "TPOS", "TPUB", "TRCK", "TSRC",
+    "TSSE", "TSST",
+];
+
+/// Generic field names that have a native ID3 frame.
+const ID3_NATIVE_FRAMES: [(&str, &str); 24] = [
+    ("BPM", "TBPM"),
+    ("COMMENT", "COMM"),
+    ("COMPILATION", "TCMP"),
+    ("CONDUCTOR", "TPE3"),
+    ("COPYRIGHT", "TCOP"),
+    ("DISCSUBTITLE", "TSST"),
+    ("ENCODEDBY", "TENC"),
+    ("ENCODER", "TSSE"),
+    ("ENCODERSETTINGS", "TSSE"),
+    ("GROUPING", "TIT1"),
+    ("ISRC", "TSRC"),
+    ("KEY", "TKEY"),
+    ("LABEL", "TPUB"),
+    ("LANGUAGE", "TLAN"),
+    ("LYRICIST", "TEXT"),
+    ("MEDIA", "TMED"),
+    ("MOOD", "TMOO"),
+    ("ORGANIZATION", "TPUB"),
+    ("ORIGINALDATE", "TDOR"),
+    ("PUBLISHER", "TPUB"),
+    ("REMIXER", "TPE4"),
+    ("SUBTITLE", "TIT3"),
+    ("URL", "WXXX"),
+    ("MUSICBRAINZ_TRACKID", "UFID:http://musicbrainz.org"),
+];
+
+/// Generic field names that are stored under a different description in ID3 `TXXX` frames and
+/// MP4 freeform atoms. These follow the conventions used by Picard.
+const CUSTOM_DESCRIPTIONS: [(&str, &str); 13] = [
+    ("ACOUSTID_ID", "Acoustid Id"),
+    ("MUSICBRAINZ_ALBUMARTISTID", "MusicBrainz Album Artist Id"),
+    ("MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id"),
+    ("MUSICBRAINZ_ALBUMSTATUS", "MusicBrainz Album Status"),
+    ("MUSICBRAINZ_ALBUMTYPE", "MusicBrainz Album Type"),
+    ("MUSICBRAINZ_ARTISTID", "MusicBrainz Artist Id"),
+    ("MUSICBRAINZ_DISCID", "MusicBrainz Disc Id"),
+    ("MUSICBRAINZ_RELEASEGROUPID", "MusicBrainz Release Group Id"),
+    ("MUSICBRAINZ_RELEASETRACKID", "MusicBrainz Release Track Id"),
+    ("MUSICBRAINZ_TRACKID", "MusicBrainz Track Id"),
+    ("MUSICBRAINZ_WORKID", "MusicBrainz Work Id"),
+    ("RELEASECOUNTRY", "MusicBrainz Album Release Country"),
+    ("RELEASETYPE", "MusicBrainz Album Type"),
+];
+
+/// Generic field names that have a native MP4 atom.
+const MP4_NATIVE_ATOMS: [(&str, &str); 12] = [
+    ("BPM", "tmpo"),
+    ("COMMENT", "©cmt"),
+    ("COMPILATION", "cpil"),
+    ("COPYRIGHT", "cprt"),
+    ("ENCODER", "©too"),
+    ("GROUPING", "©grp"),
+    ("LYRICS", "©lyr"),
+    ("MEDIATYPE", "stik"),
+    ("MOVEMENT", "©mvi"),
+    ("MOVEMENTNAME", "©mvn"),
+    ("MOVEMENTTOTAL", "©mvc"),
+    ("WORK", "©wrk"),
+];
+
+/// Generic field names that are stored in MP4 freeform atoms with a lower-case name, as written by foobar2000 and
+/// iTunes-compatible taggers.
+const MP4_FREEFORM_NAMES: [(&str, &str); 4] = [
+    ("REPLAYGAIN_ALBUM_GAIN", "replaygain_album_gain"),
+    ("REPLAYGAIN_ALBUM_PEAK", "replaygain_album_peak"),
+    ("REPLAYGAIN_TRACK_GAIN", "replaygain_track_gain"),
+    ("REPLAYGAIN_TRACK_PEAK", "replaygain_track_peak"),
+];
+
+/// Maps a generic (Vorbis comment style) field name, as used by `--set KEY=VALUE`, to the key used
+/// for the file type.
+///
+/// Fields known to `TagNames` map to the same field for the file type, eg. `ARTIST` --> `TPE1` (MP3).
+/// Anything else becomes a custom field:
+///
+/// - FLAC, Ogg Vorbis and Opus: an upper-case Vorbis comment, eg. `LABEL`
+/// - APE: an item with the name as given
+/// - MP3, DSF, DSDIFF, WAV and AIFF: a native frame (`ISRC` --> `TSRC`) if there is one, otherwise `TXXX:<description>`
+/// - MP4: a native atom (`COPYRIGHT` --> `cprt`) if there is one, otherwise a freeform atom, eg. `----:com.apple.iTunes:LABEL`
 ///
+/// Keys already in the native form (`TXXX:...`, `TKEY`, `----:mean:name`, `stik`) are passed through unchanged.
+pub fn map_key(file_type: FileTypes, key: &str) -> String {
+    let key = key.trim();
+    let upper = key.to_uppercase();
+
+    // Check if this is one of the fields we know about
+    let generic = get_tag_names(FileTypes::Flac);
+    let specific = get_tag_names(file_type);
+    if let Some(idx) = generic.as_list().iter().position(|n| *n == upper) {
+        return specific.as_list()[idx].to_string();
+    }
+
+    let description = CUSTOM_DESCRIPTIONS
+        .iter()
+        .find(|(k, _)| *k == upper)
+        .map_or(upper.as_str(), |(_, d)| d);
+
+    match file_type {
+        FileTypes::Ape | FileTypes::WavPack | FileTypes::Musepack | FileTypes::Tak => {
+            key.to_string()
+        }
+        FileTypes::Flac | FileTypes::Ogg | FileTypes::Opus | FileTypes::Unknown => upper,
+        FileTypes::MP3 | FileTypes::Dsf | FileTypes::Dff | FileTypes::Wav | FileTypes::Aiff => {
+            if upper.starts_with("TXXX:") || upper.starts_with("UFID:") {
+                format!("{}{}", &upper[..5], key.get(5..).unwrap_or_default())
+            } else if ID3_TEXT_FRAMES.contains(&upper.as_str()) {
+                upper
+            } else if let Some((_, frame)) = ID3_NATIVE_FRAMES.iter().find(|(k, _)| *k == upper) {
+                (*frame).to_string()
+            } else {
+                format!("TXXX:{description}")
+            }
+        }
+        FileTypes::M4A => {
+            if key.starts_with("----:") || MP4_NATIVE_ATOMS.iter().any(|(_, a)| *a == key) {
+                key.to_string()
+            } else if let Some((_, atom)) = MP4_NATIVE_ATOMS.iter().find(|(k, _)| *k == upper) {
+                (*atom).to_string()
+            } else if let Some((_, name)) = MP4_FREEFORM_NAMES.iter().find(|(k, _)| *k == upper) {
+                format!("----:com.apple.iTunes:{name}")
+            } else {
+                format!("----:com.apple.iTunes:{description}")
+            }
+        }
+    }
+}
+
+/// Maps a key read from a file of the given type back to the generic (Vorbis comment style) field name.
+/// This is the reverse of `map_key`, and is used to copy tags between file types.
+///
+/// Returns `None` for pictures and for keys that don't have a generic name, eg. MP4 atoms without a known meaning.
+pub fn generic_key(file_type: FileTypes, key: &str) -> Option<String> {
+    let generic = get_tag_names(FileTypes::Flac);
+    let specific = get_tag_names(file_type);
+    if let Some(idx) = specific.as_list().iter().position(|n| *n == key) {
+        let name = generic.as_list()[idx];
+        return (!name.starts_with("PICTURE")).then(|| name.to_string());
+    }
+
+    // The generic name for a `TXXX` description or freeform atom name
+    let from_description = |description: &str| {
+        CUSTOM_DESCRIPTIONS
+            .iter()
+            .find(|(_, d)| d.eq_ignore_ascii_case(description))
+            .map_or_else(|| description.to_uppercase(), |(k, _)| (*k).to_string())
+    };
+
+    match file_type {
+        FileTypes::Ape
+        | FileTypes::WavPack
+        | FileTypes::Musepack
+        | FileTypes::Tak
+        | FileTypes::Flac
+        | FileTypes::Ogg
+        | FileTypes::Opus => {
+            let upper = key.to_uppercase();
+            (!upper.starts_with("COVER ART") && !upper.starts_with("PICTURE")).then_some(upper)
+        }
+        FileTypes::MP3 | FileTypes::Dsf | FileTypes::Dff | FileTypes::Wav | FileTypes::Aiff => {
+            if let Some(description) = key.strip_prefix("TXXX:") {
+                Some(from_description(description))
+            } else if let Some((generic, _)) = ID3_NATIVE_FRAMES.iter().find(|(_, f)| *f == key) {
+                Some((*generic).to_string())
+            } else if ID3_TEXT_FRAMES.contains(&key) {
+                Some(key.to_string())
+            } else {
+                None
+            }
+        }
+        FileTypes::M4A => {
+            if let Some((_, name)) = key.strip_prefix("----:").and_then(|k| k.split_once(':')) {
+                Some(from_description(name))
+            } else {
+                MP4_NATIVE_ATOMS
+                    .iter()
+                    .find(|(_, a)| *a == key)
+                    .map(|(generic, _)| (*generic).to_string())
+            }
+        }
+        FileTypes::Unknown => None,
+    }
+}
+
+/// Separates the values of fields with more than one value, eg. two artists. This is how ID3 v2.4 text frames
+/// and APE v2 items hold several values, so the value can be written to these as-is. FLAC and MP4 files
+/// get one comment or data item per value.
+pub const VALUE_SEPARATOR: char = '\0';
+
+/// Splits a value into the values of a multi-value field. Each value is trimmed.
+pub fn split_values(value: &str) -> impl Iterator<Item = &str> {
+    value.split(VALUE_SEPARATOR).map(str::trim)
+}
+
+/// Joins several values into the value of a multi-value field.
+pub fn join_values<S: AsRef<str>>(values: impl IntoIterator<Item = S>) -> String {
+    values
+        .into_iter()
+        .map(|v| v.as_ref().to_string())
+        .collect::<Vec<String>>()
+        .join(&VALUE_SEPARATOR.to_string())
+}
+
+/// Shows the values of a multi-value field separated by "; ", eg. in the log.
+pub fn display_value(value: &str) -> String {
+    value.replace(VALUE_SEPARATOR, "; ")
+}
+
+/// Logs a new value being set. Only logged as debug if the changed values are shown in the dry-run preview instead.
+pub fn log_new_value(filename: &str, key: &str, value: &str, cfg: &DefaultValues) {
+    if cfg.shows_preview() {
+        log::debug!("{filename} :: New {key} = {}", display_value(value));
+    } else {
+        log::info!("{filename} :: New {key} = {}", display_value(value.trim()));
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    /// Tests that keys read from a file map back to the generic names, and that these map to the same keys again.
+    fn test_generic_key() {
+        assert_eq!(
+            generic_key(FileTypes::MP3, "TPE1"),
+            Some("ARTIST".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::MP3, "TRCK-T"),
+            Some("TRACKTOTAL".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::M4A, "©nam"),
+            Some("TITLE".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::Flac, "label"),
+            Some("LABEL".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::MP3, "TSRC"),
+            Some("ISRC".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::MP3, "TXXX:MusicBrainz Album Id"),
+            Some("MUSICBRAINZ_ALBUMID".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::M4A, "----:com.apple.iTunes:LABEL"),
+            Some("LABEL".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::M4A, "cprt"),
+            Some("COPYRIGHT".to_string())
+        );
+        assert_eq!(
+            generic_key(FileTypes::M4A, "©mvn"),
+            Some("MOVEMENTNAME".to_string())
+        );
+        assert_eq!(
+            generic_key(
+                FileTypes::M4A,
+                "----:com.apple.iTunes:replaygain_track_gain"
+            ),
+            Some("REPLAYGAIN_TRACK_GAIN".to_string())
+        );
+
+        // Pictures and unknown keys
+        assert_eq!(generic_key(FileTypes::MP3, "APIC-F"), None);
+        assert_eq!(generic_key(FileTypes::M4A, "covr-f"), None);
+        assert_eq!(generic_key(FileTypes::Ape, "Cover Art (Front)"), None);
+        assert_eq!(generic_key(FileTypes::M4A, "xyzw"), None);
+
+        // Round trip
+        for key in [
+            "TPE2",
+            "TXXX:CATALOGNUMBER",
+            "UFID:http://musicbrainz.org",
+            "TKEY",
+        ] {
+            let generic = generic_key(FileTypes::MP3, key).unwrap();
+            assert_eq!(map_key(FileTypes::MP3, &generic), key);
+        }
+    }
+
+    #[test]
+    /// Tests that multi-value fields are split and joined on the separator.
+    fn test_split_join_values() {
+        let value = join_values(["Simon", "Garfunkel"]);
+        assert_eq!(value, "Simon\0Garfunkel");
+        assert_eq!(
+            split_values(&value).collect::<Vec<&str>>(),
+            vec!["Simon", "Garfunkel"]
+        );
+        assert_eq!(
+            split_values(" Madonna ").collect::<Vec<&str>>(),
+            vec!["Madonna"]
+        );
+        assert_eq!(display_value(&value), "Simon; Garfunkel");
+    }
+
     #[test]
     /// Test that the right tag names are being returned.
     fn test_get_tag_names() {
@@ -759,4 +1165,58 @@ mod tests {
         );
         assert_eq!(mp4_tag.get("%td").unwrap().clone(), "©day".to_string());
     }
+
+    #[test]
+    /// Test that generic field names get mapped to the right keys for each file type.
+    fn test_map_key() {
+        // Known fields map to the same field for the file type
+        assert_eq!(map_key(FileTypes::Flac, "artist"), "ARTIST".to_string());
+        assert_eq!(map_key(FileTypes::MP3, "ARTIST"), "TPE1".to_string());
+        assert_eq!(map_key(FileTypes::Dsf, "TrackNumber"), "TRCK".to_string());
+        assert_eq!(map_key(FileTypes::M4A, "ALBUMARTIST"), "aART".to_string());
+        assert_eq!(map_key(FileTypes::Ape, "TITLE"), "TITLE".to_string());
+
+        // Custom fields
+        assert_eq!(map_key(FileTypes::Flac, "label"), "LABEL".to_string());
+        assert_eq!(map_key(FileTypes::Ape, "Label"), "Label".to_string());
+        assert_eq!(map_key(FileTypes::MP3, "ISRC"), "TSRC".to_string());
+        assert_eq!(map_key(FileTypes::MP3, "LABEL"), "TPUB".to_string());
+        assert_eq!(map_key(FileTypes::MP3, "TKEY"), "TKEY".to_string());
+        assert_eq!(
+            map_key(FileTypes::MP3, "CATALOGNUMBER"),
+            "TXXX:CATALOGNUMBER".to_string()
+        );
+        assert_eq!(
+            map_key(FileTypes::MP3, "MUSICBRAINZ_ALBUMID"),
+            "TXXX:MusicBrainz Album Id".to_string()
+        );
+        assert_eq!(
+            map_key(FileTypes::MP3, "MUSICBRAINZ_TRACKID"),
+            "UFID:http://musicbrainz.org".to_string()
+        );
+        assert_eq!(
+            map_key(FileTypes::MP3, "txxx:My Field"),
+            "TXXX:My Field".to_string()
+        );
+        assert_eq!(map_key(FileTypes::M4A, "COPYRIGHT"), "cprt".to_string());
+        assert_eq!(
+            map_key(FileTypes::M4A, "ISRC"),
+            "----:com.apple.iTunes:ISRC".to_string()
+        );
+        assert_eq!(
+            map_key(FileTypes::M4A, "MUSICBRAINZ_TRACKID"),
+            "----:com.apple.iTunes:MusicBrainz Track Id".to_string()
+        );
+        assert_eq!(
+            map_key(FileTypes::M4A, "----:com.example:Thing"),
+            "----:com.example:Thing".to_string()
+        );
+        assert_eq!(map_key(FileTypes::M4A, "compilation"), "cpil".to_string());
+        assert_eq!(map_key(FileTypes::M4A, "stik"), "stik".to_string());
+        assert_eq!(map_key(FileTypes::M4A, "©wrk"), "©wrk".to_string());
+        assert_eq!(
+            map_key(FileTypes::M4A, "ReplayGain_Track_Gain"),
+            "----:com.apple.iTunes:replaygain_track_gain".to_string()
+        );
+    }
 }
@@ -0,0 +1,399 @@
+//! Transformations applied to the existing values of tags, such as search-and-replace and case changes.
+
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, str::FromStr};
+
+/// The tags changed by `--case` if no others are given.
+pub const DEFAULT_CASE_FIELDS: [&str; 4] = ["ARTIST", "ALBUMARTIST", "ALBUM", "TITLE"];
+
+/// Words kept in lower case in title case, unless they are the first or last word.
+pub const DEFAULT_SMALL_WORDS: [&str; 20] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "over", "the", "to", "vs", "with",
+];
+
+/// A sed-style substitution, eg. `s/ ft\. / feat. /g`.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    /// The pattern to search for.
+    regex: Regex,
+
+    /// The replacement, using the `regex` crate syntax for capture groups (`${1}`).
+    replacement: String,
+
+    /// Replace all matches rather than just the first one.
+    global: bool,
+}
+
+impl Substitution {
+    /// Parses a sed-style substitution expression in the form `s/pattern/replacement/flags`.
+    ///
+    /// - Any character that isn't alphanumeric, whitespace or a backslash can be used as the delimiter, eg. `s|a/b|c|`.
+    ///   The delimiter can be escaped with a backslash.
+    /// - The pattern uses the [regex](https://docs.rs/regex) crate syntax.
+    /// - Capture groups can be referred to as `\1` or `$1` in the replacement.
+    /// - Supported flags are `g` (replace all matches) and `i` (case insensitive).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the expression isn't in the right form, has unknown flags or the pattern isn't a valid regex.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut chars = expression.chars();
+        if chars.next() != Some('s') {
+            bail!("Invalid substitution \"{expression}\". Expected s/pattern/replacement/");
+        }
+        let delimiter = match chars.next() {
+            Some(d) if !(d.is_alphanumeric() || d.is_whitespace() || d == '\\') => d,
+            _ => bail!("Invalid delimiter in substitution \"{expression}\"."),
+        };
+
+        // Split into pattern, replacement and flags. Escaped delimiters lose their backslash,
+        // everything else is kept as-is for the regex.
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        let mut escaped = false;
+        for c in chars {
+            if escaped {
+                if c != delimiter {
+                    part.push('\\');
+                }
+                part.push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == delimiter {
+                parts.push(std::mem::take(&mut part));
+            } else {
+                part.push(c);
+            }
+        }
+        if escaped {
+            part.push('\\');
+        }
+        parts.push(part);
+
+        if parts.len() != 3 {
+            bail!("Invalid substitution \"{expression}\". Expected s/pattern/replacement/flags");
+        }
+
+        let mut global = false;
+        let mut case_insensitive = false;
+        for flag in parts[2].chars() {
+            match flag {
+                'g' => global = true,
+                'i' => case_insensitive = true,
+                _ => bail!("Unknown flag '{flag}' in substitution \"{expression}\"."),
+            }
+        }
+
+        let regex = RegexBuilder::new(&parts[0])
+            .case_insensitive(case_insensitive)
+            .build()
+            .with_context(|| format!("Invalid pattern in substitution \"{expression}\""))?;
+
+        Ok(Self {
+            regex,
+            replacement: to_regex_replacement(&parts[1]),
+            global,
+        })
+    }
+
+    /// Applies the substitution to the value.
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if self.global {
+            self.regex.replace_all(value, self.replacement.as_str())
+        } else {
+            self.regex.replace(value, self.replacement.as_str())
+        }
+    }
+}
+
+/// The capitalisation to use for a tag value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// Capitalise each word, except for small words such as "of" and "the".
+    Title,
+    /// Capitalise the first word only.
+    Sentence,
+    /// ALL UPPER CASE.
+    Upper,
+    /// all lower case.
+    Lower,
+}
+
+impl FromStr for Case {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "title" => Ok(Self::Title),
+            "sentence" => Ok(Self::Sentence),
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            _ => bail!("Unknown case \"{s}\". Must be one of title, sentence, upper or lower."),
+        }
+    }
+}
+
+/// Changes the capitalisation of tag values.
+#[derive(Debug, Clone)]
+pub struct CaseConverter {
+    /// The capitalisation to use.
+    case: Case,
+
+    /// Words kept in lower case in title case, eg. "of", "the".
+    small_words: Vec<String>,
+
+    /// Words always written as given, eg. "AC/DC", "ABBA". Matched ignoring case.
+    exceptions: Vec<String>,
+}
+
+impl CaseConverter {
+    /// Creates a new converter. Small words are matched ignoring case.
+    pub fn new(case: Case, small_words: &[String], exceptions: &[String]) -> Self {
+        Self {
+            case,
+            small_words: small_words.iter().map(|w| w.to_lowercase()).collect(),
+            exceptions: exceptions.to_vec(),
+        }
+    }
+
+    /// Applies the capitalisation to the value. Whitespace is left as-is.
+    pub fn apply(&self, value: &str) -> String {
+        match self.case {
+            Case::Upper => return value.to_uppercase(),
+            Case::Lower => return value.to_lowercase(),
+            Case::Title | Case::Sentence => (),
+        }
+
+        let word_count = value.split_whitespace().count();
+        let mut res = String::with_capacity(value.len());
+        let mut index = 0;
+        let mut capitalise_next = true;
+
+        for (is_word, part) in split_words(value) {
+            if !is_word {
+                res.push_str(part);
+                continue;
+            }
+
+            let lower = part.to_lowercase();
+            let (leading, core, trailing) = trim_punctuation(part);
+
+            if let Some(exception) = self
+                .exceptions
+                .iter()
+                .find(|e| e.eq_ignore_ascii_case(core))
+            {
+                res.push_str(leading);
+                res.push_str(exception);
+                res.push_str(trailing);
+            } else if self.case == Case::Sentence {
+                if capitalise_next {
+                    res.push_str(&capitalise(&lower));
+                } else {
+                    res.push_str(&lower);
+                }
+            } else if capitalise_next
+                || index == word_count - 1
+                || leading.contains(['(', '['])
+                || !self.small_words.contains(&core.to_lowercase())
+            {
+                res.push_str(&capitalise(&lower));
+            } else {
+                res.push_str(&lower);
+            }
+
+            // Start a new sentence or sub-title after these
+            capitalise_next = part.ends_with(['.', ':', '!', '?']);
+            if self.case == Case::Title {
+                capitalise_next |= part == "-";
+            }
+            index += 1;
+        }
+
+        res
+    }
+}
+
+/// Splits the value into words and the whitespace between them. The boolean is `true` for words.
+fn split_words(value: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_word = false;
+
+    for (i, c) in value.char_indices() {
+        if c.is_whitespace() == in_word {
+            if i > start {
+                parts.push((in_word, &value[start..i]));
+            }
+            start = i;
+            in_word = !in_word;
+        }
+    }
+    if start < value.len() {
+        parts.push((in_word, &value[start..]));
+    }
+
+    parts
+}
+
+/// Splits a word into leading punctuation, the word itself and trailing punctuation, eg. `("(", "Live", ")")`.
+fn trim_punctuation(word: &str) -> (&str, &str, &str) {
+    let is_punctuation = |c: char| !c.is_alphanumeric();
+    let core = word.trim_start_matches(is_punctuation);
+    let leading = &word[..word.len() - core.len()];
+    let trimmed = core.trim_end_matches(is_punctuation);
+    let trailing = &core[trimmed.len()..];
+
+    (leading, trimmed, trailing)
+}
+
+/// Upper-cases the first letter of the word, skipping any leading punctuation, eg. "(live)" -> "(Live)".
+fn capitalise(word: &str) -> String {
+    let mut res = String::with_capacity(word.len());
+    let mut done = false;
+
+    for c in word.chars() {
+        if !done && c.is_alphanumeric() {
+            res.extend(c.to_uppercase());
+            done = true;
+        } else {
+            res.push(c);
+        }
+    }
+
+    res
+}
+
+/// Converts sed-style `\1` references to capture groups into the `${1}` form used by the `regex` crate.
+/// Any other escaped character is used literally.
+fn to_regex_replacement(replacement: &str) -> String {
+    let mut res = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some(d) if d.is_ascii_digit() => {
+                res.push_str("${");
+                res.push(d);
+                res.push('}');
+            }
+            Some('$') => res.push_str("$$"),
+            Some(other) => res.push(other),
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests parsing and applying substitutions.
+    fn test_substitution() {
+        let sub = Substitution::parse(r"s/ ft\. / feat. /").unwrap();
+        assert_eq!(sub.apply("Artist ft. Other"), "Artist feat. Other");
+        assert_eq!(sub.apply("Artist"), "Artist");
+
+        let sub = Substitution::parse(r"s/ \(Remastered \d*\)//").unwrap();
+        assert_eq!(sub.apply("Song (Remastered 2011)"), "Song");
+
+        let sub = Substitution::parse(r"s/(\w+), (\w+)/\2 \1/").unwrap();
+        assert_eq!(sub.apply("Keys, Alicia"), "Alicia Keys");
+
+        let sub = Substitution::parse(r"s/(\w+), (\w+)/$2 $1/").unwrap();
+        assert_eq!(sub.apply("Keys, Alicia"), "Alicia Keys");
+
+        let sub = Substitution::parse("s|a/b|c|g").unwrap();
+        assert_eq!(sub.apply("a/b a/b"), "c c");
+
+        let sub = Substitution::parse(r"s/a\/b/c/").unwrap();
+        assert_eq!(sub.apply("a/b a/b"), "c a/b");
+
+        let sub = Substitution::parse("s/rock/Rock/gi").unwrap();
+        assert_eq!(sub.apply("ROCK and rock"), "Rock and Rock");
+
+        let sub = Substitution::parse(r"s/EMI/\$EMI/").unwrap();
+        assert_eq!(sub.apply("EMI"), "$EMI");
+    }
+
+    #[test]
+    /// Tests that invalid substitutions are rejected.
+    fn test_invalid_substitution() {
+        assert!(Substitution::parse("").is_err());
+        assert!(Substitution::parse("y/a/b/").is_err());
+        assert!(Substitution::parse("s/a/b").is_err());
+        assert!(Substitution::parse("s/a/b/c/").is_err());
+        assert!(Substitution::parse("sxaxbx").is_err());
+        assert!(Substitution::parse("s/a/b/x").is_err());
+        assert!(Substitution::parse("s/(a/b/").is_err());
+    }
+
+    #[test]
+    /// Tests changing the capitalisation of values.
+    fn test_case_converter() {
+        let small_words: Vec<String> = DEFAULT_SMALL_WORDS
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let exceptions = vec!["AC/DC".to_string(), "ABBA".to_string(), "BBC".to_string()];
+
+        let title = CaseConverter::new(Case::Title, &small_words, &exceptions);
+        assert_eq!(
+            title.apply("the dark side of the moon"),
+            "The Dark Side of the Moon"
+        );
+        assert_eq!(title.apply("SONG TITLE"), "Song Title");
+        assert_eq!(
+            title.apply("what are you waiting for"),
+            "What Are You Waiting For"
+        );
+        assert_eq!(title.apply("ac/dc"), "AC/DC");
+        assert_eq!(
+            title.apply("abba gold: greatest hits"),
+            "ABBA Gold: Greatest Hits"
+        );
+        assert_eq!(
+            title.apply("song (live at the bbc)"),
+            "Song (Live at the BBC)"
+        );
+        assert_eq!(title.apply("album - the remixes"), "Album - The Remixes");
+        assert_eq!(title.apply("  two  spaces "), "  Two  Spaces ");
+        assert_eq!(title.apply(""), "");
+
+        let sentence = CaseConverter::new(Case::Sentence, &small_words, &exceptions);
+        assert_eq!(
+            sentence.apply("THE DARK SIDE OF THE MOON"),
+            "The dark side of the moon"
+        );
+        assert_eq!(
+            sentence.apply("back in black by ac/dc"),
+            "Back in black by AC/DC"
+        );
+        assert_eq!(sentence.apply("first. second"), "First. Second");
+
+        let upper = CaseConverter::new(Case::Upper, &small_words, &exceptions);
+        assert_eq!(upper.apply("Motörhead"), "MOTÖRHEAD");
+
+        let lower = CaseConverter::new(Case::Lower, &small_words, &exceptions);
+        assert_eq!(lower.apply("ABBA"), "abba");
+    }
+
+    #[test]
+    /// Tests parsing the case names.
+    fn test_case_from_str() {
+        assert_eq!("title".parse::<Case>().unwrap(), Case::Title);
+        assert_eq!("Sentence".parse::<Case>().unwrap(), Case::Sentence);
+        assert_eq!("UPPER".parse::<Case>().unwrap(), Case::Upper);
+        assert_eq!("lower".parse::<Case>().unwrap(), Case::Lower);
+        assert!("camel".parse::<Case>().is_err());
+    }
+}
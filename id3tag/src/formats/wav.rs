@@ -0,0 +1,253 @@
+//! Contains the functionality to process WAV and AIFF files.
+//! The tags are stored in an `ID3 ` chunk and are handled the same way as for MP3 files. WAV files can also have a
+//! `LIST/INFO` chunk, which is kept in step with the ID3 tag as set by the `riff_info` option.
+
+use crate::default_values::DefaultValues;
+use crate::formats::id3_versions::{self, Id3Version};
+use crate::formats::images::CoverType;
+use crate::formats::mp3;
+use crate::formats::removals::Removals;
+use crate::formats::tags;
+use anyhow::{bail, Context, Result};
+use common::{FileTypes, RiffFile, RIFF_INFO_FRAMES};
+use id3::{ErrorKind, Tag, TagLike};
+use std::{collections::HashMap, str::FromStr};
+
+/// What to do with the `LIST/INFO` chunk in WAV files when the tags are written.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum RiffInfo {
+    /// Copy the values only found in the `INFO` chunk into the ID3 tag, and write the ID3 values to the `INFO` chunk.
+    #[default]
+    Sync,
+    /// Leave the `INFO` chunk as-is.
+    Keep,
+    /// Copy the values only found in the `INFO` chunk into the ID3 tag, and remove the `INFO` chunk.
+    Remove,
+}
+
+impl FromStr for RiffInfo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sync" => Ok(Self::Sync),
+            "keep" => Ok(Self::Keep),
+            "remove" => Ok(Self::Remove),
+            _ => bail!("Unknown RIFF INFO setting \"{s}\". Must be one of sync, keep or remove."),
+        }
+    }
+}
+
+/// Performs the actual processing of WAV and AIFF files.
+///
+/// **Parameters:**
+///
+/// - `filename: &str` -- The name of the file to be processed, eg. "somefile.wav".
+/// - `file_type: FileTypes` -- `FileTypes::Wav` or `FileTypes::Aiff`
+/// - `nt: &HashMap<String, String>` -- A set of new tags in Key/Value form, eg. _key = TPE2_, _value = "The Tragically Hip"_
+/// - `cfg: &DefaultValues` -- A struct containing default values read from a config file and the CLI
+///
+/// **Returns:**
+///
+/// `anyhow::Result<bool>` -- `Ok(true)` if the file was saved successfully or a dry-run was performed.
+pub fn process(
+    filename: &str,
+    file_type: FileTypes,
+    nt: &HashMap<String, String>,
+    cfg: &DefaultValues,
+) -> Result<bool> {
+    log::debug!("Filename: {filename}");
+    let mut processed_ok = false;
+    let riff_info = cfg.riff_info_setting()?;
+
+    let mut tag = read_tag(filename)?;
+    id3_versions::upgrade_frames(&mut tag);
+    let mut riff = RiffFile::read_from_path(filename)?;
+    let has_info = file_type == FileTypes::Wav && riff_info != RiffInfo::Keep;
+
+    // Don't lose the values that are only in the INFO chunk
+    if has_info {
+        copy_info_to_tag(&riff, &mut tag);
+    }
+
+    // Remove existing tags if asked to
+    let removals = Removals::new(file_type, cfg);
+    if !removals.is_empty() {
+        mp3::remove_tags(filename, &mut tag, &removals, cfg);
+    }
+
+    // Set the new tags
+    mp3::set_values(filename, &mut tag, nt, cfg)?;
+
+    if has_info {
+        if riff_info == RiffInfo::Sync {
+            copy_tag_to_info(&tag, &mut riff);
+        } else {
+            riff.clear();
+        }
+        log::debug!("{filename} :: INFO = {:?}", riff.info_items());
+    }
+
+    // Write tags to file - unless we're on a dry run.
+    if cfg.execution.dry_run.unwrap_or(true) {
+        processed_ok = true;
+    } else {
+        match write_tags(
+            filename,
+            &tag,
+            cfg.id3_version_setting()?,
+            has_info.then_some(&riff),
+        ) {
+            Ok(()) => {
+                processed_ok = true;
+                log::info!("{filename}  ✓");
+            }
+            Err(err) => {
+                if cfg.execution.stop_on_error.unwrap_or(false) {
+                    return Err(err).with_context(|| format!("Unable to save {filename}"));
+                }
+                log::error!("{filename}: Failed to write {file_type} tags: {err:#}");
+            }
+        }
+    }
+
+    // Rename file
+    if cfg.rename_file.is_some() {
+        match mp3::rename_file(filename, file_type, cfg, &tag) {
+            Ok(()) => {}
+            Err(err) => {
+                log::error!("Failed to rename {filename}: {err}");
+                processed_ok = false;
+            }
+        }
+    }
+
+    // return safely
+    Ok(processed_ok)
+}
+
+/// Reads the existing values from the file, using the same keys as for MP3 files.
+/// Values that are only in the `LIST/INFO` chunk of a WAV file are included.
+pub fn read_values(filename: &str, file_type: FileTypes) -> Result<HashMap<String, String>> {
+    let mut values = mp3::existing_values(&read_tag(filename)?);
+
+    if file_type == FileTypes::Wav {
+        for (key, value) in info_values(&RiffFile::read_from_path(filename)?) {
+            values.entry(key).or_insert(value);
+        }
+    }
+
+    // Return safely
+    Ok(values)
+}
+
+/// Reads the front and back covers from the ID3 tag in the file.
+pub fn read_covers(filename: &str) -> Result<Vec<(CoverType, Vec<u8>)>> {
+    Ok(mp3::existing_covers(&read_tag(filename)?))
+}
+
+/// Reads the ID3 tag from the file. Files without one get an empty tag.
+/// The `id3` crate finds the `ID3 ` chunk in WAV and AIFF files itself.
+fn read_tag(filename: &str) -> Result<Tag> {
+    match Tag::read_from_path(filename) {
+        Ok(tag) => Ok(tag),
+        Err(err) if matches!(err.kind, ErrorKind::NoTag) => {
+            log::debug!("No ID3 tag found in {filename}. Creating a new one.");
+            Ok(Tag::new())
+        }
+        Err(err) => Err(err).with_context(|| format!("Unable to read the ID3 tag in {filename}")),
+    }
+}
+
+/// Writes the ID3 tag to the file as the given `ID3v2` version, followed by the `LIST/INFO` chunk if there is one to write.
+fn write_tags(
+    filename: &str,
+    tag: &Tag,
+    version: Id3Version,
+    riff: Option<&RiffFile>,
+) -> Result<()> {
+    id3_versions::tag_for_version(tag, version).write_to_path(filename, version.version())?;
+
+    if let Some(riff) = riff {
+        riff.save()?;
+    }
+
+    // Return safely
+    Ok(())
+}
+
+/// Gets the values in the `LIST/INFO` chunk, keyed on the ID3 frame that holds the same value.
+/// A track number such as "3/12" is split into `TRCK` and `TRCK-T`.
+fn info_values(riff: &RiffFile) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+
+    for (id, frame) in RIFF_INFO_FRAMES {
+        let Some(value) = riff.get(id).map(str::trim).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+
+        if let Some((track, total)) = value.split_once('/').filter(|_| frame == "TRCK") {
+            values.insert("TRCK".to_string(), track.trim().to_string());
+            values.insert("TRCK-T".to_string(), total.trim().to_string());
+        } else {
+            values.insert(frame.to_string(), value.to_string());
+        }
+    }
+
+    values
+}
+
+/// Copies the values that are in the `LIST/INFO` chunk but not in the ID3 tag into the tag.
+fn copy_info_to_tag(riff: &RiffFile, tag: &mut Tag) {
+    let existing = mp3::existing_values(tag);
+
+    for (key, value) in info_values(riff) {
+        if existing.contains_key(&key) {
+            continue;
+        }
+        log::debug!("Copying {key} = {value} from the INFO chunk.");
+
+        match key.as_str() {
+            "COMM" => mp3::set_comment(tag, &value),
+            "TRCK" | "TRCK-T" => match value.parse::<u32>() {
+                Ok(n) if key == "TRCK" => tag.set_track(n),
+                Ok(n) => tag.set_total_tracks(n),
+                Err(err) => log::warn!("Unable to copy the track number {value}: {err}"),
+            },
+            _ => tag.set_text(key, value),
+        }
+    }
+}
+
+/// Writes the ID3 values to the `LIST/INFO` chunk. Items for values that aren't in the tag are removed,
+/// and items that have no ID3 equivalent are kept as-is. Fields with more than one value are joined with "; ",
+/// and the track number is written as eg. "3/12" if there is a track count.
+fn copy_tag_to_info(tag: &Tag, riff: &mut RiffFile) {
+    let values = mp3::existing_values(tag);
+
+    for (id, frame) in RIFF_INFO_FRAMES {
+        match values.get(frame) {
+            Some(value) => riff.set(id, &tags::display_value(value)),
+            None => riff.remove(id),
+        }
+    }
+
+    if let (Some(track), Some(total)) = (values.get("TRCK"), values.get("TRCK-T")) {
+        riff.set("ITRK", &format!("{track}/{total}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Tests the `riff_info` settings.
+    fn test_riff_info_from_str() {
+        assert_eq!(RiffInfo::from_str("sync").unwrap(), RiffInfo::Sync);
+        assert_eq!(RiffInfo::from_str("Keep").unwrap(), RiffInfo::Keep);
+        assert_eq!(RiffInfo::from_str("REMOVE").unwrap(), RiffInfo::Remove);
+        assert!(RiffInfo::from_str("both").is_err());
+        assert_eq!(RiffInfo::default(), RiffInfo::Sync);
+    }
+}
@@ -0,0 +1,563 @@
+//! Keeps a journal of the tag values and file names changed by a run of `id3tag`, so the changes can be undone
+//! with `--undo`.
+//!
+//! The journal is a JSON file with one entry per file. The tags are recorded using the generic (Vorbis comment style)
+//! names, eg. `ALBUMARTIST`, so they can be set again the same way as with `--set`. The front and back covers are
+//! recorded by their size and SHA-256 hash, and the old images are written to a folder next to the journal file,
+//! eg. `retag.covers/` for `retag.json`, so they can be set again too.
+//!
+//! Dry runs fill in the journal with the changes that would be made, and show it as a preview instead of writing it.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thousands::Separable;
+
+use crate::default_values::DefaultValues;
+use crate::formats::{self, images::CoverType, tags};
+
+/// The folder the journals are written to if no file name is given.
+const DEFAULT_JOURNAL_FOLDER: &str = "~/.config/id3tag/journals";
+
+/// How the dry-run preview is shown.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// A table of the old and new values for each file.
+    #[default]
+    Text,
+    /// The same JSON as the journal file.
+    Json,
+}
+
+impl FromStr for DiffFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown diff format \"{s}\". Must be text or json."),
+        }
+    }
+}
+
+/// A change to the value of a tag. `None` means the file didn't have the tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagChange {
+    /// The generic tag name, eg. `ALBUMARTIST`.
+    pub tag: String,
+
+    /// The value before the change.
+    pub old: Option<String>,
+
+    /// The value after the change.
+    pub new: Option<String>,
+}
+
+/// A change to the front or back cover. `None` means the file didn't have the cover.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverChange {
+    /// The generic tag name, ie. `PICTUREFRONT` or `PICTUREBACK`.
+    pub tag: String,
+
+    /// The cover before the change.
+    pub old: Option<CoverImage>,
+
+    /// The cover after the change.
+    pub new: Option<CoverImage>,
+}
+
+/// Identifies a cover image without storing it in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverImage {
+    /// The size of the image, in bytes.
+    pub size: usize,
+
+    /// The SHA-256 hash of the image. Also the name of the file the old image is kept in.
+    pub sha256: String,
+}
+
+impl CoverImage {
+    /// Identifies the image.
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            size: data.len(),
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+}
+
+/// The changes made to a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// The path of the file before it was processed.
+    pub path: PathBuf,
+
+    /// The path of the file after it was renamed, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_path: Option<PathBuf>,
+
+    /// The tags that were changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<TagChange>,
+
+    /// The covers that were changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub covers: Vec<CoverChange>,
+}
+
+impl JournalEntry {
+    /// The path of the file now, ie. the new path if the file was renamed.
+    pub fn current_path(&self) -> &Path {
+        self.new_path.as_deref().unwrap_or(&self.path)
+    }
+}
+
+/// The contents of the journal file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    /// The changes for each file, in path order.
+    entries: Vec<JournalEntry>,
+}
+
+/// The journal for a run of `id3tag`. Files are processed in parallel, so the entries are behind a mutex.
+#[derive(Debug, Default)]
+pub struct Journal {
+    /// The entries, keyed on the absolute path of the file before it was processed.
+    entries: Mutex<HashMap<PathBuf, JournalEntry>>,
+
+    /// The old cover images, keyed on their hash. Written next to the journal file when it is saved.
+    images: Mutex<HashMap<String, Vec<u8>>>,
+
+    /// Set if the journal is only shown as the dry-run preview, so the old images aren't needed.
+    preview: bool,
+}
+
+impl Journal {
+    /// Starts a journal for the dry-run preview. The old cover images aren't kept.
+    pub fn for_preview() -> Self {
+        Self {
+            preview: true,
+            ..Self::default()
+        }
+    }
+
+    /// Records the tags that differ between the old and new values of the file.
+    /// The values are keyed on the generic tag names.
+    pub fn record_changes(
+        &self,
+        filename: &str,
+        old: &HashMap<String, String>,
+        new: &HashMap<String, String>,
+    ) {
+        let changes = changes(old, new);
+        if changes.is_empty() {
+            return;
+        }
+        log::debug!("{filename} :: Journal changes = {changes:?}");
+        self.update(filename, |entry| entry.changes = changes);
+    }
+
+    /// Records the front and back covers that differ between the old and new covers of the file,
+    /// and keeps the old images so they can be written with the journal, unless this is only the preview.
+    pub fn record_covers(
+        &self,
+        filename: &str,
+        old: &[(CoverType, Vec<u8>)],
+        new: &[(CoverType, Vec<u8>)],
+    ) {
+        let mut covers = Vec::new();
+        for cover_type in [CoverType::Front, CoverType::Back] {
+            let old_data = find_cover(old, cover_type);
+            let old_image = old_data.map(CoverImage::new);
+            let new_image = find_cover(new, cover_type).map(CoverImage::new);
+            if old_image == new_image {
+                continue;
+            }
+
+            if let (Some(data), Some(image)) = (old_data, &old_image) {
+                if !self.preview {
+                    lock(&self.images)
+                        .entry(image.sha256.clone())
+                        .or_insert_with(|| data.to_vec());
+                }
+            }
+            covers.push(CoverChange {
+                tag: cover_type.tag_name().to_string(),
+                old: old_image,
+                new: new_image,
+            });
+        }
+        if covers.is_empty() {
+            return;
+        }
+        log::debug!("{filename} :: Journal cover changes = {covers:?}");
+        self.update(filename, |entry| entry.covers = covers);
+    }
+
+    /// Records that the file was renamed.
+    pub fn record_rename(&self, filename: &str, new_filename: &str) {
+        let new_path = absolute(new_filename);
+        self.update(filename, |entry| entry.new_path = Some(new_path));
+    }
+
+    /// Returns the path of the file now, ie. the new name if the file has been renamed.
+    pub fn current_path(&self, filename: &str) -> String {
+        lock(&self.entries)
+            .get(&absolute(filename))
+            .and_then(|entry| entry.new_path.as_ref())
+            .map_or_else(|| filename.to_string(), |p| p.to_string_lossy().to_string())
+    }
+
+    /// Writes the journal to the file, unless nothing was changed. The old covers are written to the folder
+    /// returned by `covers_folder` first, so the journal never refers to an image that isn't there.
+    ///
+    /// **Returns** the number of files in the journal.
+    pub fn save(&self, filename: &str) -> Result<usize> {
+        let entries = self.sorted_entries();
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(folder) = Path::new(filename).parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let old_images: BTreeSet<&String> = entries
+            .iter()
+            .flat_map(|entry| &entry.covers)
+            .filter_map(|cover| cover.old.as_ref().map(|image| &image.sha256))
+            .collect();
+        if !old_images.is_empty() {
+            let folder = covers_folder(filename);
+            fs::create_dir_all(&folder)?;
+            let images = lock(&self.images);
+            for sha256 in old_images {
+                if let Some(data) = images.get(sha256) {
+                    fs::write(folder.join(sha256), data)?;
+                }
+            }
+        }
+
+        let count = entries.len();
+        let json = serde_json::to_string_pretty(&JournalFile { entries })?;
+        fs::write(filename, json)?;
+
+        // Return safely
+        Ok(count)
+    }
+
+    /// Prints the changes that would be made by a dry run, either as a table for each file or as JSON.
+    pub fn print_preview(&self, format: DiffFormat) -> Result<()> {
+        let entries = self.sorted_entries();
+        if format == DiffFormat::Json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&JournalFile { entries })?
+            );
+        } else if entries.is_empty() {
+            println!("No changes.");
+        } else {
+            for entry in &entries {
+                print!("{}", preview_text(entry));
+            }
+        }
+
+        // Return safely
+        Ok(())
+    }
+
+    /// Reads the entries from a journal file.
+    pub fn load(filename: &str) -> Result<Vec<JournalEntry>> {
+        let json = fs::read_to_string(filename)
+            .with_context(|| format!("Unable to read journal {filename}"))?;
+        let journal: JournalFile = serde_json::from_str(&json)
+            .with_context(|| format!("Unable to parse journal {filename}"))?;
+
+        Ok(journal.entries)
+    }
+
+    /// Returns a copy of the entries, sorted by path.
+    fn sorted_entries(&self) -> Vec<JournalEntry> {
+        let mut entries: Vec<JournalEntry> = lock(&self.entries).values().cloned().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+
+    /// Updates the entry for the file, adding it if needed.
+    fn update(&self, filename: &str, change: impl FnOnce(&mut JournalEntry)) {
+        let path = absolute(filename);
+        let mut entries = lock(&self.entries);
+        let entry = entries.entry(path.clone()).or_insert_with(|| JournalEntry {
+            path,
+            ..JournalEntry::default()
+        });
+        change(entry);
+    }
+}
+
+/// Locks the entries or images. A panic in another thread doesn't stop the journal from being written.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the folder the old covers are written to, ie. the journal file name without the extension
+/// and with `.covers` added.
+pub fn covers_folder(filename: &str) -> PathBuf {
+    Path::new(filename).with_extension("covers")
+}
+
+/// Finds the first cover of the given type.
+fn find_cover(covers: &[(CoverType, Vec<u8>)], cover_type: CoverType) -> Option<&[u8]> {
+    covers
+        .iter()
+        .find(|(t, _)| *t == cover_type)
+        .map(|(_, data)| data.as_slice())
+}
+
+/// Returns the name of a new journal file in `~/.config/id3tag/journals`, based on the current time.
+pub fn default_filename() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let folder = shellexpand::tilde(DEFAULT_JOURNAL_FOLDER);
+
+    format!("{folder}/id3tag-{secs}-{}.json", std::process::id())
+}
+
+/// Finds the tags that differ between the old and new values, sorted by tag name.
+fn changes(old: &HashMap<String, String>, new: &HashMap<String, String>) -> Vec<TagChange> {
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|tag| old.get(*tag) != new.get(*tag))
+        .map(|tag| TagChange {
+            tag: tag.clone(),
+            old: old.get(tag).cloned(),
+            new: new.get(tag).cloned(),
+        })
+        .collect()
+}
+
+/// Formats the changes to a file as a table of old → new values, followed by the covers and the new name if the file
+/// is renamed. Fields with more than one value are joined with "; ", and covers are shown by size and hash.
+fn preview_text(entry: &JournalEntry) -> String {
+    let value = |v: &Option<String>| {
+        v.as_deref()
+            .map_or("(none)".to_string(), tags::display_value)
+    };
+    let cover = |c: &Option<CoverImage>| {
+        c.as_ref().map_or("(none)".to_string(), |image| {
+            format!(
+                "{} bytes ({})",
+                image.size.separate_with_commas(),
+                image.sha256.get(..8).unwrap_or(&image.sha256)
+            )
+        })
+    };
+    let rename = entry.new_path.as_ref().map(|new_path| {
+        (
+            "File name",
+            display_path(&entry.path),
+            display_path(new_path),
+        )
+    });
+    let rows: Vec<(&str, String, String)> = entry
+        .changes
+        .iter()
+        .map(|c| (c.tag.as_str(), value(&c.old), value(&c.new)))
+        .chain(
+            entry
+                .covers
+                .iter()
+                .map(|c| (c.tag.as_str(), cover(&c.old), cover(&c.new))),
+        )
+        .chain(rename)
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut text = format!("{}\n", display_path(&entry.path));
+    for (name, old, new) in rows {
+        let _ = writeln!(text, "  {name:<width$}  {old} → {new}");
+    }
+    text
+}
+
+/// Shows the path relative to the current directory if it is inside it.
+fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the absolute path of the file, without resolving symbolic links.
+fn absolute(filename: &str) -> PathBuf {
+    std::path::absolute(filename).unwrap_or_else(|_| PathBuf::from(filename))
+}
+
+/// Undoes the changes in the journal: the old tag values and covers are set again, and renamed files get their old
+/// names back.
+/// Tags that were changed again after the journal was written are restored anyway, with a warning.
+pub fn undo(filename: &str, cfg: &DefaultValues) -> Result<()> {
+    let entries = Journal::load(filename)?;
+    log::debug!("Undoing {} file(s) from {filename}", entries.len());
+
+    let covers = covers_folder(filename);
+    let mut undone = 0;
+    for entry in entries.iter().rev() {
+        match undo_entry(entry, &covers, cfg) {
+            Ok(()) => undone += 1,
+            Err(err) => {
+                if cfg.execution.stop_on_error.unwrap_or(true) {
+                    return Err(err);
+                }
+                log::error!("{err:#}");
+            }
+        }
+    }
+
+    log::info!(
+        "Undid the changes to {undone} of {} file(s).",
+        entries.len()
+    );
+
+    // Return safely
+    Ok(())
+}
+
+/// Restores the tag values, covers and the name of a single file. The old covers are read from the `covers` folder.
+fn undo_entry(entry: &JournalEntry, covers: &Path, cfg: &DefaultValues) -> Result<()> {
+    let current = entry.current_path().to_string_lossy().to_string();
+    let original = entry.path.to_string_lossy().to_string();
+    if !entry.current_path().exists() {
+        bail!("Unable to undo the changes to {original}: {current} not found.");
+    }
+
+    if !entry.changes.is_empty() || !entry.covers.is_empty() {
+        let file_type = common::get_file_type(&current)?;
+        formats::restore_values(&current, file_type, entry, covers, cfg)
+            .with_context(|| format!("Unable to restore the tags in {current}"))?;
+    }
+
+    if entry.new_path.is_some() {
+        if entry.path.exists() {
+            bail!("Unable to rename {current} back to {original}: the file already exists.");
+        }
+        if cfg.execution.dry_run.unwrap_or(true) {
+            log::info!("dr: {current} --> {original}");
+        } else {
+            fs::rename(&current, &entry.path)
+                .with_context(|| format!("Unable to rename {current} back to {original}"))?;
+            log::debug!("{current} --> {original}");
+        }
+    }
+
+    // Return safely
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::test_files::TestFiles;
+
+    #[test]
+    /// Tests the `diff_format` settings.
+    fn test_diff_format_from_str() {
+        assert_eq!(DiffFormat::from_str("text").unwrap(), DiffFormat::Text);
+        assert_eq!(DiffFormat::from_str("JSON").unwrap(), DiffFormat::Json);
+        assert!(DiffFormat::from_str("yaml").is_err());
+        assert_eq!(DiffFormat::default(), DiffFormat::Text);
+    }
+
+    #[test]
+    /// Tests recording changes and renames, and reading the journal back.
+    fn test_journal() {
+        let old = HashMap::from([
+            ("ARTIST".to_string(), "Madonna".to_string()),
+            ("TITLE".to_string(), "Vogue".to_string()),
+            ("GENRE".to_string(), "Pop".to_string()),
+        ]);
+        let new = HashMap::from([
+            ("ARTIST".to_string(), "Madonna".to_string()),
+            ("TITLE".to_string(), "Frozen".to_string()),
+            ("ALBUM".to_string(), "Ray of Light".to_string()),
+        ]);
+
+        let journal = Journal::default();
+        journal.record_changes("music/01.mp3", &old, &new);
+        journal.record_changes("music/02.mp3", &old, &old);
+        assert_eq!(journal.current_path("music/01.mp3"), "music/01.mp3");
+        journal.record_rename("music/01.mp3", "music/01 Frozen.mp3");
+        assert!(journal
+            .current_path("music/01.mp3")
+            .ends_with("music/01 Frozen.mp3"));
+
+        let files = TestFiles::new();
+        let filename = files.path("journal.json").to_string_lossy().to_string();
+        assert_eq!(journal.save(&filename).unwrap(), 1);
+
+        let entries = Journal::load(&filename).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].path.is_absolute());
+        assert!(entries[0].path.ends_with("music/01.mp3"));
+        assert!(entries[0].current_path().ends_with("music/01 Frozen.mp3"));
+        let change = |tag: &str, old: Option<&str>, new: Option<&str>| TagChange {
+            tag: tag.to_string(),
+            old: old.map(ToString::to_string),
+            new: new.map(ToString::to_string),
+        };
+        assert_eq!(
+            entries[0].changes,
+            vec![
+                change("ALBUM", None, Some("Ray of Light")),
+                change("GENRE", Some("Pop"), None),
+                change("TITLE", Some("Vogue"), Some("Frozen")),
+            ]
+        );
+
+        // Nothing to write
+        assert_eq!(Journal::default().save(&filename).unwrap(), 0);
+
+        // The preview shows one row per change, and the new name last
+        let preview = preview_text(&entries[0]);
+        let lines: Vec<&str> = preview.lines().collect();
+        assert!(lines[0].ends_with("music/01.mp3"));
+        assert_eq!(lines[1], "  ALBUM      (none) → Ray of Light");
+        assert_eq!(lines[2], "  GENRE      Pop → (none)");
+        assert_eq!(lines[3], "  TITLE      Vogue → Frozen");
+        assert!(lines[4].starts_with("  File name  "));
+        assert!(lines[4].ends_with("music/01 Frozen.mp3"));
+
+        // The preview shows covers by size and the start of the hash
+        let preview = Journal::for_preview();
+        let cover = vec![0_u8; 1234];
+        preview.record_covers("music/01.mp3", &[(CoverType::Front, cover.clone())], &[]);
+        preview.record_covers("music/02.mp3", &[(CoverType::Back, cover)], &[]);
+        assert!(lock(&preview.images).is_empty());
+        let entries = preview.sorted_entries();
+        let texts: Vec<String> = entries.iter().map(preview_text).collect();
+        let hash = CoverImage::new(&[0_u8; 1234]).sha256;
+        assert!(texts[0].ends_with(&format!(
+            "  PICTUREFRONT  1,234 bytes ({}) → (none)\n",
+            &hash[..8]
+        )));
+        assert!(texts[1].contains("  PICTUREBACK  1,234 bytes"));
+    }
+}
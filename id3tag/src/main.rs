@@ -3,18 +3,22 @@
 //! While updating individual tags (such as track names) is supported, it's not
 //! easy to do this using this application.
 //!
-//! In the future this application will endeavour to support reading tags from CSV files,
-//! moving and renaming files based on tags, etc.
+//! Tags can also be read from a CSV file, such as one written by `id3export`, using `--from-csv`,
+//! or from a CUE sheet using `--cue`.
 #![warn(clippy::pedantic)]
 #![forbid(unsafe_code)]
 
 use anyhow::{bail, Result};
 use clap::ArgMatches;
-use std::time::Instant;
+use std::{path::Path, sync::Arc, time::Instant};
 
 // Local modules
+mod csv_import;
+mod cue_sheet;
 mod default_values;
+mod directory_config;
 mod formats;
+mod journal;
 mod rename_file;
 
 use crate::default_values::DefaultValues;
@@ -40,7 +44,7 @@ fn run() -> Result<()> {
     }
 
     // Build the config -- read the CLI arguments and the config file if one is provided.
-    let config = DefaultValues::build_config(&cli)?;
+    let mut config = DefaultValues::build_config(&cli)?;
 
     // Configure logging
     let logging_config_filename = get_logging_config_filename(&cli, &config);
@@ -53,23 +57,50 @@ fn run() -> Result<()> {
         log::info!("Dry-run starting.");
     }
 
+    // Undo the changes in a journal instead of processing any files
+    if let Some(filename) = cli.get_one::<String>("undo") {
+        return journal::undo(&shellexpand::tilde(filename), &config);
+    }
+
     // Initialize counters for total files, skipped and processed.
     // let counts = Arc::new(Mutex::new(shared::Counts::default()));
 
-    // Expand glob patterns and create a list of files to process
-    let filenames = common::expand_file_args(
+    // Expand glob patterns and create a list of files to process.
+    // If no files are given, use the ones listed in the `--from-csv` file.
+    let mut filenames = common::expand_file_args(
         cli.get_many::<String>("files")
             .unwrap_or_default()
             .map(String::as_str),
     );
+    if filenames.is_empty() {
+        if let Some(csv_tags) = &config.csv_tags {
+            filenames = csv_tags.paths().to_vec();
+        }
+    }
+
+    // When copying tags, folders are expanded to the music files in them, eg. `--copy-tags-from flac/ mp3/`
+    if config.copy_tags_from.is_some() {
+        filenames = expand_folders(filenames)?;
+    }
     let file_count = filenames.len();
 
+    // Number the tracks in each folder before processing them one by one.
+    if config.tags.auto_track_number.unwrap_or(false) {
+        let sort_by = config
+            .tags
+            .auto_track_sort
+            .as_deref()
+            .unwrap_or(formats::auto_number::SORT_BY_NAME);
+        let track_numbers = formats::auto_number::track_numbers(&filenames, sort_by);
+        config.track_numbers = Some(Arc::new(track_numbers));
+    }
+
     for file in &filenames {
         log::trace!("file: {file:?}");
     }
 
     // Process things - uses single threaded mode if we can't figure it out. Better safe than sorry.
-    let res_vec: Vec<bool> = if config.execution.single_thread.unwrap_or(true) {
+    let res_vec: Vec<Option<bool>> = if config.execution.single_thread.unwrap_or(true) {
         filenames
             .iter()
             .map(|filename| process_file(filename, &cli, &config))
@@ -83,35 +114,26 @@ fn run() -> Result<()> {
 
     log::trace!("res_vec = {res_vec:?}");
 
-    // Print summary information
-    if config.execution.print_summary.unwrap_or(false) {
-        let mut processed = 0;
-        let mut skipped = 0;
-
-        for res_ok in res_vec {
-            if res_ok {
-                processed += 1;
-            } else {
-                skipped += 1;
-            }
+    // Show what a dry run would change, or write the undo journal
+    if let Some(journal) = config
+        .journal_entries
+        .as_ref()
+        .filter(|_| config.shows_preview())
+    {
+        journal.print_preview(config.diff_format_setting()?)?;
+    } else if let (Some(journal), Some(filename)) = (&config.journal_entries, &config.journal) {
+        match journal.save(filename) {
+            Ok(0) => log::debug!("Nothing changed. No journal written."),
+            Ok(count) => log::info!(
+                "Changes to {count} file(s) written to {filename}. Use id3tag --undo {filename} to undo them."
+            ),
+            Err(err) => log::error!("Unable to write the journal {filename}: {err:#}"),
         }
+    }
 
-        log::info!("   ");
-        log::info!(
-            "Files examined:              {:>5}",
-            file_count.separate_with_commas()
-        );
-        log::info!(
-            "   Processed:                {:>5}",
-            processed.separate_with_commas()
-        );
-        log::info!(
-            "   Skipped due to errors:    {:>5}",
-            skipped.separate_with_commas()
-        );
-        let elapsed = now.elapsed();
-        log::debug!("elapsed = {elapsed:?}");
-        log::info!("Time elapsed:{:>21}", human_duration(&elapsed));
+    // Print summary information
+    if config.execution.print_summary.unwrap_or(false) {
+        print_summary(file_count, &res_vec, &config, now);
     }
 
     // Everything is a-okay in the end
@@ -132,19 +154,93 @@ fn main() {
     });
 }
 
-/// Processes the file based on the filename
-fn process_file(filename: &str, cli_args: &ArgMatches, config: &DefaultValues) -> bool {
+/// Processes the file based on the filename. Returns `None` if the file doesn't match the `--where` filter.
+fn process_file(filename: &str, cli_args: &ArgMatches, config: &DefaultValues) -> Option<bool> {
     log::debug!("----------- NEW FILE ---------");
     log::debug!("process_file::filename = {filename}");
 
     let file_type = common::get_file_type(filename).unwrap_or(common::FileTypes::Unknown);
 
-    let res = formats::process_file(file_type, filename, config, cli_args).unwrap_or(false);
+    // The tags read for the filter are used for the undo journal too
+    let mut existing = None;
+    if let Some(filter) = &config.file_filter {
+        match formats::ExistingTags::read(filename, file_type) {
+            Ok(tags) if tags.matches(file_type, filter) => existing = Some(tags),
+            Ok(_) => {
+                log::debug!("{filename} doesn't match the filter. Skipping.");
+                return None;
+            }
+            Err(err) => {
+                log::error!("Unable to check {filename} against the filter: {err:#}");
+                return Some(false);
+            }
+        }
+    }
+
+    let res =
+        formats::process_file(file_type, filename, config, cli_args, existing).unwrap_or(false);
 
     log::debug!("process_file result = {res}");
 
     // return safely
-    res
+    Some(res)
+}
+
+/// Prints the number of files processed, skipped due to errors and not matching the `--where` filter.
+fn print_summary(
+    file_count: usize,
+    res_vec: &[Option<bool>],
+    config: &DefaultValues,
+    now: Instant,
+) {
+    let mut processed = 0;
+    let mut skipped = 0;
+    let mut filtered = 0;
+
+    for res in res_vec {
+        match res {
+            Some(true) => processed += 1,
+            Some(false) => skipped += 1,
+            None => filtered += 1,
+        }
+    }
+
+    log::info!("   ");
+    log::info!(
+        "Files examined:              {:>5}",
+        file_count.separate_with_commas()
+    );
+    log::info!(
+        "   Processed:                {:>5}",
+        processed.separate_with_commas()
+    );
+    log::info!(
+        "   Skipped due to errors:    {:>5}",
+        skipped.separate_with_commas()
+    );
+    if config.file_filter.is_some() {
+        log::info!(
+            "   Not matching the filter:  {:>5}",
+            filtered.separate_with_commas()
+        );
+    }
+    let elapsed = now.elapsed();
+    log::debug!("elapsed = {elapsed:?}");
+    log::info!("Time elapsed:{:>21}", human_duration(&elapsed));
+}
+
+/// Replaces any folders in the list with the music files in them.
+fn expand_folders(filenames: Vec<String>) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for filename in filenames {
+        if Path::new(&filename).is_dir() {
+            expanded.extend(formats::copy_tags::music_files(Path::new(&filename))?);
+        } else {
+            expanded.push(filename);
+        }
+    }
+
+    Ok(expanded)
 }
 
 /// Gets the file name for the logging config.
@@ -105,10 +105,18 @@ pub fn rename_file(
     // Perform the actual rename and check the outcome
     if config.execution.dry_run.unwrap_or(true) {
         log::debug!("dr: {filename} --> {}", new_path.display());
+        if let Some(journal) = &config.journal_entries {
+            journal.record_rename(filename, &npl);
+        }
     } else {
         // Get parent dir
         match std::fs::rename(filename, &new_path) {
-            Ok(()) => log::debug!("{filename} --> {npl}"),
+            Ok(()) => {
+                log::debug!("{filename} --> {npl}");
+                if let Some(journal) = &config.journal_entries {
+                    journal.record_rename(filename, &npl);
+                }
+            }
             Err(err) => {
                 if config.execution.stop_on_error.unwrap_or(true) {
                     return Err(err)